@@ -15,6 +15,12 @@ pub struct CliArgs {
     // Replication info
     #[arg(long)]
     replicaof: Option<String>,
+    /// The maximum size in bytes of a client's pending replies, 0 for no limit
+    #[arg(long)]
+    client_output_buffer_limit: Option<usize>,
+    /// The number of databases
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    databases: Option<u16>,
 }
 
 impl From<CliArgs> for Configuration {
@@ -24,6 +30,8 @@ impl From<CliArgs> for Configuration {
             value.dbfilename,
             value.port.unwrap_or(6379),
             value.replicaof,
+            value.client_output_buffer_limit.unwrap_or(0),
+            value.databases.unwrap_or(16) as usize,
         )
     }
 }
@@ -1,5 +1,7 @@
-use crate::redis::core::{ReadResp, WriteResp};
-use crate::redis::reader::MessageReaderError;
+use crate::redis::connection::Connection;
+use crate::redis::core::WriteResp;
+use crate::redis::reader::{MessageReader, MessageReaderError};
+use mio::event::Event;
 use mio::net::TcpStream;
 use mio::{Events, Interest, Poll, Token};
 use std::io::{self};
@@ -9,41 +11,77 @@ use std::time::Duration;
 const CLIENT_TOKEN: Token = Token(0);
 
 pub struct TcpClient {
-    stream: TcpStream,
+    connection: Connection,
     poll: Poll,
 }
 
 impl TcpClient {
     pub fn connect(addr: SocketAddr) -> io::Result<Self> {
-        let mut stream = TcpStream::connect(addr)?;
+        let mut connection = Connection::new(TcpStream::connect(addr)?, 0);
         let poll = Poll::new()?;
 
         poll.registry().register(
-            &mut stream,
+            &mut connection,
             CLIENT_TOKEN,
             Interest::READABLE | Interest::WRITABLE,
         )?;
 
-        Ok(Self { stream, poll })
+        Ok(Self { connection, poll })
     }
 
-    pub fn send(&mut self, data: &[Option<impl AsRef<str>>]) -> io::Result<()> {
-        self.stream.write_array(data)
+    pub fn send(&mut self, data: &[Option<impl AsRef<[u8]>>]) -> io::Result<()> {
+        self.connection.write_array(data)?;
+        loop {
+            self.connection.send_pending()?;
+            if !self.connection.has_pending_output() {
+                return Ok(());
+            }
+            self.wait_for(|event| event.is_writable())?;
+        }
+    }
+
+    pub fn receive(&mut self) -> Result<Vec<Vec<u8>>, MessageReaderError> {
+        self.receive_with(MessageReader::next_message)
+    }
+
+    /// Stops polling the connection on its own so that it can join the server's event loop,
+    /// keeping what was received and not parsed yet.
+    pub fn into_connection(mut self) -> io::Result<Connection> {
+        self.poll.registry().deregister(&mut self.connection)?;
+        Ok(self.connection)
     }
 
-    pub fn receive(&mut self) -> Result<Vec<String>, MessageReaderError> {
-        self.wait_for_readable()?;
-        self.stream.read_resp()
+    /// Receives the RDB file a master sends after FULLRESYNC.
+    pub fn receive_rdb(&mut self) -> Result<Vec<u8>, MessageReaderError> {
+        self.receive_with(MessageReader::next_rdb)
+    }
+
+    /// Reads until `next` takes a complete frame out of the buffer. Frames are taken one at a
+    /// time since the RDB file following FULLRESYNC is not a regular RESP frame.
+    fn receive_with<T>(
+        &mut self,
+        next: impl Fn(&mut MessageReader) -> Result<Option<T>, MessageReaderError>,
+    ) -> Result<T, MessageReaderError> {
+        loop {
+            self.connection.receive()?;
+            if let Some(frame) = next(self.connection.reader_mut())? {
+                return Ok(frame);
+            }
+            if self.connection.is_closed() {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            self.wait_for(|event| event.is_readable())?;
+        }
     }
 
-    fn wait_for_readable(&mut self) -> io::Result<()> {
+    fn wait_for(&mut self, ready: impl Fn(&Event) -> bool) -> io::Result<()> {
         let mut events = Events::with_capacity(1);
 
         loop {
             self.poll.poll(&mut events, Some(Duration::from_secs(5)))?;
 
             for event in events.iter() {
-                if event.token() == CLIENT_TOKEN && event.is_readable() {
+                if event.token() == CLIENT_TOKEN && ready(event) {
                     return Ok(());
                 }
             }
@@ -0,0 +1,148 @@
+use crate::redis::reader::MessageReader;
+use mio::event::Source;
+use mio::net::TcpStream;
+use mio::{Interest, Registry, Token};
+use std::io::{self, ErrorKind, Read, Write};
+
+const READ_CHUNK_SIZE: usize = 4096;
+
+pub struct Connection {
+    stream: TcpStream,
+    reader: MessageReader,
+    output: Vec<u8>,
+    output_limit: usize,
+    write_interest: bool,
+    closed: bool,
+}
+
+impl Connection {
+    /// Creates a connection whose pending output may grow up to `output_limit` bytes,
+    /// `0` meaning no limit.
+    pub fn new(stream: TcpStream, output_limit: usize) -> Self {
+        Self {
+            stream,
+            reader: MessageReader::default(),
+            output: Vec::new(),
+            output_limit,
+            write_interest: false,
+            closed: false,
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn has_pending_output(&self) -> bool {
+        !self.output.is_empty()
+    }
+
+    pub(super) fn reader_mut(&mut self) -> &mut MessageReader {
+        &mut self.reader
+    }
+
+    /// Drains everything the socket currently has to offer into the read buffer.
+    pub(super) fn receive(&mut self) -> io::Result<()> {
+        let mut buffer = [0u8; READ_CHUNK_SIZE];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => {
+                    self.closed = true;
+                    return Ok(());
+                }
+                Ok(n) => self.reader.extend(&buffer[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Writes as much of the output buffer as the socket accepts without blocking.
+    pub fn send_pending(&mut self) -> io::Result<()> {
+        let mut written = 0;
+        while written < self.output.len() {
+            match self.stream.write(&self.output[written..]) {
+                Ok(0) => {
+                    self.output.drain(..written);
+                    return Err(ErrorKind::WriteZero.into());
+                }
+                Ok(n) => written += n,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.output.drain(..written);
+                    return Err(e);
+                }
+            }
+        }
+        self.output.drain(..written);
+        Ok(())
+    }
+
+    /// Sends pending output and keeps write interest registered only while some of it
+    /// is still waiting for the socket to become writable.
+    pub fn flush_output(&mut self, registry: &Registry, token: Token) -> io::Result<()> {
+        self.send_pending()?;
+        let write_interest = self.has_pending_output();
+        if write_interest != self.write_interest {
+            let interest = if write_interest {
+                Interest::READABLE | Interest::WRITABLE
+            } else {
+                Interest::READABLE
+            };
+            registry.reregister(&mut self.stream, token, interest)?;
+            self.write_interest = write_interest;
+        }
+        Ok(())
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.closed {
+            return Ok(buf.len());
+        }
+        self.output.extend_from_slice(buf);
+        if self.output_limit > 0 && self.output.len() > self.output_limit {
+            log::warn!(
+                "closing client: output buffer of {} bytes exceeds the limit of {} bytes",
+                self.output.len(),
+                self.output_limit
+            );
+            self.output.clear();
+            self.closed = true;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Source for Connection {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        self.write_interest = interests.is_writable();
+        self.stream.register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        self.write_interest = interests.is_writable();
+        self.stream.reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        self.stream.deregister(registry)
+    }
+}
@@ -0,0 +1,194 @@
+use crate::redis::core::request::Request;
+use crate::redis::core::WriteResp;
+use mio::Token;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// The reply a blocked client gets when its timeout elapses before any key is ready.
+#[derive(Debug, Clone, Copy)]
+pub enum TimeoutReply {
+    NullArray,
+    NullBulk,
+}
+
+impl TimeoutReply {
+    pub fn write(&self, writer: &mut impl WriteResp) -> std::io::Result<()> {
+        match self {
+            TimeoutReply::NullArray => writer.write_null_array(),
+            TimeoutReply::NullBulk => writer.write_bulk_sting(&None::<&[u8]>),
+        }
+    }
+}
+
+/// Returned by a blocking command that has nothing to serve yet and wants to wait on `keys`.
+pub struct Block {
+    pub keys: Vec<Vec<u8>>,
+    /// How long to wait, `None` meaning forever.
+    pub timeout: Option<Duration>,
+    pub on_timeout: TimeoutReply,
+    /// The request to execute again once a key is ready when it differs from the original one,
+    /// like XREAD with `$` which must keep reading from the ID it resolved when it blocked.
+    pub request: Option<Request>,
+}
+
+impl Block {
+    pub fn new(keys: Vec<Vec<u8>>, timeout: Option<Duration>, on_timeout: TimeoutReply) -> Self {
+        Self {
+            keys,
+            timeout,
+            on_timeout,
+            request: None,
+        }
+    }
+}
+
+pub type BlockResult = std::io::Result<Option<Block>>;
+
+struct BlockedClient {
+    request: Request,
+    db: usize,
+    keys: Vec<Vec<u8>>,
+    deadline: Option<Instant>,
+    on_timeout: TimeoutReply,
+}
+
+/// Clients waiting for keys, queued per database and key in the order they blocked.
+#[derive(Default)]
+pub struct BlockedClients {
+    clients: HashMap<Token, BlockedClient>,
+    waiters: HashMap<(usize, Vec<u8>), VecDeque<Token>>,
+}
+
+impl BlockedClients {
+    pub fn block(&mut self, client: Token, db: usize, request: Request, block: Block) {
+        for key in &block.keys {
+            let waiters = self.waiters.entry((db, key.clone())).or_default();
+            if !waiters.contains(&client) {
+                waiters.push_back(client);
+            }
+        }
+        let blocked = BlockedClient {
+            request,
+            db,
+            keys: block.keys,
+            deadline: block
+                .timeout
+                .and_then(|timeout| Instant::now().checked_add(timeout)),
+            on_timeout: block.on_timeout,
+        };
+        self.clients.insert(client, blocked);
+    }
+
+    /// Removes the client from every queue and returns the request it was blocked on along with
+    /// the reply it expects on timeout.
+    pub fn unblock(&mut self, client: Token) -> Option<(Request, TimeoutReply)> {
+        let blocked = self.clients.remove(&client)?;
+        for key in blocked.keys {
+            let key = (blocked.db, key);
+            if let Some(waiters) = self.waiters.get_mut(&key) {
+                waiters.retain(|waiter| *waiter != client);
+                if waiters.is_empty() {
+                    self.waiters.remove(&key);
+                }
+            }
+        }
+        Some((blocked.request, blocked.on_timeout))
+    }
+
+    pub fn is_blocked(&self, client: Token) -> bool {
+        self.clients.contains_key(&client)
+    }
+
+    pub fn request(&self, client: Token) -> Option<&Request> {
+        self.clients.get(&client).map(|blocked| &blocked.request)
+    }
+
+    /// The clients waiting for `key` in database `db`, first come first served.
+    pub fn waiters(&self, db: usize, key: &[u8]) -> Vec<Token> {
+        self.waiters
+            .get(&(db, key.to_vec()))
+            .map(|waiters| waiters.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Every key some client waits for, along with its database.
+    pub fn keys(&self) -> Vec<(usize, Vec<u8>)> {
+        self.waiters.keys().cloned().collect()
+    }
+
+    /// How long until the earliest deadline, which is how long the event loop may sleep.
+    pub fn next_timeout(&self) -> Option<Duration> {
+        self.clients
+            .values()
+            .filter_map(|blocked| blocked.deadline)
+            .min()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// The clients whose deadline has passed, earliest first.
+    pub fn timed_out(&self) -> Vec<Token> {
+        let now = Instant::now();
+        let mut clients: Vec<_> = self
+            .clients
+            .iter()
+            .filter_map(|(client, blocked)| Some((blocked.deadline?, *client)))
+            .filter(|(deadline, _)| *deadline <= now)
+            .collect();
+        clients.sort();
+        clients.into_iter().map(|(_, client)| client).collect()
+    }
+}
+
+/// Parses a timeout given in seconds, possibly fractional, `0` meaning forever.
+pub fn parse_timeout_seconds(value: &[u8]) -> Result<Option<Duration>, &'static str> {
+    let timeout = std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse::<f64>().ok())
+        .filter(|timeout| timeout.is_finite())
+        .ok_or("timeout is not a float or out of range")?;
+    if timeout < 0.0 {
+        return Err("timeout is negative");
+    }
+    let timeout = Duration::try_from_secs_f64(timeout).map_err(|_| "timeout is out of range")?;
+    Ok(Some(timeout).filter(|timeout| !timeout.is_zero()))
+}
+
+/// Parses a timeout given in milliseconds, `0` meaning forever.
+pub fn parse_timeout_millis(value: &[u8]) -> Result<Option<Duration>, &'static str> {
+    let timeout = std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .ok_or("timeout is not an integer or out of range")?;
+    if timeout < 0 {
+        return Err("timeout is negative");
+    }
+    Ok(Some(Duration::from_millis(timeout as u64)).filter(|timeout| !timeout.is_zero()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::blocking::{Block, BlockedClients, TimeoutReply};
+    use crate::redis::core::request::Request;
+    use mio::Token;
+
+    fn block(keys: &[&[u8]]) -> Block {
+        let keys = keys.iter().map(|key| key.to_vec()).collect();
+        Block::new(keys, None, TimeoutReply::NullArray)
+    }
+
+    #[test]
+    fn test_waiters_are_served_in_order() {
+        let mut blocked = BlockedClients::default();
+        blocked.block(Token(2), 0, Request::new(vec![]), block(&[b"a", b"b"]));
+        blocked.block(Token(1), 0, Request::new(vec![]), block(&[b"b"]));
+        blocked.block(Token(3), 1, Request::new(vec![]), block(&[b"b"]));
+        assert_eq!(blocked.waiters(0, b"b"), vec![Token(2), Token(1)]);
+
+        assert!(blocked.unblock(Token(2)).is_some());
+        assert!(blocked.waiters(0, b"a").is_empty());
+        assert_eq!(blocked.waiters(0, b"b"), vec![Token(1)]);
+        assert_eq!(blocked.waiters(1, b"b"), vec![Token(3)]);
+        assert!(blocked.unblock(Token(2)).is_none());
+        assert_eq!(blocked.next_timeout(), None);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::redis::core::errors::{unknown_command, wrong_arguments};
+use crate::redis::core::request::Request;
+
+/// The number of arguments of each command, counting its name: exact when positive, a minimum
+/// when negative, as in the Redis command table.
+const ARITIES: [(&str, i32); 153] = [
+    ("append", 3),
+    ("blmove", 6),
+    ("blpop", -3),
+    ("brpop", -3),
+    ("bzpopmax", -3),
+    ("bzpopmin", -3),
+    ("config", -2),
+    ("copy", -3),
+    ("dbsize", 1),
+    ("decr", 2),
+    ("decrby", 3),
+    ("del", -2),
+    ("discard", 1),
+    ("echo", 2),
+    ("exec", 1),
+    ("exists", -2),
+    ("expire", -3),
+    ("expireat", -3),
+    ("expiretime", 2),
+    ("flushall", -1),
+    ("flushdb", -1),
+    ("get", 2),
+    ("getdel", 2),
+    ("getex", -2),
+    ("getrange", 4),
+    ("getset", 3),
+    ("hdel", -3),
+    ("hexists", 3),
+    ("hget", 3),
+    ("hgetall", 2),
+    ("hincrby", 4),
+    ("hincrbyfloat", 4),
+    ("hkeys", 2),
+    ("hlen", 2),
+    ("hmget", -3),
+    ("hrandfield", -2),
+    ("hscan", -3),
+    ("hset", -4),
+    ("hsetnx", 4),
+    ("hstrlen", 3),
+    ("hvals", 2),
+    ("incr", 2),
+    ("incrby", 3),
+    ("incrbyfloat", 3),
+    ("info", -1),
+    ("keys", 2),
+    ("lcs", -3),
+    ("lindex", 3),
+    ("linsert", 5),
+    ("llen", 2),
+    ("lmove", 5),
+    ("lpop", -2),
+    ("lpos", -3),
+    ("lpush", -3),
+    ("lrange", 4),
+    ("lrem", 4),
+    ("lset", 4),
+    ("ltrim", 4),
+    ("mget", -2),
+    ("move", 3),
+    ("mset", -3),
+    ("msetnx", -3),
+    ("multi", 1),
+    ("persist", 2),
+    ("pexpire", -3),
+    ("pexpireat", -3),
+    ("pexpiretime", 2),
+    ("ping", -1),
+    ("psetex", 4),
+    ("psubscribe", -2),
+    ("psync", -3),
+    ("pttl", 2),
+    ("publish", 3),
+    ("pubsub", -2),
+    ("punsubscribe", -1),
+    ("quit", -1),
+    ("randomkey", 1),
+    ("rename", 3),
+    ("renamenx", 3),
+    ("replconf", -1),
+    ("reset", 1),
+    ("rpop", -2),
+    ("rpush", -3),
+    ("sadd", -3),
+    ("save", 1),
+    ("scan", -2),
+    ("scard", 2),
+    ("sdiff", -2),
+    ("sdiffstore", -3),
+    ("select", 2),
+    ("set", -3),
+    ("setex", 4),
+    ("setnx", 3),
+    ("setrange", 4),
+    ("sinter", -2),
+    ("sintercard", -3),
+    ("sinterstore", -3),
+    ("sismember", 3),
+    ("smembers", 2),
+    ("smismember", -3),
+    ("smove", 4),
+    ("spop", -2),
+    ("srandmember", -2),
+    ("srem", -3),
+    ("sscan", -3),
+    ("strlen", 2),
+    ("subscribe", -2),
+    ("sunion", -2),
+    ("sunionstore", -3),
+    ("swapdb", 3),
+    ("touch", -2),
+    ("ttl", 2),
+    ("type", 2),
+    ("unlink", -2),
+    ("unsubscribe", -1),
+    ("unwatch", 1),
+    ("wait", 3),
+    ("watch", -2),
+    ("xack", -4),
+    ("xadd", -5),
+    ("xautoclaim", -6),
+    ("xclaim", -6),
+    ("xdel", -3),
+    ("xgroup", -2),
+    ("xinfo", -2),
+    ("xlen", 2),
+    ("xpending", -3),
+    ("xrange", -4),
+    ("xread", -4),
+    ("xreadgroup", -7),
+    ("xrevrange", -4),
+    ("xtrim", -4),
+    ("zadd", -4),
+    ("zcard", 2),
+    ("zcount", 4),
+    ("zdiffstore", -4),
+    ("zincrby", 4),
+    ("zinterstore", -4),
+    ("zlexcount", 4),
+    ("zmscore", -3),
+    ("zpopmax", -2),
+    ("zpopmin", -2),
+    ("zrange", -4),
+    ("zrangestore", -5),
+    ("zrank", -3),
+    ("zrem", -3),
+    ("zremrangebylex", 4),
+    ("zremrangebyrank", 4),
+    ("zremrangebyscore", 4),
+    ("zrevrank", -3),
+    ("zscan", -3),
+    ("zscore", 3),
+    ("zunionstore", -4),
+];
+
+/// The commands that may modify the dataset, which are forwarded to replicas when they did.
+const WRITE_COMMANDS: [&str; 76] = [
+    "append",
+    "blmove",
+    "blpop",
+    "brpop",
+    "bzpopmax",
+    "bzpopmin",
+    "copy",
+    "decr",
+    "decrby",
+    "del",
+    "expire",
+    "expireat",
+    "flushall",
+    "flushdb",
+    "getdel",
+    "getex",
+    "getset",
+    "hdel",
+    "hincrby",
+    "hincrbyfloat",
+    "hset",
+    "hsetnx",
+    "incr",
+    "incrby",
+    "incrbyfloat",
+    "linsert",
+    "lmove",
+    "lpop",
+    "lpush",
+    "lrem",
+    "lset",
+    "ltrim",
+    "move",
+    "mset",
+    "msetnx",
+    "persist",
+    "pexpire",
+    "pexpireat",
+    "psetex",
+    "rename",
+    "renamenx",
+    "rpop",
+    "rpush",
+    "sadd",
+    "sdiffstore",
+    "set",
+    "setex",
+    "setnx",
+    "setrange",
+    "sinterstore",
+    "smove",
+    "spop",
+    "srem",
+    "sunionstore",
+    "swapdb",
+    "unlink",
+    "xack",
+    "xadd",
+    "xautoclaim",
+    "xclaim",
+    "xdel",
+    "xgroup",
+    "xreadgroup",
+    "xtrim",
+    "zadd",
+    "zdiffstore",
+    "zincrby",
+    "zinterstore",
+    "zpopmax",
+    "zpopmin",
+    "zrangestore",
+    "zrem",
+    "zremrangebylex",
+    "zremrangebyrank",
+    "zremrangebyscore",
+    "zunionstore",
+];
+
+/// Checks that a command exists and gets a valid number of arguments, which is what can be
+/// checked before running it.
+pub fn check_command(command: &str, request: &Request) -> Result<(), String> {
+    let index = ARITIES
+        .binary_search_by(|(name, _)| name.cmp(&command))
+        .map_err(|_| unknown_command(command))?;
+    let arity = ARITIES[index].1;
+    let len = request.len() as i32;
+    if arity >= 0 && len != arity || arity < 0 && len < -arity {
+        return Err(wrong_arguments(command));
+    }
+    Ok(())
+}
+
+pub fn is_write(command: &str) -> bool {
+    WRITE_COMMANDS.binary_search(&command).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::commands::{ARITIES, WRITE_COMMANDS};
+
+    #[test]
+    fn test_commands_are_sorted() {
+        assert!(ARITIES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(WRITE_COMMANDS.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(WRITE_COMMANDS
+            .iter()
+            .all(|command| ARITIES.iter().any(|(name, _)| name == command)));
+    }
+}
@@ -5,6 +5,8 @@ pub struct Configuration {
     db_file_name: Option<String>,
     port: u16,
     replicaof: Option<String>,
+    client_output_buffer_limit: usize,
+    databases: usize,
 }
 
 impl Configuration {
@@ -13,12 +15,16 @@ impl Configuration {
         db_file_name: Option<String>,
         port: u16,
         replicaof: Option<String>,
+        client_output_buffer_limit: usize,
+        databases: usize,
     ) -> Self {
         Self {
             dir,
             db_file_name,
             port,
             replicaof,
+            client_output_buffer_limit,
+            databases,
         }
     }
 
@@ -30,6 +36,14 @@ impl Configuration {
         self.port
     }
 
+    pub fn client_output_buffer_limit(&self) -> usize {
+        self.client_output_buffer_limit
+    }
+
+    pub fn databases(&self) -> usize {
+        self.databases
+    }
+
     pub fn dir(&self) -> Option<&String> {
         self.dir.as_ref()
     }
@@ -0,0 +1,1259 @@
+use crate::redis::core::blocking::{parse_timeout_millis, Block, BlockResult, TimeoutReply};
+use crate::redis::core::errors::{wrong_arguments, NOT_INTEGER, SYNTAX_ERROR};
+use crate::redis::core::request::Request;
+use crate::redis::core::stream::{
+    parse_range_end, parse_range_start, write_entries, write_entry, INVALID_ID,
+};
+use crate::redis::core::WriteResp;
+use crate::redis::rdb::{ConsumerGroup, Database, Stream, StreamFields, StreamId};
+use chrono::Utc;
+use std::ops::Bound;
+
+const NIL: Option<&[u8]> = None;
+const OK: &str = "OK";
+const NO_KEY: &str = "The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.";
+const DEFAULT_AUTOCLAIM_COUNT: usize = 100;
+const AUTOCLAIM_ATTEMPTS_FACTOR: usize = 10;
+const DEFAULT_INFO_COUNT: usize = 10;
+
+/// Entries read from a consumer's history, whose fields are gone if the entry was deleted.
+type HistoryEntries = Vec<(StreamId, Option<StreamFields>)>;
+
+pub fn xgroup(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    let Some(subcommand) = request.get_str(1) else {
+        return writer.write_error(wrong_arguments("xgroup"));
+    };
+    let subcommand = subcommand.to_lowercase();
+    let arity_ok = match subcommand.as_str() {
+        "create" | "setid" => request.len() >= 5,
+        "destroy" => request.len() == 4,
+        "createconsumer" | "delconsumer" => request.len() == 5,
+        _ => {
+            return writer.write_error(format!(
+                "unknown subcommand '{}'. Try XGROUP HELP.",
+                request.get_str(1).unwrap()
+            ))
+        }
+    };
+    if !arity_ok {
+        return writer.write_error(wrong_arguments(&format!("xgroup|{}", subcommand)));
+    }
+
+    let key = request.get(2).unwrap();
+    let group = request.get(3).unwrap();
+    match subcommand.as_str() {
+        "create" => xgroup_create(writer, storage, request),
+        "setid" => xgroup_setid(writer, storage, request),
+        "destroy" => match storage.get_stream_mut(key) {
+            Ok(Some(stream)) => {
+                let destroyed = stream.destroy_group(group);
+                if destroyed {
+                    storage.touch(key);
+                }
+                writer.write_integer(destroyed as i64)
+            }
+            Ok(None) => writer.write_error(NO_KEY),
+            Err(e) => writer.write_error(e.to_string()),
+        },
+        _ => {
+            let consumer = request.get(4).unwrap();
+            let group = match storage.get_stream_mut(key) {
+                Ok(Some(stream)) => match stream.group_mut(group) {
+                    Some(group) => group,
+                    None => return writer.write_error(no_group_for_key(key, group)),
+                },
+                Ok(None) => return writer.write_error(NO_KEY),
+                Err(e) => return writer.write_error(e.to_string()),
+            };
+            let reply = if subcommand == "createconsumer" {
+                group.create_consumer(consumer, now()).then_some(1)
+            } else {
+                group
+                    .delete_consumer(consumer)
+                    .map(|pending| pending as i64)
+            };
+            if reply.is_some() {
+                storage.touch(key);
+            }
+            writer.write_integer(reply.unwrap_or(0))
+        }
+    }
+}
+
+fn xgroup_create(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    let mut make_stream = false;
+    let mut entries_read = None;
+    let mut index = 5;
+    while index < request.len() {
+        let option = request.get_str(index).unwrap().to_lowercase();
+        match option.as_str() {
+            "mkstream" => {
+                make_stream = true;
+                index += 1;
+            }
+            "entriesread" if index + 1 < request.len() => {
+                match parse_entries_read(request, index + 1) {
+                    Ok(value) => entries_read = value,
+                    Err(e) => return writer.write_error(e),
+                }
+                index += 2;
+            }
+            _ => return writer.write_error(SYNTAX_ERROR),
+        }
+    }
+
+    let id = request.get(4).unwrap();
+    if id != b"$" && StreamId::parse(id, 0).is_none() {
+        return writer.write_error(INVALID_ID);
+    }
+
+    let key = request.get(2).unwrap();
+    match storage.get_stream(key) {
+        Ok(Some(_)) => {}
+        Ok(None) if make_stream => {}
+        Ok(None) => return writer.write_error(NO_KEY),
+        Err(e) => return writer.write_error(e.to_string()),
+    }
+    let stream = storage.get_or_insert_stream(key).unwrap();
+    let id = parse_group_id(stream, id).unwrap();
+
+    if stream.create_group(
+        request.get(3).unwrap(),
+        ConsumerGroup::new(id, entries_read),
+    ) {
+        storage.touch(key);
+        writer.write_simple_string(OK)
+    } else {
+        writer.write_error("BUSYGROUP Consumer Group name already exists")
+    }
+}
+
+fn xgroup_setid(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    let entries_read = match request.len() {
+        5 => None,
+        7 if request
+            .get_str(5)
+            .unwrap()
+            .eq_ignore_ascii_case("entriesread") =>
+        {
+            match parse_entries_read(request, 6) {
+                Ok(value) => value,
+                Err(e) => return writer.write_error(e),
+            }
+        }
+        _ => return writer.write_error(SYNTAX_ERROR),
+    };
+
+    let key = request.get(2).unwrap();
+    let name = request.get(3).unwrap();
+    let stream = match storage.get_stream_mut(key) {
+        Ok(Some(stream)) => stream,
+        Ok(None) => return writer.write_error(NO_KEY),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let Some(id) = parse_group_id(stream, request.get(4).unwrap()) else {
+        return writer.write_error(INVALID_ID);
+    };
+    let Some(group) = stream.group_mut(name) else {
+        return writer.write_error(no_group_for_key(key, name));
+    };
+    group.last_id = id;
+    group.entries_read = entries_read;
+    storage.touch(key);
+    writer.write_simple_string(OK)
+}
+
+/// Parses the ENTRIESREAD argument, -1 standing for an unknown count.
+fn parse_entries_read(request: &Request, index: usize) -> Result<Option<u64>, String> {
+    match request.get_int(index) {
+        Some(-1) => Ok(None),
+        Some(value) if value >= 0 => Ok(Some(value as u64)),
+        Some(_) => Err("value for ENTRIESREAD must be positive or -1".to_string()),
+        None => Err(NOT_INTEGER.to_string()),
+    }
+}
+
+fn parse_group_id(stream: &Stream, value: &[u8]) -> Option<StreamId> {
+    if value == b"$" {
+        Some(stream.last_id())
+    } else {
+        StreamId::parse(value, 0)
+    }
+}
+
+pub fn xreadgroup(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> BlockResult {
+    let mut group = None;
+    let mut count = 0;
+    let mut block = None;
+    let mut no_ack = false;
+    let mut index = 1;
+    loop {
+        let Some(option) = request.get_str(index) else {
+            return writer.write_error(SYNTAX_ERROR).map(|_| None);
+        };
+        match option.to_lowercase().as_str() {
+            "group" if index + 2 < request.len() => {
+                group = Some((
+                    request.get(index + 1).unwrap(),
+                    request.get(index + 2).unwrap(),
+                ));
+                index += 3;
+            }
+            "count" if index + 1 < request.len() => {
+                match request.get_int(index + 1) {
+                    Some(value) => count = value.max(0) as usize,
+                    None => return writer.write_error(NOT_INTEGER).map(|_| None),
+                }
+                index += 2;
+            }
+            "block" if index + 1 < request.len() => {
+                match parse_timeout_millis(request.get(index + 1).unwrap()) {
+                    Ok(timeout) => block = Some(timeout),
+                    Err(e) => return writer.write_error(e).map(|_| None),
+                }
+                index += 2;
+            }
+            "noack" => {
+                no_ack = true;
+                index += 1;
+            }
+            "streams" => {
+                index += 1;
+                break;
+            }
+            _ => return writer.write_error(SYNTAX_ERROR).map(|_| None),
+        }
+    }
+
+    let Some((group, consumer)) = group else {
+        return writer
+            .write_error("Missing GROUP option for XREADGROUP")
+            .map(|_| None);
+    };
+    let arguments = request.len() - index;
+    if arguments == 0 || !arguments.is_multiple_of(2) {
+        return writer
+            .write_error(
+                "Unbalanced 'xreadgroup' list of streams: for each stream key an ID or '>' must be specified.",
+            )
+            .map(|_| None);
+    }
+
+    let streams = arguments / 2;
+    let mut reads = Vec::with_capacity(streams);
+    for offset in 0..streams {
+        let key = request.get(index + offset).unwrap();
+        let id = match request.get(index + streams + offset).unwrap() {
+            b">" => None,
+            b"$" => return writer.write_error("The $ ID is meaningless in the context of XREADGROUP: you want to read the history of this consumer by specifying a proper ID, or use the > ID to get new messages. The $ ID would just return an empty result set.").map(|_| None),
+            id => match StreamId::parse(id, 0) {
+                Some(id) => Some(id),
+                None => return writer.write_error(INVALID_ID).map(|_| None),
+            },
+        };
+        reads.push((key, id));
+    }
+
+    for (key, _) in &reads {
+        match storage.get_stream(key) {
+            Ok(Some(stream)) if stream.group(group).is_some() => {}
+            Ok(_) => {
+                return writer
+                    .write_error(format!(
+                "NOGROUP No such key '{}' or consumer group '{}' in XREADGROUP with GROUP option",
+                String::from_utf8_lossy(key),
+                String::from_utf8_lossy(group)
+            ))
+                    .map(|_| None)
+            }
+            Err(e) => return writer.write_error(e.to_string()).map(|_| None),
+        }
+    }
+
+    let now = now();
+    let mut results: Vec<(&[u8], HistoryEntries)> = Vec::new();
+    for &(key, id) in &reads {
+        let stream = storage.get_stream_mut(key).unwrap().unwrap();
+        match id {
+            None => {
+                let entries = stream.deliver(group, consumer, count, no_ack, now);
+                if !entries.is_empty() {
+                    let entries = entries
+                        .into_iter()
+                        .map(|(id, fields)| (id, Some(fields)))
+                        .collect();
+                    results.push((key, entries));
+                    storage.touch(key);
+                }
+            }
+            Some(id) => {
+                let consumer = stream
+                    .group_mut(group)
+                    .unwrap()
+                    .touch_consumer(consumer, now);
+                let take = if count == 0 { usize::MAX } else { count };
+                let pending: Vec<_> = consumer
+                    .pending
+                    .range((Bound::Excluded(id), Bound::Unbounded))
+                    .take(take)
+                    .copied()
+                    .collect();
+                let entries = pending
+                    .into_iter()
+                    .map(|id| (id, stream.get(&id).cloned()))
+                    .collect();
+                results.push((key, entries));
+            }
+        }
+    }
+
+    if results.is_empty() {
+        return match block {
+            Some(timeout) => {
+                let keys = reads.iter().map(|(key, _)| key.to_vec()).collect();
+                Ok(Some(Block::new(keys, timeout, TimeoutReply::NullArray)))
+            }
+            None => writer.write_null_array().map(|_| None),
+        };
+    }
+    writer.write_array_header(results.len())?;
+    for (key, entries) in results {
+        writer.write_array_header(2)?;
+        writer.write_bulk_sting(&Some(key))?;
+        write_optional_entries(writer, &entries)?;
+    }
+    Ok(None)
+}
+
+pub fn xack(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 4 {
+        return writer.write_error(wrong_arguments("xack"));
+    }
+
+    let mut ids = Vec::with_capacity(request.len() - 3);
+    for index in 3..request.len() {
+        match StreamId::parse(request.get(index).unwrap(), 0) {
+            Some(id) => ids.push(id),
+            None => return writer.write_error(INVALID_ID),
+        }
+    }
+
+    let key = request.get(1).unwrap();
+    match storage.get_stream_mut(key) {
+        Ok(stream) => {
+            let group = stream.and_then(|stream| stream.group_mut(request.get(2).unwrap()));
+            let acknowledged = match group {
+                Some(group) => ids.iter().filter(|id| group.remove_pending(id)).count(),
+                None => 0,
+            };
+            if acknowledged > 0 {
+                storage.touch(key);
+            }
+            writer.write_integer(acknowledged as i64)
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn xpending(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments("xpending"));
+    }
+
+    let mut index = 3;
+    let mut min_idle = 0;
+    if request
+        .get_str(index)
+        .is_some_and(|option| option.eq_ignore_ascii_case("idle"))
+    {
+        match request.get_int(index + 1) {
+            Some(value) => min_idle = value.max(0) as u64,
+            None => return writer.write_error(NOT_INTEGER),
+        }
+        index += 2;
+    }
+    let extended = match request.len() - index {
+        0 if index == 3 => None,
+        3 | 4 => {
+            let (Some(start), Some(end)) = (
+                parse_range_start(request.get(index).unwrap()),
+                parse_range_end(request.get(index + 1).unwrap()),
+            ) else {
+                return writer.write_error(INVALID_ID);
+            };
+            let Some(count) = request.get_int(index + 2) else {
+                return writer.write_error(NOT_INTEGER);
+            };
+            Some((start, end, count.max(0) as usize, request.get(index + 3)))
+        }
+        _ => return writer.write_error(SYNTAX_ERROR),
+    };
+
+    let key = request.get(1).unwrap();
+    let name = request.get(2).unwrap();
+    let group = match storage.get_stream(key) {
+        Ok(stream) => match stream.and_then(|stream| stream.group(name)) {
+            Some(group) => group,
+            None => return writer.write_error(no_key_or_group(key, name)),
+        },
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    let Some((start, end, count, consumer)) = extended else {
+        writer.write_array_header(4)?;
+        writer.write_integer(group.pending.len() as i64)?;
+        if group.pending.is_empty() {
+            writer.write_bulk_sting(&NIL)?;
+            writer.write_bulk_sting(&NIL)?;
+            return writer.write_null_array();
+        }
+        let first = group.pending.keys().next().unwrap();
+        let last = group.pending.keys().next_back().unwrap();
+        writer.write_bulk_sting(&Some(first.to_string()))?;
+        writer.write_bulk_sting(&Some(last.to_string()))?;
+        let consumers: Vec<_> = group
+            .consumers
+            .iter()
+            .filter(|(_, consumer)| !consumer.pending.is_empty())
+            .collect();
+        writer.write_array_header(consumers.len())?;
+        for (name, consumer) in consumers {
+            writer.write_array_header(2)?;
+            writer.write_bulk_sting(&Some(name))?;
+            writer.write_bulk_sting(&Some(consumer.pending.len().to_string()))?;
+        }
+        return Ok(());
+    };
+
+    let now = now();
+    let entries: Vec<_> = match (start, end) {
+        (Some(start), Some(end)) if start <= end => group
+            .pending
+            .range(start..=end)
+            .filter(|(_, entry)| consumer.is_none_or(|consumer| entry.consumer == consumer))
+            .filter(|(_, entry)| now.saturating_sub(entry.delivery_time) >= min_idle)
+            .take(count)
+            .collect(),
+        _ => Vec::new(),
+    };
+    writer.write_array_header(entries.len())?;
+    for (id, entry) in entries {
+        writer.write_array_header(4)?;
+        writer.write_bulk_sting(&Some(id.to_string()))?;
+        writer.write_bulk_sting(&Some(&entry.consumer))?;
+        writer.write_integer(now.saturating_sub(entry.delivery_time) as i64)?;
+        writer.write_integer(entry.delivery_count as i64)?;
+    }
+    Ok(())
+}
+
+pub fn xclaim(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 6 {
+        return writer.write_error(wrong_arguments("xclaim"));
+    }
+
+    let Some(min_idle) = request.get_int(4) else {
+        return writer.write_error("Invalid min-idle-time argument for XCLAIM");
+    };
+    let min_idle = min_idle.max(0) as u64;
+
+    let mut index = 5;
+    let mut ids = Vec::new();
+    while let Some(id) = request.get(index).and_then(|id| StreamId::parse(id, 0)) {
+        ids.push(id);
+        index += 1;
+    }
+    if ids.is_empty() {
+        return writer.write_error(INVALID_ID);
+    }
+
+    let now = now();
+    let mut delivery_time = now;
+    let mut retry_count = None;
+    let mut force = false;
+    let mut just_id = false;
+    let mut last_id = None;
+    while index < request.len() {
+        let option = request.get_str(index).unwrap().to_lowercase();
+        let value = request.get(index + 1);
+        match option.as_str() {
+            "idle" if value.is_some() => match request.get_int(index + 1) {
+                Some(idle) => delivery_time = now.saturating_sub(idle.max(0) as u64),
+                None => return writer.write_error("Invalid IDLE option argument for XCLAIM"),
+            },
+            "time" if value.is_some() => match request.get_int(index + 1) {
+                Some(time) => delivery_time = (time.max(0) as u64).min(now),
+                None => return writer.write_error("Invalid TIME option argument for XCLAIM"),
+            },
+            "retrycount" if value.is_some() => match request.get_int(index + 1) {
+                Some(count) => retry_count = Some(count.max(0) as u64),
+                None => return writer.write_error("Invalid RETRYCOUNT option argument for XCLAIM"),
+            },
+            "lastid" if value.is_some() => match StreamId::parse(value.unwrap(), 0) {
+                Some(id) => last_id = Some(id),
+                None => return writer.write_error(INVALID_ID),
+            },
+            "force" => force = true,
+            "justid" => just_id = true,
+            _ => {
+                return writer.write_error(format!(
+                    "Unrecognized XCLAIM option '{}'",
+                    request.get_str(index).unwrap()
+                ))
+            }
+        }
+        index += if matches!(option.as_str(), "force" | "justid") {
+            1
+        } else {
+            2
+        };
+    }
+
+    let key = request.get(1).unwrap();
+    let name = request.get(2).unwrap();
+    let consumer = request.get(3).unwrap();
+    let stream = match storage.get_stream_mut(key) {
+        Ok(Some(stream)) if stream.group(name).is_some() => stream,
+        Ok(_) => return writer.write_error(no_key_or_group(key, name)),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let exists: Vec<_> = ids.iter().map(|id| stream.get(id).is_some()).collect();
+
+    let group = stream.group_mut(name).unwrap();
+    let mut modified = false;
+    if let Some(last_id) = last_id.filter(|last_id| *last_id > group.last_id) {
+        group.last_id = last_id;
+        modified = true;
+    }
+
+    let mut claimed = Vec::new();
+    for (id, exists) in ids.into_iter().zip(exists) {
+        let pending = group.pending.get(&id);
+        if pending.is_none() && !(force && exists) {
+            continue;
+        }
+        if !exists {
+            group.remove_pending(&id);
+            modified = true;
+            continue;
+        }
+        if pending.is_some_and(|entry| now.saturating_sub(entry.delivery_time) < min_idle) {
+            continue;
+        }
+
+        let entry = group.claim(id, consumer);
+        entry.delivery_time = delivery_time;
+        if let Some(retry_count) = retry_count {
+            entry.delivery_count = retry_count;
+        } else if !just_id {
+            entry.delivery_count += 1;
+        }
+        claimed.push(id);
+    }
+    let state = group.touch_consumer(consumer, now);
+    if !claimed.is_empty() {
+        state.active_time = Some(now);
+    }
+
+    write_claimed(writer, stream, &claimed, just_id)?;
+    if modified || !claimed.is_empty() {
+        storage.touch(key);
+    }
+    Ok(())
+}
+
+pub fn xautoclaim(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 6 {
+        return writer.write_error(wrong_arguments("xautoclaim"));
+    }
+
+    let Some(min_idle) = request.get_int(4) else {
+        return writer.write_error("Invalid min-idle-time argument for XAUTOCLAIM");
+    };
+    let min_idle = min_idle.max(0) as u64;
+    let Some(start) = parse_range_start(request.get(5).unwrap()) else {
+        return writer.write_error(INVALID_ID);
+    };
+
+    let mut count = DEFAULT_AUTOCLAIM_COUNT;
+    let mut just_id = false;
+    let mut index = 6;
+    while index < request.len() {
+        let option = request.get_str(index).unwrap().to_lowercase();
+        match option.as_str() {
+            "count" if index + 1 < request.len() => {
+                match request.get_int(index + 1) {
+                    Some(value) if value > 0 => count = value as usize,
+                    Some(_) => return writer.write_error("COUNT must be > 0"),
+                    None => return writer.write_error(NOT_INTEGER),
+                }
+                index += 2;
+            }
+            "justid" => {
+                just_id = true;
+                index += 1;
+            }
+            _ => return writer.write_error(SYNTAX_ERROR),
+        }
+    }
+
+    let key = request.get(1).unwrap();
+    let name = request.get(2).unwrap();
+    let consumer = request.get(3).unwrap();
+    let stream = match storage.get_stream_mut(key) {
+        Ok(Some(stream)) if stream.group(name).is_some() => stream,
+        Ok(_) => return writer.write_error(no_key_or_group(key, name)),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    let candidates: Vec<_> = match start {
+        Some(start) => stream.group(name).unwrap().pending.range(start..),
+        None => stream
+            .group(name)
+            .unwrap()
+            .pending
+            .range(StreamId::MIN..StreamId::MIN),
+    }
+    .take(count.saturating_mul(AUTOCLAIM_ATTEMPTS_FACTOR))
+    .map(|(id, _)| (*id, stream.get(id).is_some()))
+    .collect();
+
+    let now = now();
+    let group = stream.group_mut(name).unwrap();
+    let mut claimed = Vec::new();
+    let mut deleted = Vec::new();
+    let mut last_examined = None;
+    for (id, exists) in candidates {
+        if claimed.len() == count {
+            break;
+        }
+        last_examined = Some(id);
+        if !exists {
+            group.remove_pending(&id);
+            deleted.push(id);
+            continue;
+        }
+        if now.saturating_sub(group.pending[&id].delivery_time) < min_idle {
+            continue;
+        }
+
+        let entry = group.claim(id, consumer);
+        entry.delivery_time = now;
+        if !just_id {
+            entry.delivery_count += 1;
+        }
+        claimed.push(id);
+    }
+    // The cursor is the next entry to examine, or 0-0 once the whole list was scanned.
+    let cursor = last_examined
+        .and_then(|id| {
+            group
+                .pending
+                .range((Bound::Excluded(id), Bound::Unbounded))
+                .next()
+        })
+        .map_or(StreamId::MIN, |(id, _)| *id);
+    let state = group.touch_consumer(consumer, now);
+    if !claimed.is_empty() {
+        state.active_time = Some(now);
+    }
+
+    writer.write_array_header(3)?;
+    writer.write_bulk_sting(&Some(cursor.to_string()))?;
+    write_claimed(writer, stream, &claimed, just_id)?;
+    if !claimed.is_empty() || !deleted.is_empty() {
+        storage.touch(key);
+    }
+    let deleted: Vec<_> = deleted.iter().map(|id| Some(id.to_string())).collect();
+    writer.write_array(&deleted)
+}
+
+pub fn xinfo(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    let Some(subcommand) = request.get_str(1) else {
+        return writer.write_error(wrong_arguments("xinfo"));
+    };
+    let subcommand = subcommand.to_lowercase();
+    let arity_ok = match subcommand.as_str() {
+        "stream" => request.len() >= 3,
+        "groups" => request.len() == 3,
+        "consumers" => request.len() == 4,
+        _ => {
+            return writer.write_error(format!(
+                "unknown subcommand '{}'. Try XINFO HELP.",
+                request.get_str(1).unwrap()
+            ))
+        }
+    };
+    if !arity_ok {
+        return writer.write_error(wrong_arguments(&format!("xinfo|{}", subcommand)));
+    }
+
+    let full = match subcommand.as_str() {
+        "stream" => match request.len() {
+            3 => None,
+            4 if request.get_str(3).unwrap().eq_ignore_ascii_case("full") => {
+                Some(DEFAULT_INFO_COUNT)
+            }
+            6 if request.get_str(3).unwrap().eq_ignore_ascii_case("full")
+                && request.get_str(4).unwrap().eq_ignore_ascii_case("count") =>
+            {
+                match request.get_int(5) {
+                    Some(count) if count <= 0 => Some(usize::MAX),
+                    Some(count) => Some(count as usize),
+                    None => return writer.write_error(NOT_INTEGER),
+                }
+            }
+            _ => return writer.write_error(SYNTAX_ERROR),
+        },
+        _ => None,
+    };
+
+    let key = request.get(2).unwrap();
+    let stream = match storage.get_stream(key) {
+        Ok(Some(stream)) => stream,
+        Ok(None) => return writer.write_error("no such key"),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    let now = now();
+    match subcommand.as_str() {
+        "stream" => match full {
+            None => write_stream_info(writer, stream),
+            Some(count) => write_full_stream_info(writer, stream, count),
+        },
+        "groups" => {
+            writer.write_array_header(stream.groups().len())?;
+            for (name, group) in stream.groups() {
+                writer.write_array_header(12)?;
+                write_field(writer, "name")?;
+                writer.write_bulk_sting(&Some(name))?;
+                write_field(writer, "consumers")?;
+                writer.write_integer(group.consumers.len() as i64)?;
+                write_field(writer, "pending")?;
+                writer.write_integer(group.pending.len() as i64)?;
+                write_field(writer, "last-delivered-id")?;
+                writer.write_bulk_sting(&Some(group.last_id.to_string()))?;
+                write_field(writer, "entries-read")?;
+                write_optional_integer(writer, group.entries_read)?;
+                write_field(writer, "lag")?;
+                write_optional_integer(writer, stream.lag(group))?;
+            }
+            Ok(())
+        }
+        _ => {
+            let name = request.get(3).unwrap();
+            let Some(group) = stream.group(name) else {
+                return writer.write_error(no_group_for_key(key, name));
+            };
+            writer.write_array_header(group.consumers.len())?;
+            for (name, consumer) in &group.consumers {
+                writer.write_array_header(8)?;
+                write_field(writer, "name")?;
+                writer.write_bulk_sting(&Some(name))?;
+                write_field(writer, "pending")?;
+                writer.write_integer(consumer.pending.len() as i64)?;
+                write_field(writer, "idle")?;
+                writer.write_integer(now.saturating_sub(consumer.seen_time) as i64)?;
+                write_field(writer, "inactive")?;
+                writer.write_integer(
+                    consumer
+                        .active_time
+                        .map_or(-1, |time| now.saturating_sub(time) as i64),
+                )?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn write_stream_info(writer: &mut impl WriteResp, stream: &Stream) -> std::io::Result<()> {
+    writer.write_array_header(20)?;
+    write_stream_summary(writer, stream)?;
+    write_field(writer, "groups")?;
+    writer.write_integer(stream.groups().len() as i64)?;
+    write_field(writer, "first-entry")?;
+    write_optional_entry(writer, stream.iter().next())?;
+    write_field(writer, "last-entry")?;
+    write_optional_entry(writer, stream.last_entry())
+}
+
+fn write_full_stream_info(
+    writer: &mut impl WriteResp,
+    stream: &Stream,
+    count: usize,
+) -> std::io::Result<()> {
+    writer.write_array_header(18)?;
+    write_stream_summary(writer, stream)?;
+    write_field(writer, "entries")?;
+    let entries: Vec<_> = stream.iter().take(count).collect();
+    write_entries(writer, &entries)?;
+
+    write_field(writer, "groups")?;
+    writer.write_array_header(stream.groups().len())?;
+    for (name, group) in stream.groups() {
+        writer.write_array_header(14)?;
+        write_field(writer, "name")?;
+        writer.write_bulk_sting(&Some(name))?;
+        write_field(writer, "last-delivered-id")?;
+        writer.write_bulk_sting(&Some(group.last_id.to_string()))?;
+        write_field(writer, "entries-read")?;
+        write_optional_integer(writer, group.entries_read)?;
+        write_field(writer, "lag")?;
+        write_optional_integer(writer, stream.lag(group))?;
+        write_field(writer, "pel-count")?;
+        writer.write_integer(group.pending.len() as i64)?;
+        write_field(writer, "pending")?;
+        let pending: Vec<_> = group.pending.iter().take(count).collect();
+        writer.write_array_header(pending.len())?;
+        for (id, entry) in pending {
+            writer.write_array_header(4)?;
+            writer.write_bulk_sting(&Some(id.to_string()))?;
+            writer.write_bulk_sting(&Some(&entry.consumer))?;
+            writer.write_integer(entry.delivery_time as i64)?;
+            writer.write_integer(entry.delivery_count as i64)?;
+        }
+
+        write_field(writer, "consumers")?;
+        writer.write_array_header(group.consumers.len())?;
+        for (name, consumer) in &group.consumers {
+            writer.write_array_header(10)?;
+            write_field(writer, "name")?;
+            writer.write_bulk_sting(&Some(name))?;
+            write_field(writer, "seen-time")?;
+            writer.write_integer(consumer.seen_time as i64)?;
+            write_field(writer, "active-time")?;
+            writer.write_integer(consumer.active_time.map_or(-1, |time| time as i64))?;
+            write_field(writer, "pel-count")?;
+            writer.write_integer(consumer.pending.len() as i64)?;
+            write_field(writer, "pending")?;
+            let pending: Vec<_> = consumer.pending.iter().take(count).collect();
+            writer.write_array_header(pending.len())?;
+            for id in pending {
+                let entry = &group.pending[id];
+                writer.write_array_header(3)?;
+                writer.write_bulk_sting(&Some(id.to_string()))?;
+                writer.write_integer(entry.delivery_time as i64)?;
+                writer.write_integer(entry.delivery_count as i64)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the fields shared by the summary and the full form of XINFO STREAM.
+fn write_stream_summary(writer: &mut impl WriteResp, stream: &Stream) -> std::io::Result<()> {
+    let nodes = stream
+        .len()
+        .div_ceil(crate::redis::rdb::STREAM_NODE_MAX_ENTRIES);
+    write_field(writer, "length")?;
+    writer.write_integer(stream.len() as i64)?;
+    write_field(writer, "radix-tree-keys")?;
+    writer.write_integer(nodes as i64)?;
+    write_field(writer, "radix-tree-nodes")?;
+    writer.write_integer(nodes.max(1) as i64)?;
+    write_field(writer, "last-generated-id")?;
+    writer.write_bulk_sting(&Some(stream.last_id().to_string()))?;
+    write_field(writer, "max-deleted-entry-id")?;
+    writer.write_bulk_sting(&Some(stream.max_deleted_id().to_string()))?;
+    write_field(writer, "entries-added")?;
+    writer.write_integer(stream.entries_added() as i64)?;
+    write_field(writer, "recorded-first-entry-id")?;
+    let first_id = stream.first_id().unwrap_or(StreamId::MIN);
+    writer.write_bulk_sting(&Some(first_id.to_string()))
+}
+
+fn write_field(writer: &mut impl WriteResp, name: &str) -> std::io::Result<()> {
+    writer.write_bulk_sting(&Some(name))
+}
+
+fn write_optional_integer(writer: &mut impl WriteResp, value: Option<u64>) -> std::io::Result<()> {
+    match value {
+        Some(value) => writer.write_integer(value as i64),
+        None => writer.write_bulk_sting(&NIL),
+    }
+}
+
+fn write_optional_entry(
+    writer: &mut impl WriteResp,
+    entry: Option<(&StreamId, &StreamFields)>,
+) -> std::io::Result<()> {
+    match entry {
+        Some((id, fields)) => write_entry(writer, id, fields),
+        None => writer.write_bulk_sting(&NIL),
+    }
+}
+
+/// Writes claimed entries, or only their IDs with JUSTID.
+fn write_claimed(
+    writer: &mut impl WriteResp,
+    stream: &Stream,
+    ids: &[StreamId],
+    just_id: bool,
+) -> std::io::Result<()> {
+    if just_id {
+        let ids: Vec<_> = ids.iter().map(|id| Some(id.to_string())).collect();
+        return writer.write_array(&ids);
+    }
+    let entries: Vec<_> = ids
+        .iter()
+        .filter_map(|id| stream.get(id).map(|fields| (id, fields)))
+        .collect();
+    write_entries(writer, &entries)
+}
+
+/// Writes entries like XRANGE does, entries deleted since their delivery having nil fields.
+fn write_optional_entries(
+    writer: &mut impl WriteResp,
+    entries: &HistoryEntries,
+) -> std::io::Result<()> {
+    writer.write_array_header(entries.len())?;
+    for (id, fields) in entries {
+        match fields {
+            Some(fields) => write_entry(writer, id, fields)?,
+            None => {
+                writer.write_array_header(2)?;
+                writer.write_bulk_sting(&Some(id.to_string()))?;
+                writer.write_null_array()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn no_group_for_key(key: &[u8], group: &[u8]) -> String {
+    format!(
+        "NOGROUP No such consumer group '{}' for key name '{}'",
+        String::from_utf8_lossy(group),
+        String::from_utf8_lossy(key)
+    )
+}
+
+fn no_key_or_group(key: &[u8], group: &[u8]) -> String {
+    format!(
+        "NOGROUP No such key '{}' or consumer group '{}'",
+        String::from_utf8_lossy(key),
+        String::from_utf8_lossy(group)
+    )
+}
+
+fn now() -> u64 {
+    Utc::now().timestamp_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::consumer_group::{
+        xack, xautoclaim, xclaim, xgroup, xpending, xreadgroup,
+    };
+    use crate::redis::core::request::Request;
+    use crate::redis::core::stream::{xadd, xdel};
+    use crate::redis::rdb::Database;
+
+    fn run(storage: &mut Database, arguments: &[&str]) -> String {
+        let mut reply = Vec::new();
+        let request = Request::from_strs(arguments);
+        match arguments[0] {
+            "XADD" => xadd(&mut reply, storage, &request).unwrap(),
+            "XDEL" => xdel(&mut reply, storage, &request).unwrap(),
+            "XGROUP" => xgroup(&mut reply, storage, &request).unwrap(),
+            "XREADGROUP" => assert!(xreadgroup(&mut reply, storage, &request).unwrap().is_none()),
+            "XACK" => xack(&mut reply, storage, &request).unwrap(),
+            "XPENDING" => xpending(&mut reply, storage, &request).unwrap(),
+            "XCLAIM" => xclaim(&mut reply, storage, &request).unwrap(),
+            "XAUTOCLAIM" => xautoclaim(&mut reply, storage, &request).unwrap(),
+            command => panic!("unexpected command {command}"),
+        }
+        String::from_utf8(reply).unwrap()
+    }
+
+    fn stream_with_group(ids: &[&str]) -> Database {
+        let mut storage = Database::default();
+        for id in ids {
+            run(&mut storage, &["XADD", "s", id, "f", "v"]);
+        }
+        assert_eq!(
+            run(&mut storage, &["XGROUP", "CREATE", "s", "g", "0"]),
+            "+OK\r\n"
+        );
+        storage
+    }
+
+    fn entry(id: &str) -> String {
+        format!("*2\r\n$3\r\n{id}\r\n*2\r\n$1\r\nf\r\n$1\r\nv\r\n")
+    }
+
+    fn read(entries: &[String]) -> String {
+        format!(
+            "*1\r\n*2\r\n$1\r\ns\r\n*{}\r\n{}",
+            entries.len(),
+            entries.concat()
+        )
+    }
+
+    #[test]
+    fn test_xreadgroup_reads_new_entries_or_history() {
+        let mut storage = stream_with_group(&["1-0", "2-0"]);
+        for (arguments, expected) in [
+            (
+                &[
+                    "XREADGROUP",
+                    "GROUP",
+                    "g",
+                    "alice",
+                    "COUNT",
+                    "1",
+                    "STREAMS",
+                    "s",
+                    ">",
+                ][..],
+                read(&[entry("1-0")]),
+            ),
+            (
+                &["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", ">"],
+                read(&[entry("2-0")]),
+            ),
+            (
+                &["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", ">"],
+                "*-1\r\n".to_string(),
+            ),
+            (
+                &["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", "0"],
+                read(&[entry("1-0"), entry("2-0")]),
+            ),
+            (
+                &["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", "1-0"],
+                read(&[entry("2-0")]),
+            ),
+            (
+                &["XREADGROUP", "GROUP", "g", "bob", "STREAMS", "s", "0"],
+                read(&[]),
+            ),
+            (
+                &["XREADGROUP", "GROUP", "missing", "bob", "STREAMS", "s", ">"],
+                "-NOGROUP No such key 's' or consumer group 'missing' in XREADGROUP with GROUP \
+                 option\r\n"
+                    .to_string(),
+            ),
+        ] {
+            assert_eq!(run(&mut storage, arguments), expected, "{arguments:?}");
+        }
+
+        // History keeps listing entries deleted after their delivery, without their fields.
+        run(&mut storage, &["XDEL", "s", "1-0"]);
+        assert_eq!(
+            run(
+                &mut storage,
+                &["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", "0"]
+            ),
+            read(&["*2\r\n$3\r\n1-0\r\n*-1\r\n".to_string(), entry("2-0")])
+        );
+
+        let request = Request::from_strs(&[
+            "XREADGROUP",
+            "GROUP",
+            "g",
+            "alice",
+            "BLOCK",
+            "0",
+            "STREAMS",
+            "s",
+            ">",
+        ]);
+        let block = xreadgroup(&mut Vec::new(), &mut storage, &request)
+            .unwrap()
+            .unwrap();
+        assert_eq!(block.keys, vec![b"s".to_vec()]);
+    }
+
+    #[test]
+    fn test_xack_shrinks_the_pending_entries_list() {
+        let mut storage = stream_with_group(&["1-0", "2-0", "3-0"]);
+        run(
+            &mut storage,
+            &[
+                "XREADGROUP",
+                "GROUP",
+                "g",
+                "alice",
+                "COUNT",
+                "2",
+                "STREAMS",
+                "s",
+                ">",
+            ],
+        );
+        run(
+            &mut storage,
+            &["XREADGROUP", "GROUP", "g", "bob", "STREAMS", "s", ">"],
+        );
+
+        let mut reply = String::new();
+        for arguments in [
+            &["XPENDING", "s", "g"][..],
+            &["XACK", "s", "g", "1-0", "9-0"],
+            &["XACK", "s", "g", "1-0"],
+            &["XPENDING", "s", "g"],
+            &["XPENDING", "s", "g", "IDLE", "3600000", "-", "+", "10"],
+            &["XPENDING", "s", "g", "-", "+", "0"],
+            &["XPENDING", "s", "missing"],
+        ] {
+            reply += &run(&mut storage, arguments);
+        }
+        assert_eq!(
+            reply,
+            "*4\r\n:3\r\n$3\r\n1-0\r\n$3\r\n3-0\r\n\
+             *2\r\n*2\r\n$5\r\nalice\r\n$1\r\n2\r\n*2\r\n$3\r\nbob\r\n$1\r\n1\r\n\
+             :1\r\n:0\r\n\
+             *4\r\n:2\r\n$3\r\n2-0\r\n$3\r\n3-0\r\n\
+             *2\r\n*2\r\n$5\r\nalice\r\n$1\r\n1\r\n*2\r\n$3\r\nbob\r\n$1\r\n1\r\n\
+             *0\r\n*0\r\n-NOGROUP No such key 's' or consumer group 'missing'\r\n"
+        );
+
+        // The extended form lists ID, owner, idle time and deliveries, the idle time varying.
+        for arguments in [
+            &["XPENDING", "s", "g", "(2-0", "+", "10"][..],
+            &["XPENDING", "s", "g", "-", "+", "10", "bob"],
+        ] {
+            let reply = run(&mut storage, arguments);
+            let lines: Vec<_> = reply.split("\r\n").collect();
+            assert_eq!(lines[..6], ["*1", "*4", "$3", "3-0", "$3", "bob"]);
+            assert!(lines[6].starts_with(':'));
+            assert_eq!(lines[7..], [":1", ""]);
+        }
+
+        assert_eq!(
+            run(&mut storage, &["XACK", "s", "g", "2-0", "3-0"]),
+            ":2\r\n"
+        );
+        assert_eq!(
+            run(&mut storage, &["XPENDING", "s", "g"]),
+            "*4\r\n:0\r\n$-1\r\n$-1\r\n*-1\r\n"
+        );
+    }
+
+    #[test]
+    fn test_xclaim_honours_the_minimum_idle_time() {
+        let mut storage = stream_with_group(&["1-0"]);
+        run(
+            &mut storage,
+            &["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", ">"],
+        );
+
+        let mut reply = String::new();
+        for arguments in [
+            &["XCLAIM", "s", "g", "bob", "3600000", "1-0"][..],
+            &[
+                "XCLAIM", "s", "g", "bob", "0", "1-0", "IDLE", "7200000", "JUSTID",
+            ],
+            &["XCLAIM", "s", "g", "carol", "3600000", "1-0"],
+            &["XCLAIM", "s", "g", "carol", "0", "5-0"],
+            &["XCLAIM", "s", "g", "carol", "0", "5-0", "FORCE"],
+            &["XCLAIM", "s", "g", "carol", "x", "1-0"],
+        ] {
+            reply += &run(&mut storage, arguments);
+        }
+        assert_eq!(
+            reply,
+            format!(
+                "*0\r\n*1\r\n$3\r\n1-0\r\n*1\r\n{}*0\r\n*0\r\n\
+                 -Invalid min-idle-time argument for XCLAIM\r\n",
+                entry("1-0")
+            )
+        );
+
+        // JUSTID leaves the delivery count alone, a regular claim increments it.
+        let reply = run(&mut storage, &["XPENDING", "s", "g", "-", "+", "10"]);
+        let lines: Vec<_> = reply.split("\r\n").collect();
+        assert_eq!(lines[..6], ["*1", "*4", "$3", "1-0", "$5", "carol"]);
+        assert_eq!(lines[7..], [":2", ""]);
+    }
+
+    #[test]
+    fn test_xautoclaim_advances_its_cursor_and_drops_deleted_entries() {
+        let mut storage = stream_with_group(&["1-0", "2-0", "3-0"]);
+        run(
+            &mut storage,
+            &["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", ">"],
+        );
+        run(&mut storage, &["XDEL", "s", "2-0"]);
+
+        assert_eq!(
+            run(
+                &mut storage,
+                &["XAUTOCLAIM", "s", "g", "bob", "0", "0", "COUNT", "1"]
+            ),
+            format!("*3\r\n$3\r\n2-0\r\n*1\r\n{}*0\r\n", entry("1-0"))
+        );
+        assert_eq!(
+            run(
+                &mut storage,
+                &["XAUTOCLAIM", "s", "g", "bob", "0", "2-0", "COUNT", "1"]
+            ),
+            format!(
+                "*3\r\n$3\r\n0-0\r\n*1\r\n{}*1\r\n$3\r\n2-0\r\n",
+                entry("3-0")
+            )
+        );
+        assert_eq!(
+            run(
+                &mut storage,
+                &["XAUTOCLAIM", "s", "g", "carol", "3600000", "0", "JUSTID"]
+            ),
+            "*3\r\n$3\r\n0-0\r\n*0\r\n*0\r\n"
+        );
+        assert_eq!(
+            run(
+                &mut storage,
+                &["XAUTOCLAIM", "s", "g", "carol", "0", "0", "JUSTID"]
+            ),
+            "*3\r\n$3\r\n0-0\r\n*2\r\n$3\r\n1-0\r\n$3\r\n3-0\r\n*0\r\n"
+        );
+        assert_eq!(
+            run(
+                &mut storage,
+                &["XAUTOCLAIM", "s", "g", "carol", "0", "0", "COUNT", "0"]
+            ),
+            "-COUNT must be > 0\r\n"
+        );
+        assert_eq!(
+            run(&mut storage, &["XPENDING", "s", "g"]),
+            "*4\r\n:2\r\n$3\r\n1-0\r\n$3\r\n3-0\r\n*1\r\n*2\r\n$5\r\ncarol\r\n$1\r\n2\r\n"
+        );
+    }
+}
@@ -0,0 +1,13 @@
+pub const NOT_INTEGER: &str = "value is not an integer or out of range";
+pub const SYNTAX_ERROR: &str = "syntax error";
+/// The error for a count of random elements whose absolute value does not fit in an i64.
+pub const COUNT_OUT_OF_RANGE: &str =
+    "value is out of range, value must between -9223372036854775807 and 9223372036854775807";
+
+pub fn wrong_arguments(command: &str) -> String {
+    format!("wrong number of arguments for '{}' command", command)
+}
+
+pub fn unknown_command(command: &str) -> String {
+    format!("Unknown command '{}'", command)
+}
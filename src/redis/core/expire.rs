@@ -0,0 +1,285 @@
+use crate::redis::core::errors::{wrong_arguments, NOT_INTEGER};
+use crate::redis::core::request::Request;
+use crate::redis::core::WriteResp;
+use crate::redis::rdb::Database;
+use chrono::Utc;
+
+pub fn expire(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    set_expiration(writer, storage, request, "expire", 1000, false)
+}
+
+pub fn pexpire(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    set_expiration(writer, storage, request, "pexpire", 1, false)
+}
+
+pub fn expireat(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    set_expiration(writer, storage, request, "expireat", 1000, true)
+}
+
+pub fn pexpireat(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    set_expiration(writer, storage, request, "pexpireat", 1, true)
+}
+
+/// Sets the expiration of a key from a time given in `unit` milliseconds, either relative to
+/// now or as a Unix time. A time in the past deletes the key.
+fn set_expiration(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+    command: &str,
+    unit: i64,
+    absolute: bool,
+) -> std::io::Result<()> {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments(command));
+    }
+
+    let (mut nx, mut xx, mut gt, mut lt) = (false, false, false, false);
+    for index in 3..request.len() {
+        let option = request.get_str(index).unwrap();
+        match option.to_lowercase().as_str() {
+            "nx" => nx = true,
+            "xx" => xx = true,
+            "gt" => gt = true,
+            "lt" => lt = true,
+            _ => return writer.write_error(format!("Unsupported option {}", option)),
+        }
+    }
+    if nx && (xx || gt || lt) {
+        return writer
+            .write_error("NX and XX, GT or LT options at the same time are not compatible");
+    }
+    if gt && lt {
+        return writer.write_error("GT and LT options at the same time are not compatible");
+    }
+
+    let Some(value) = request.get_int(2) else {
+        return writer.write_error(NOT_INTEGER);
+    };
+    let now = Utc::now().timestamp_millis();
+    let expire_at = value.checked_mul(unit).and_then(|time| {
+        if absolute {
+            Some(time)
+        } else {
+            time.checked_add(now)
+        }
+    });
+    let Some(expire_at) = expire_at else {
+        return writer.write_error(format!("invalid expire time in '{}' command", command));
+    };
+
+    let key = request.get(1).unwrap();
+    let Some(current) = storage.get_expire_time(key) else {
+        return writer.write_integer(0);
+    };
+    // A key without an expiration behaves as if it expired at infinity.
+    let allowed = match current {
+        None => !xx && !gt,
+        Some(current) => {
+            !nx && (!gt || expire_at > current as i64) && (!lt || expire_at < current as i64)
+        }
+    };
+    if !allowed {
+        return writer.write_integer(0);
+    }
+
+    if expire_at <= now {
+        storage.remove(key);
+    } else {
+        storage.set_expire_time(key, Some(expire_at as u64));
+    }
+    writer.write_integer(1)
+}
+
+pub fn ttl(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    get_expiration(writer, storage, request, "ttl", 1000, false)
+}
+
+pub fn pttl(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    get_expiration(writer, storage, request, "pttl", 1, false)
+}
+
+pub fn expiretime(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    get_expiration(writer, storage, request, "expiretime", 1000, true)
+}
+
+pub fn pexpiretime(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    get_expiration(writer, storage, request, "pexpiretime", 1, true)
+}
+
+/// Replies with the expiration of a key in `unit` milliseconds, either remaining or as a Unix
+/// time, -1 if it has none and -2 if the key does not exist.
+fn get_expiration(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+    command: &str,
+    unit: i64,
+    absolute: bool,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments(command));
+    }
+
+    match storage.get_expire_time(request.get(1).unwrap()) {
+        None => writer.write_integer(-2),
+        Some(None) => writer.write_integer(-1),
+        Some(Some(expire_at)) => {
+            let time = if absolute {
+                expire_at as i64
+            } else {
+                (expire_at as i64 - Utc::now().timestamp_millis()).max(0)
+            };
+            writer.write_integer((time + unit / 2) / unit)
+        }
+    }
+}
+
+pub fn persist(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("persist"));
+    }
+
+    let key = request.get(1).unwrap();
+    match storage.get_expire_time(key) {
+        Some(Some(_)) => {
+            storage.set_expire_time(key, None);
+            writer.write_integer(1)
+        }
+        _ => writer.write_integer(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::expire::{expire, expireat, persist, pexpireat, pexpiretime, ttl};
+    use crate::redis::core::request::Request;
+    use crate::redis::rdb::Database;
+
+    #[test]
+    fn test_conditions_compare_with_the_current_expiration() {
+        let mut storage = Database::default();
+        storage.set(b"k".to_vec(), b"v".to_vec(), None);
+        let mut reply = Vec::new();
+        for arguments in [
+            // Without an expiration, the key behaves as if it expired at infinity.
+            &["PEXPIREAT", "k", "4102444800000", "XX"][..],
+            &["PEXPIREAT", "k", "4102444800000", "GT"],
+            &["PEXPIREAT", "k", "4102444800000", "LT"],
+            &["PEXPIREAT", "k", "4102444800001", "NX"],
+            &["PEXPIREAT", "k", "4102444800001", "LT"],
+            &["PEXPIREAT", "k", "4102444800001", "GT", "XX"],
+            &["PEXPIREAT", "k", "4102444800000", "GT"],
+            &["PEXPIREAT", "x", "4102444800000"],
+        ] {
+            pexpireat(&mut reply, &mut storage, &Request::from_strs(arguments)).unwrap();
+        }
+        pexpiretime(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["PEXPIRETIME", "k"]),
+        )
+        .unwrap();
+        assert_eq!(
+            reply,
+            b":0\r\n:0\r\n:1\r\n:0\r\n:0\r\n:1\r\n:0\r\n:0\r\n:4102444800001\r\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_options_and_times() {
+        let mut storage = Database::default();
+        storage.set(b"k".to_vec(), b"v".to_vec(), None);
+        let mut reply = Vec::new();
+        for arguments in [
+            &["EXPIRE", "k", "10", "NX", "GT"][..],
+            &["EXPIRE", "k", "10", "GT", "LT"],
+            &["EXPIRE", "k", "10", "SOON"],
+            &["EXPIRE", "k", "ten"],
+            &["EXPIRE", "k", "9223372036854775807"],
+        ] {
+            expire(&mut reply, &mut storage, &Request::from_strs(arguments)).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            "-NX and XX, GT or LT options at the same time are not compatible\r\n\
+             -GT and LT options at the same time are not compatible\r\n\
+             -Unsupported option SOON\r\n-value is not an integer or out of range\r\n\
+             -invalid expire time in 'expire' command\r\n"
+        );
+        assert_eq!(storage.get_expire_time(b"k"), Some(None));
+    }
+
+    #[test]
+    fn test_ttl_follows_the_key() {
+        let mut storage = Database::default();
+        storage.set(b"k".to_vec(), b"v".to_vec(), None);
+        let mut reply = Vec::new();
+        ttl(&mut reply, &mut storage, &Request::from_strs(&["TTL", "k"])).unwrap();
+        expire(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["EXPIRE", "k", "100"]),
+        )
+        .unwrap();
+        ttl(&mut reply, &mut storage, &Request::from_strs(&["TTL", "k"])).unwrap();
+        persist(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["PERSIST", "k"]),
+        )
+        .unwrap();
+        persist(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["PERSIST", "k"]),
+        )
+        .unwrap();
+        // A time in the past deletes the key.
+        expireat(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["EXPIREAT", "k", "1"]),
+        )
+        .unwrap();
+        ttl(&mut reply, &mut storage, &Request::from_strs(&["TTL", "k"])).unwrap();
+        assert_eq!(reply, b":-1\r\n:1\r\n:100\r\n:1\r\n:0\r\n:1\r\n:-2\r\n");
+        assert!(!storage.contains_key(b"k"));
+    }
+}
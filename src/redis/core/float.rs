@@ -0,0 +1,120 @@
+/// Parses a float argument, rejecting NaN the way Redis does.
+pub fn parse_float(value: &[u8]) -> Option<f64> {
+    let value: f64 = std::str::from_utf8(value).ok()?.parse().ok()?;
+    if value.is_nan() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Formats a float like Redis' human-readable representation of a long double: up to 17
+/// significant digits in fixed-point notation, at most 17 decimals and no trailing zeros.
+///
+/// The value is only computed as an f64, whose last digit carries a rounding error that a long
+/// double would not have: 0.1 + 0.2 is the f64 after 0.3. A result one unit in the last place
+/// away from a decimal of 15 significant digits, which an f64 always tells apart, is taken as
+/// that decimal, giving 0.3 like Redis. Other values get the shortest digits that round-trip.
+pub fn format_float(value: f64) -> String {
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    let magnitude = value.abs();
+    let ulp = f64::from_bits(magnitude.to_bits() + 1) - magnitude;
+    let short = format!("{:.14e}", magnitude);
+    let scientific = if (short.parse::<f64>().unwrap() - magnitude).abs() <= ulp {
+        short
+    } else {
+        format!("{:e}", magnitude)
+    };
+
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let digits = mantissa.replace('.', "");
+    let digits = digits.trim_end_matches('0');
+    let fixed = if exponent < 0 {
+        format!("0.{}{}", "0".repeat((-exponent - 1) as usize), digits)
+    } else if digits.len() <= exponent as usize + 1 {
+        format!(
+            "{}{}",
+            digits,
+            "0".repeat(exponent as usize + 1 - digits.len())
+        )
+    } else {
+        let (integer, decimals) = digits.split_at(exponent as usize + 1);
+        format!("{}.{}", integer, decimals)
+    };
+    let fixed = match fixed.split_once('.') {
+        Some((_, decimals)) if decimals.len() > 17 => {
+            let rounded = format!("{:.17}", magnitude);
+            rounded
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string()
+        }
+        _ => fixed,
+    };
+    if value < 0.0 && fixed != "0" {
+        format!("-{}", fixed)
+    } else {
+        fixed
+    }
+}
+
+/// Formats a sorted set score like Redis' `%.17g`, but with the shortest digits that
+/// round-trip: exponent notation is used below 1e-4 and from 1e17 on.
+pub fn format_score(score: f64) -> String {
+    if !score.is_finite() || score == 0.0 {
+        return score.to_string();
+    }
+    let scientific = format!("{:e}", score);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    if (-4..17).contains(&exponent) {
+        score.to_string()
+    } else {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", mantissa, sign, exponent.abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::float::{format_float, format_score, parse_float};
+
+    #[test]
+    fn test_format_score_like_redis() {
+        assert_eq!(format_score(1.5), "1.5");
+        assert_eq!(format_score(-3.0), "-3");
+        assert_eq!(format_score(0.1), "0.1");
+        assert_eq!(format_score(0.0001), "0.0001");
+        assert_eq!(format_score(1e16), "10000000000000000");
+        assert_eq!(format_score(f64::INFINITY), "inf");
+        assert_eq!(format_score(f64::NEG_INFINITY), "-inf");
+    }
+
+    #[test]
+    fn test_format_score_with_exponents() {
+        assert_eq!(format_score(1e17), "1e+17");
+        assert_eq!(format_score(1.5e300), "1.5e+300");
+        assert_eq!(format_score(-2.5e20), "-2.5e+20");
+        assert_eq!(format_score(0.00001), "1e-05");
+        assert_eq!(format_score(1.5e-7), "1.5e-07");
+        assert_eq!(format_score(5e-324), "5e-324");
+    }
+
+    #[test]
+    fn test_format_float_like_redis() {
+        let add =
+            |a: &[u8], b: &[u8]| format_float(parse_float(a).unwrap() + parse_float(b).unwrap());
+        assert_eq!(add(b"0.1", b"0.2"), "0.3");
+        assert_eq!(add(b"10.5", b"0.1"), "10.6");
+        assert_eq!(add(b"0", b"3.0e3"), "3000");
+        assert_eq!(add(b"0", b"5.0e200"), format!("5{}", "0".repeat(200)));
+        assert_eq!(add(b"1", b"-1.5"), "-0.5");
+        assert_eq!(format_float(1.2345678901234567), "1.2345678901234567");
+        assert_eq!(format_float(1.0 / 3.0), "0.3333333333333333");
+        assert_eq!(format_float(1e-20), "0");
+        assert_eq!(format_float(1.5e-10), "0.00000000015");
+    }
+}
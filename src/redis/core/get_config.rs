@@ -4,6 +4,7 @@ use crate::redis::core::WriteResp;
 
 const DIR: &str = "dir";
 const DB_FILE_NAME: &str = "dbfilename";
+const DATABASES: &str = "databases";
 
 pub fn get_config(
     writer: &mut impl WriteResp,
@@ -14,12 +15,12 @@ pub fn get_config(
         return writer.write_error("wrong number of arguments for 'config' command");
     }
 
-    let arg = request.get(1).unwrap();
+    let arg = request.get_str(1).unwrap();
     if arg.to_lowercase() != "get" {
         return writer.write_error(format!("unknown argument: '{}'", arg));
     }
 
-    let parameter = request.get(2).unwrap();
+    let parameter = request.get_str(2).unwrap();
 
     if parameter.eq_ignore_ascii_case(DIR) {
         writer.write_array(&[Some(DIR), config.dir().map(|x| x.as_str())])
@@ -28,6 +29,8 @@ pub fn get_config(
             Some(DB_FILE_NAME),
             config.db_file_name().map(|x| x.as_str()),
         ])
+    } else if parameter.eq_ignore_ascii_case(DATABASES) {
+        writer.write_array(&[Some(DATABASES), Some(&config.databases().to_string())])
     } else {
         writer.write_error("unknown configuration parameter")
     }
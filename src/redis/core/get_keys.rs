@@ -1,7 +1,24 @@
+use crate::redis::core::errors::wrong_arguments;
+use crate::redis::core::glob::glob_match;
+use crate::redis::core::request::Request;
 use crate::redis::core::WriteResp;
-use crate::redis::rdb::RedisStorage;
+use crate::redis::rdb::Database;
 
-pub fn get_keys(writer: &mut impl WriteResp, storage: &mut RedisStorage) -> std::io::Result<()> {
-    let result: Vec<_> = storage.get_keys().into_iter().map(Some).collect();
+pub fn get_keys(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("keys"));
+    }
+
+    let pattern = request.get(1).unwrap();
+    let result: Vec<_> = storage
+        .get_keys()
+        .into_iter()
+        .filter(|key| glob_match(pattern, key))
+        .map(Some)
+        .collect();
     writer.write_array(&result)
 }
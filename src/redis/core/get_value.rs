@@ -1,17 +1,19 @@
 use crate::redis::core::request::Request;
 use crate::redis::core::WriteResp;
-use crate::redis::rdb::RedisStorage;
+use crate::redis::rdb::Database;
 
 pub fn get_value(
     writer: &mut impl WriteResp,
-    storage: &mut RedisStorage,
+    storage: &mut Database,
     request: &Request,
 ) -> std::io::Result<()> {
     if request.len() != 2 {
         writer.write_error("wrong number of arguments for 'get' command")
     } else {
         let key = request.get(1).unwrap();
-        let result = storage.get(key);
-        writer.write_bulk_sting(&result)
+        match storage.get(key) {
+            Ok(result) => writer.write_bulk_sting(&result),
+            Err(e) => writer.write_error(e.to_string()),
+        }
     }
 }
@@ -0,0 +1,578 @@
+use crate::redis::core::errors::{wrong_arguments, COUNT_OUT_OF_RANGE, NOT_INTEGER, SYNTAX_ERROR};
+use crate::redis::core::float::{format_float, parse_float};
+use crate::redis::core::random::random_index;
+use crate::redis::core::request::Request;
+use crate::redis::core::scan::{write_scan_reply, ScanOptions};
+use crate::redis::core::WriteResp;
+use crate::redis::rdb::Database;
+
+const NIL: Option<&[u8]> = None;
+
+pub fn hset(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 4 || !request.len().is_multiple_of(2) {
+        return writer.write_error(wrong_arguments("hset"));
+    }
+
+    let key = request.get(1).unwrap();
+    let hash = match storage.get_or_insert_hash(key) {
+        Ok(hash) => hash,
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let mut added = 0;
+    for index in (2..request.len()).step_by(2) {
+        let field = request.get(index).unwrap().to_vec();
+        let value = request.get(index + 1).unwrap().to_vec();
+        if hash.insert(field, value).is_none() {
+            added += 1;
+        }
+    }
+    storage.touch(key);
+    writer.write_integer(added)
+}
+
+pub fn hsetnx(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("hsetnx"));
+    }
+
+    let key = request.get(1).unwrap();
+    let hash = match storage.get_or_insert_hash(key) {
+        Ok(hash) => hash,
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let field = request.get(2).unwrap();
+    if hash.contains_key(field) {
+        return writer.write_integer(0);
+    }
+    hash.insert(field.to_vec(), request.get(3).unwrap().to_vec());
+    storage.touch(key);
+    writer.write_integer(1)
+}
+
+pub fn hget(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("hget"));
+    }
+
+    match storage.get_hash(request.get(1).unwrap()) {
+        Ok(hash) => {
+            writer.write_bulk_sting(&hash.and_then(|hash| hash.get(request.get(2).unwrap())))
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn hmget(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments("hmget"));
+    }
+
+    match storage.get_hash(request.get(1).unwrap()) {
+        Ok(hash) => {
+            let values: Vec<_> = (2..request.len())
+                .map(|index| hash.and_then(|hash| hash.get(request.get(index).unwrap())))
+                .collect();
+            writer.write_array(&values)
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn hdel(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments("hdel"));
+    }
+
+    let key = request.get(1).unwrap();
+    let hash = match storage.get_hash_mut(key) {
+        Ok(Some(hash)) => hash,
+        Ok(None) => return writer.write_integer(0),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let removed = (2..request.len())
+        .filter(|index| hash.remove(request.get(*index).unwrap()).is_some())
+        .count();
+    if removed > 0 {
+        storage.touch(key);
+    }
+    storage.remove_if_empty(key);
+    writer.write_integer(removed as i64)
+}
+
+pub fn hgetall(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("hgetall"));
+    }
+
+    match storage.get_hash(request.get(1).unwrap()) {
+        Ok(hash) => {
+            let values: Vec<_> = hash
+                .into_iter()
+                .flatten()
+                .flat_map(|(field, value)| [Some(field), Some(value)])
+                .collect();
+            writer.write_array(&values)
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn hkeys(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("hkeys"));
+    }
+
+    match storage.get_hash(request.get(1).unwrap()) {
+        Ok(hash) => {
+            let fields: Vec<_> = hash
+                .into_iter()
+                .flat_map(|hash| hash.keys())
+                .map(Some)
+                .collect();
+            writer.write_array(&fields)
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn hvals(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("hvals"));
+    }
+
+    match storage.get_hash(request.get(1).unwrap()) {
+        Ok(hash) => {
+            let values: Vec<_> = hash
+                .into_iter()
+                .flat_map(|hash| hash.values())
+                .map(Some)
+                .collect();
+            writer.write_array(&values)
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn hlen(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("hlen"));
+    }
+
+    match storage.get_hash(request.get(1).unwrap()) {
+        Ok(hash) => writer.write_integer(hash.map_or(0, |hash| hash.len()) as i64),
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn hexists(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("hexists"));
+    }
+
+    match storage.get_hash(request.get(1).unwrap()) {
+        Ok(hash) => {
+            let exists = hash.is_some_and(|hash| hash.contains_key(request.get(2).unwrap()));
+            writer.write_integer(exists as i64)
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn hstrlen(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("hstrlen"));
+    }
+
+    match storage.get_hash(request.get(1).unwrap()) {
+        Ok(hash) => {
+            let len = hash
+                .and_then(|hash| hash.get(request.get(2).unwrap()))
+                .map_or(0, |value| value.len());
+            writer.write_integer(len as i64)
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn hincrby(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("hincrby"));
+    }
+
+    let Some(increment) = request.get_int(3) else {
+        return writer.write_error(NOT_INTEGER);
+    };
+
+    let key = request.get(1).unwrap();
+    let hash = match storage.get_or_insert_hash(key) {
+        Ok(hash) => hash,
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let field = request.get(2).unwrap();
+    let current: i64 = match hash.get(field) {
+        None => 0,
+        Some(value) => match std::str::from_utf8(value).ok().and_then(|x| x.parse().ok()) {
+            Some(value) => value,
+            None => return writer.write_error("hash value is not an integer"),
+        },
+    };
+    let Some(value) = current.checked_add(increment) else {
+        return writer.write_error("increment or decrement would overflow");
+    };
+    hash.insert(field.to_vec(), value.to_string().into_bytes());
+    storage.touch(key);
+    writer.write_integer(value)
+}
+
+pub fn hincrbyfloat(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("hincrbyfloat"));
+    }
+
+    let Some(increment) = parse_float(request.get(3).unwrap()) else {
+        return writer.write_error("value is not a valid float");
+    };
+
+    let key = request.get(1).unwrap();
+    let hash = match storage.get_or_insert_hash(key) {
+        Ok(hash) => hash,
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let field = request.get(2).unwrap();
+    let current = match hash.get(field) {
+        None => 0.0,
+        Some(value) => match parse_float(value) {
+            Some(value) => value,
+            None => return writer.write_error("hash value is not a float"),
+        },
+    };
+    let value = current + increment;
+    if !value.is_finite() {
+        return writer.write_error("increment would produce NaN or Infinity");
+    }
+    let value = format_float(value).into_bytes();
+    hash.insert(field.to_vec(), value.clone());
+    storage.touch(key);
+    writer.write_bulk_sting(&Some(value))
+}
+
+pub fn hrandfield(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 2 || request.len() > 4 {
+        return writer.write_error(wrong_arguments("hrandfield"));
+    }
+
+    let count = match request.get(2) {
+        None => None,
+        Some(_) => match request.get_int(2) {
+            Some(i64::MIN) => return writer.write_error(COUNT_OUT_OF_RANGE),
+            Some(count) => Some(count),
+            None => return writer.write_error(NOT_INTEGER),
+        },
+    };
+    let with_values = match request.get_str(3) {
+        None => false,
+        Some(option) if option.eq_ignore_ascii_case("withvalues") => true,
+        Some(_) => return writer.write_error(SYNTAX_ERROR),
+    };
+    // Each field comes with its value, so the reply length must still fit.
+    if with_values && count.is_some_and(|count| count.unsigned_abs() > i64::MAX as u64 / 2) {
+        return writer.write_error("value is out of range");
+    }
+
+    let hash = match storage.get_hash(request.get(1).unwrap()) {
+        Ok(Some(hash)) => hash,
+        Ok(None) if count.is_some() => return writer.write_array(&[NIL; 0]),
+        Ok(None) => return writer.write_bulk_sting(&NIL),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    let Some(count) = count else {
+        let field = hash.random_entry(random_index).map(|(field, _)| field);
+        return writer.write_bulk_sting(&field);
+    };
+    if count >= 0 {
+        let entries = hash.random_entries(count as usize, random_index);
+        writer.write_array_header(if with_values {
+            entries.len() * 2
+        } else {
+            entries.len()
+        })?;
+        for (field, value) in entries {
+            write_field(writer, field, value, with_values)?;
+        }
+        return Ok(());
+    }
+    // The same field may come up several times, so the reply is written as it is drawn.
+    let len = count.unsigned_abs() as usize;
+    writer.write_array_header(if with_values { len * 2 } else { len })?;
+    for _ in 0..len {
+        let (field, value) = hash.random_entry(random_index).unwrap();
+        write_field(writer, field, value, with_values)?;
+    }
+    Ok(())
+}
+
+fn write_field(
+    writer: &mut impl WriteResp,
+    field: &[u8],
+    value: &[u8],
+    with_value: bool,
+) -> std::io::Result<()> {
+    writer.write_bulk_sting(&Some(field))?;
+    if with_value {
+        writer.write_bulk_sting(&Some(value))?;
+    }
+    Ok(())
+}
+
+pub fn hscan(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    let options = match ScanOptions::parse(request, 2, "hscan") {
+        Ok(options) => options,
+        Err(e) => return writer.write_error(e),
+    };
+
+    let hash = match storage.get_hash(request.get(1).unwrap()) {
+        Ok(Some(hash)) => hash,
+        Ok(None) => return write_scan_reply(writer, 0, &[NIL; 0]),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let (cursor, entries) = hash.scan(options.cursor, options.count);
+
+    let mut values = Vec::new();
+    for (field, value) in entries {
+        if !options.matches(field) {
+            continue;
+        }
+        values.push(Some(field));
+        if !options.no_values {
+            values.push(Some(value));
+        }
+    }
+    write_scan_reply(writer, cursor, &values)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::hash::{hdel, hincrby, hincrbyfloat, hmget, hrandfield, hset, hsetnx};
+    use crate::redis::core::request::Request;
+    use crate::redis::rdb::Database;
+
+    #[test]
+    fn test_fields_are_set_and_deleted() {
+        let mut storage = Database::default();
+        let mut reply = Vec::new();
+        hset(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["HSET", "h", "a", "1", "b", "2"]),
+        )
+        .unwrap();
+        hset(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["HSET", "h", "a", "3", "c", "4"]),
+        )
+        .unwrap();
+        hsetnx(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["HSETNX", "h", "a", "5"]),
+        )
+        .unwrap();
+        hmget(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["HMGET", "h", "a", "x"]),
+        )
+        .unwrap();
+        hdel(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["HDEL", "h", "a", "b", "c", "x"]),
+        )
+        .unwrap();
+        assert_eq!(reply, b":2\r\n:1\r\n:0\r\n*2\r\n$1\r\n3\r\n$-1\r\n:3\r\n");
+        assert!(!storage.contains_key(b"h"));
+    }
+
+    #[test]
+    fn test_counters_check_their_field() {
+        let mut storage = Database::default();
+        let mut reply = Vec::new();
+        for arguments in [
+            &["HINCRBY", "h", "n", "9223372036854775806"][..],
+            &["HINCRBY", "h", "n", "1"],
+            &["HINCRBY", "h", "n", "1"],
+            &["HSET", "h", "s", "abc"],
+            &["HINCRBY", "h", "s", "1"],
+            &["HINCRBYFLOAT", "h", "f", "10.5"],
+            &["HINCRBYFLOAT", "h", "f", "0.1"],
+            &["HINCRBYFLOAT", "h", "s", "1"],
+        ] {
+            let request = Request::from_strs(arguments);
+            match arguments[0] {
+                "HINCRBY" => hincrby(&mut reply, &mut storage, &request),
+                "HINCRBYFLOAT" => hincrbyfloat(&mut reply, &mut storage, &request),
+                _ => hset(&mut reply, &mut storage, &request),
+            }
+            .unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            ":9223372036854775806\r\n:9223372036854775807\r\n\
+             -increment or decrement would overflow\r\n:1\r\n-hash value is not an integer\r\n\
+             $4\r\n10.5\r\n$4\r\n10.6\r\n-hash value is not a float\r\n"
+        );
+    }
+
+    #[test]
+    fn test_hrandfield_counts() {
+        let mut storage = Database::default();
+        let mut reply = Vec::new();
+        hset(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["HSET", "h", "a", "1", "b", "2"]),
+        )
+        .unwrap();
+
+        let mut reply = Vec::new();
+        hrandfield(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["HRANDFIELD", "h", "5"]),
+        )
+        .unwrap();
+        assert!(reply.starts_with(b"*2\r\n"));
+        let mut reply = Vec::new();
+        let random = Request::from_strs(&["HRANDFIELD", "h", "-5", "WITHVALUES"]);
+        hrandfield(&mut reply, &mut storage, &random).unwrap();
+        assert!(reply.starts_with(b"*10\r\n"));
+        let mut reply = Vec::new();
+        hrandfield(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["HRANDFIELD", "x", "1"]),
+        )
+        .unwrap();
+        hrandfield(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["HRANDFIELD", "x"]),
+        )
+        .unwrap();
+        assert_eq!(reply, b"*0\r\n$-1\r\n");
+    }
+
+    #[test]
+    fn test_commands_reject_other_types() {
+        let mut storage = Database::default();
+        storage.set(b"s".to_vec(), b"value".to_vec(), None);
+        let mut reply = Vec::new();
+        hset(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["HSET", "s", "a", "1"]),
+        )
+        .unwrap();
+        hincrby(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["HINCRBY", "s", "a", "1"]),
+        )
+        .unwrap();
+        let wrong_type = "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n";
+        assert_eq!(String::from_utf8(reply).unwrap(), wrong_type.repeat(2));
+    }
+
+    #[test]
+    fn test_hrandfield_rejects_extreme_counts() {
+        let mut storage = Database::default();
+        let mut reply = Vec::new();
+        hset(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["HSET", "h", "a", "1"]),
+        )
+        .unwrap();
+
+        let mut reply = Vec::new();
+        for arguments in [
+            &["HRANDFIELD", "h", "-9223372036854775808"][..],
+            &["HRANDFIELD", "h", "-9223372036854775808", "WITHVALUES"],
+            &["HRANDFIELD", "h", "-4611686018427387904", "WITHVALUES"],
+            &["HRANDFIELD", "h", "-2", "WITHVALUES"],
+        ] {
+            hrandfield(&mut reply, &mut storage, &Request::from_strs(arguments)).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            "-value is out of range, value must between -9223372036854775807 and \
+             9223372036854775807\r\n\
+             -value is out of range, value must between -9223372036854775807 and \
+             9223372036854775807\r\n\
+             -value is out of range\r\n\
+             *4\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\na\r\n$1\r\n1\r\n"
+        );
+    }
+}
@@ -0,0 +1,467 @@
+use crate::redis::core::errors::{wrong_arguments, NOT_INTEGER, SYNTAX_ERROR};
+use crate::redis::core::random::random_index;
+use crate::redis::core::request::Request;
+use crate::redis::core::WriteResp;
+use crate::redis::rdb::{Database, RedisStorage};
+
+const OK: &str = "OK";
+const SAME_OBJECT: &str = "source and destination objects are the same";
+const DB_OUT_OF_RANGE: &str = "DB index is out of range";
+
+pub fn del(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    remove_keys(writer, storage, request, "del")
+}
+
+/// Same as DEL: values are always freed right away.
+pub fn unlink(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    remove_keys(writer, storage, request, "unlink")
+}
+
+fn remove_keys(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+    command: &str,
+) -> std::io::Result<()> {
+    if request.len() < 2 {
+        return writer.write_error(wrong_arguments(command));
+    }
+
+    let removed = (1..request.len())
+        .filter(|index| storage.remove(request.get(*index).unwrap()))
+        .count();
+    writer.write_integer(removed as i64)
+}
+
+pub fn exists(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    count_existing(writer, storage, request, "exists")
+}
+
+/// Same as EXISTS since access times are not tracked.
+pub fn touch(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    count_existing(writer, storage, request, "touch")
+}
+
+/// Counts the keys that exist, a key given several times being counted as many times.
+fn count_existing(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+    command: &str,
+) -> std::io::Result<()> {
+    if request.len() < 2 {
+        return writer.write_error(wrong_arguments(command));
+    }
+
+    let count = (1..request.len())
+        .filter(|index| storage.contains_key(request.get(*index).unwrap()))
+        .count();
+    writer.write_integer(count as i64)
+}
+
+pub fn key_type(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("type"));
+    }
+
+    let value = storage.get_values(&[request.get(1).unwrap()]).remove(0);
+    writer.write_simple_string(value.map_or("none", |value| value.type_name()))
+}
+
+pub fn rename(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("rename"));
+    }
+
+    let (source, destination) = (request.get(1).unwrap(), request.get(2).unwrap());
+    if !storage.contains_key(source) {
+        return writer.write_error("no such key");
+    }
+    if source != destination {
+        storage.rename(source, destination);
+    }
+    writer.write_simple_string(OK)
+}
+
+pub fn renamenx(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("renamenx"));
+    }
+
+    let (source, destination) = (request.get(1).unwrap(), request.get(2).unwrap());
+    if !storage.contains_key(source) {
+        return writer.write_error("no such key");
+    }
+    if storage.contains_key(destination) {
+        return writer.write_integer(0);
+    }
+    storage.rename(source, destination);
+    writer.write_integer(1)
+}
+
+pub fn copy(
+    writer: &mut impl WriteResp,
+    storage: &mut RedisStorage,
+    db: usize,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments("copy"));
+    }
+
+    let mut replace = false;
+    let mut destination_db = db;
+    let mut index = 3;
+    while index < request.len() {
+        let option = request.get_str(index).unwrap().to_lowercase();
+        match option.as_str() {
+            "replace" => replace = true,
+            "db" if index + 1 < request.len() => {
+                destination_db = match parse_db_index(storage, request, index + 1) {
+                    Ok(destination_db) => destination_db,
+                    Err(e) => return writer.write_error(e),
+                };
+                index += 1;
+            }
+            _ => return writer.write_error(SYNTAX_ERROR),
+        }
+        index += 1;
+    }
+
+    let (source, destination) = (request.get(1).unwrap(), request.get(2).unwrap());
+    if source == destination && db == destination_db {
+        return writer.write_error(SAME_OBJECT);
+    }
+    if !replace && storage.database(destination_db).contains_key(destination) {
+        return writer.write_integer(0);
+    }
+    let copied = storage.copy_key(db, source, destination_db, destination);
+    writer.write_integer(copied as i64)
+}
+
+pub fn move_key(
+    writer: &mut impl WriteResp,
+    storage: &mut RedisStorage,
+    db: usize,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("move"));
+    }
+
+    let destination_db = match parse_db_index(storage, request, 2) {
+        Ok(destination_db) => destination_db,
+        Err(e) => return writer.write_error(e),
+    };
+    if destination_db == db {
+        return writer.write_error(SAME_OBJECT);
+    }
+    let moved = storage.move_key(request.get(1).unwrap(), db, destination_db);
+    writer.write_integer(moved as i64)
+}
+
+pub fn select(
+    writer: &mut impl WriteResp,
+    storage: &RedisStorage,
+    selected: &mut usize,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("select"));
+    }
+
+    match parse_db_index(storage, request, 1) {
+        Ok(db) => {
+            *selected = db;
+            writer.write_simple_string(OK)
+        }
+        Err(e) => writer.write_error(e),
+    }
+}
+
+pub fn swapdb(
+    writer: &mut impl WriteResp,
+    storage: &mut RedisStorage,
+    blocked: &[(usize, Vec<u8>)],
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("swapdb"));
+    }
+
+    let Some(first) = request.get_int(1) else {
+        return writer.write_error("invalid first DB index");
+    };
+    let Some(second) = request.get_int(2) else {
+        return writer.write_error("invalid second DB index");
+    };
+    let in_range = |index: i64| usize::try_from(index).is_ok_and(|index| index < storage.len());
+    if !in_range(first) || !in_range(second) {
+        return writer.write_error(DB_OUT_OF_RANGE);
+    }
+    storage.swap(first as usize, second as usize, blocked);
+    writer.write_simple_string(OK)
+}
+
+pub fn flushdb(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if let Err(e) = check_flush_mode(request, "flushdb") {
+        return writer.write_error(e);
+    }
+    storage.clear();
+    writer.write_simple_string(OK)
+}
+
+pub fn flushall(
+    writer: &mut impl WriteResp,
+    storage: &mut RedisStorage,
+    request: &Request,
+) -> std::io::Result<()> {
+    if let Err(e) = check_flush_mode(request, "flushall") {
+        return writer.write_error(e);
+    }
+    storage.clear();
+    writer.write_simple_string(OK)
+}
+
+/// Accepts the optional ASYNC or SYNC argument, values being freed right away either way.
+fn check_flush_mode(request: &Request, command: &str) -> Result<(), String> {
+    match request.len() {
+        1 => Ok(()),
+        2 => match request.get_str(1).unwrap().to_lowercase().as_str() {
+            "async" | "sync" => Ok(()),
+            _ => Err(SYNTAX_ERROR.to_string()),
+        },
+        _ => Err(wrong_arguments(command)),
+    }
+}
+
+fn parse_db_index(
+    storage: &RedisStorage,
+    request: &Request,
+    index: usize,
+) -> Result<usize, &'static str> {
+    let db = request.get_int(index).ok_or(NOT_INTEGER)?;
+    usize::try_from(db)
+        .ok()
+        .filter(|db| *db < storage.len())
+        .ok_or(DB_OUT_OF_RANGE)
+}
+
+pub fn randomkey(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 1 {
+        return writer.write_error(wrong_arguments("randomkey"));
+    }
+
+    // Picking an expired key deletes it, so this ends once a live key or no key is left.
+    while !storage.is_empty() {
+        let key = storage.random_key(random_index).unwrap().to_vec();
+        if storage.contains_key(&key) {
+            return writer.write_bulk_sting(&Some(key));
+        }
+    }
+    writer.write_bulk_sting(&None::<&[u8]>)
+}
+
+pub fn dbsize(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 1 {
+        return writer.write_error(wrong_arguments("dbsize"));
+    }
+    writer.write_integer(storage.len() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::keyspace::{
+        copy, del, exists, key_type, move_key, randomkey, rename, renamenx, select, swapdb,
+    };
+    use crate::redis::core::request::Request;
+    use crate::redis::rdb::{Database, RedisStorage};
+
+    #[test]
+    fn test_database_indexes_are_checked() {
+        let mut storage = RedisStorage::new(16);
+        let mut selected = 0;
+        let mut reply = Vec::new();
+        for index in ["15", "16", "-1", "one"] {
+            select(
+                &mut reply,
+                &storage,
+                &mut selected,
+                &Request::from_strs(&["SELECT", index]),
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            reply,
+            b"+OK\r\n-DB index is out of range\r\n-DB index is out of range\r\n\
+              -value is not an integer or out of range\r\n"
+        );
+        assert_eq!(selected, 15);
+
+        let mut reply = Vec::new();
+        storage
+            .database(15)
+            .set(b"a".to_vec(), b"15".to_vec(), None);
+        storage.database(0).set(b"a".to_vec(), b"0".to_vec(), None);
+        move_key(
+            &mut reply,
+            &mut storage,
+            15,
+            &Request::from_strs(&["MOVE", "a", "0"]),
+        )
+        .unwrap();
+        move_key(
+            &mut reply,
+            &mut storage,
+            15,
+            &Request::from_strs(&["MOVE", "a", "16"]),
+        )
+        .unwrap();
+        swapdb(
+            &mut reply,
+            &mut storage,
+            &[],
+            &Request::from_strs(&["SWAPDB", "0", "16"]),
+        )
+        .unwrap();
+        assert_eq!(
+            reply,
+            b":0\r\n-DB index is out of range\r\n-DB index is out of range\r\n"
+        );
+        assert_eq!(
+            storage.database(15).get(b"a").unwrap(),
+            Some(b"15".as_slice())
+        );
+    }
+
+    #[test]
+    fn test_keys_are_renamed_and_copied() {
+        let mut storage = RedisStorage::new(16);
+        let db = storage.database(0);
+        db.set(b"a".to_vec(), b"1".to_vec(), Some(4102444800000));
+        db.set(b"b".to_vec(), b"2".to_vec(), None);
+        let mut reply = Vec::new();
+        rename(&mut reply, db, &Request::from_strs(&["RENAME", "a", "a"])).unwrap();
+        renamenx(&mut reply, db, &Request::from_strs(&["RENAMENX", "a", "a"])).unwrap();
+        renamenx(&mut reply, db, &Request::from_strs(&["RENAMENX", "a", "b"])).unwrap();
+        rename(&mut reply, db, &Request::from_strs(&["RENAME", "a", "b"])).unwrap();
+        rename(&mut reply, db, &Request::from_strs(&["RENAME", "a", "b"])).unwrap();
+        assert_eq!(reply, b"+OK\r\n:0\r\n:0\r\n+OK\r\n-no such key\r\n");
+        assert_eq!(db.get(b"b").unwrap(), Some(b"1".as_slice()));
+        assert_eq!(db.get_expire_time(b"b"), Some(Some(4102444800000)));
+
+        let mut reply = Vec::new();
+        for arguments in [
+            &["COPY", "b", "b"][..],
+            &["COPY", "b", "c"],
+            &["COPY", "b", "c"],
+            &["COPY", "b", "c", "REPLACE", "DB", "1"],
+            &["COPY", "b", "c", "DB", "16"],
+            &["COPY", "x", "c", "REPLACE"],
+        ] {
+            copy(&mut reply, &mut storage, 0, &Request::from_strs(arguments)).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            "-source and destination objects are the same\r\n:1\r\n:0\r\n:1\r\n\
+             -DB index is out of range\r\n:0\r\n"
+        );
+        assert_eq!(
+            storage.database(0).get(b"c").unwrap(),
+            Some(b"1".as_slice())
+        );
+        assert_eq!(
+            storage.database(1).get_expire_time(b"c"),
+            Some(Some(4102444800000))
+        );
+    }
+
+    #[test]
+    fn test_keys_are_counted_and_deleted() {
+        let mut storage = RedisStorage::new(16);
+        let db = storage.database(0);
+        db.set(b"a".to_vec(), b"1".to_vec(), None);
+        db.get_or_insert_list(b"l")
+            .unwrap()
+            .push_back(b"1".to_vec());
+        let mut reply = Vec::new();
+        exists(
+            &mut reply,
+            db,
+            &Request::from_strs(&["EXISTS", "a", "a", "x", "l"]),
+        )
+        .unwrap();
+        key_type(&mut reply, db, &Request::from_strs(&["TYPE", "l"])).unwrap();
+        key_type(&mut reply, db, &Request::from_strs(&["TYPE", "x"])).unwrap();
+        del(
+            &mut reply,
+            db,
+            &Request::from_strs(&["DEL", "a", "a", "x", "l"]),
+        )
+        .unwrap();
+        assert_eq!(reply, b":3\r\n+list\r\n+none\r\n:2\r\n");
+        assert!(db.is_empty());
+    }
+
+    #[test]
+    fn test_randomkey_skips_expired_keys() {
+        let mut storage = Database::default();
+        storage.set(b"live".to_vec(), b"v".to_vec(), None);
+        for i in 0..50 {
+            storage.set(format!("expired:{i}").into_bytes(), b"v".to_vec(), Some(1));
+        }
+
+        let request = Request::from_strs(&["RANDOMKEY"]);
+        for _ in 0..10 {
+            let mut reply = Vec::new();
+            randomkey(&mut reply, &mut storage, &request).unwrap();
+            assert_eq!(reply, b"$4\r\nlive\r\n");
+        }
+
+        storage.remove(b"live");
+        let mut reply = Vec::new();
+        randomkey(&mut reply, &mut storage, &request).unwrap();
+        assert_eq!(reply, b"$-1\r\n");
+        assert!(storage.is_empty());
+    }
+}
@@ -0,0 +1,691 @@
+use crate::redis::core::blocking::{parse_timeout_seconds, Block, BlockResult, TimeoutReply};
+use crate::redis::core::errors::{wrong_arguments, NOT_INTEGER, SYNTAX_ERROR};
+use crate::redis::core::range::normalize_range;
+use crate::redis::core::request::Request;
+use crate::redis::core::WriteResp;
+use crate::redis::rdb::{Database, WrongTypeError};
+
+const NIL: Option<&[u8]> = None;
+const OK: &str = "OK";
+
+pub fn lpush(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    push(writer, storage, request, "lpush", true)
+}
+
+pub fn rpush(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    push(writer, storage, request, "rpush", false)
+}
+
+fn push(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+    command: &str,
+    left: bool,
+) -> std::io::Result<()> {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments(command));
+    }
+
+    let key = request.get(1).unwrap();
+    let list = match storage.get_or_insert_list(key) {
+        Ok(list) => list,
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    for index in 2..request.len() {
+        let element = request.get(index).unwrap().to_vec();
+        if left {
+            list.push_front(element);
+        } else {
+            list.push_back(element);
+        }
+    }
+    let len = list.len();
+    storage.touch(key);
+    writer.write_integer(len as i64)
+}
+
+pub fn lpop(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    pop(writer, storage, request, "lpop", true)
+}
+
+pub fn rpop(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    pop(writer, storage, request, "rpop", false)
+}
+
+fn pop(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+    command: &str,
+    left: bool,
+) -> std::io::Result<()> {
+    if request.len() != 2 && request.len() != 3 {
+        return writer.write_error(wrong_arguments(command));
+    }
+
+    let count = match request.get(2) {
+        None => None,
+        Some(_) => match request.get_int(2) {
+            Some(count) if count >= 0 => Some(count as usize),
+            Some(_) => return writer.write_error("value is out of range, must be positive"),
+            None => return writer.write_error(NOT_INTEGER),
+        },
+    };
+
+    let key = request.get(1).unwrap();
+    let list = match storage.get_list_mut(key) {
+        Ok(Some(list)) => list,
+        Ok(None) if count.is_some() => return writer.write_null_array(),
+        Ok(None) => return writer.write_bulk_sting(&NIL),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    let values: Vec<_> = (0..count.unwrap_or(1))
+        .map_while(|_| {
+            if left {
+                list.pop_front()
+            } else {
+                list.pop_back()
+            }
+        })
+        .map(Some)
+        .collect();
+    if !values.is_empty() {
+        storage.touch(key);
+    }
+    storage.remove_if_empty(key);
+
+    match count {
+        Some(_) => writer.write_array(&values),
+        None => writer.write_bulk_sting(&values.into_iter().next().flatten()),
+    }
+}
+
+pub fn blpop(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> BlockResult {
+    blocking_pop(writer, storage, request, "blpop", true)
+}
+
+pub fn brpop(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> BlockResult {
+    blocking_pop(writer, storage, request, "brpop", false)
+}
+
+/// Pops from the first non-empty list among the keys, or blocks until one of them gets an
+/// element.
+fn blocking_pop(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+    command: &str,
+    left: bool,
+) -> BlockResult {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments(command)).map(|_| None);
+    }
+
+    let timeout = match parse_timeout_seconds(request.get(request.len() - 1).unwrap()) {
+        Ok(timeout) => timeout,
+        Err(e) => return writer.write_error(e).map(|_| None),
+    };
+
+    let keys: Vec<_> = (1..request.len() - 1)
+        .map(|index| request.get(index).unwrap())
+        .collect();
+    for key in &keys {
+        let list = match storage.get_list_mut(key) {
+            Ok(Some(list)) => list,
+            Ok(None) => continue,
+            Err(e) => return writer.write_error(e.to_string()).map(|_| None),
+        };
+        let element = if left {
+            list.pop_front()
+        } else {
+            list.pop_back()
+        };
+        storage.touch(key);
+        storage.remove_if_empty(key);
+        return writer
+            .write_array(&[Some(key.to_vec()), element])
+            .map(|_| None);
+    }
+
+    let keys = keys.into_iter().map(<[u8]>::to_vec).collect();
+    Ok(Some(Block::new(keys, timeout, TimeoutReply::NullArray)))
+}
+
+pub fn lmove(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 5 {
+        return writer.write_error(wrong_arguments("lmove"));
+    }
+
+    let (Some(from_left), Some(to_left)) = (parse_side(request, 3), parse_side(request, 4)) else {
+        return writer.write_error(SYNTAX_ERROR);
+    };
+    let (source, destination) = (request.get(1).unwrap(), request.get(2).unwrap());
+    match move_element(storage, source, destination, from_left, to_left) {
+        Ok(element) => writer.write_bulk_sting(&element),
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn blmove(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> BlockResult {
+    if request.len() != 6 {
+        return writer.write_error(wrong_arguments("blmove")).map(|_| None);
+    }
+
+    let (Some(from_left), Some(to_left)) = (parse_side(request, 3), parse_side(request, 4)) else {
+        return writer.write_error(SYNTAX_ERROR).map(|_| None);
+    };
+    let timeout = match parse_timeout_seconds(request.get(5).unwrap()) {
+        Ok(timeout) => timeout,
+        Err(e) => return writer.write_error(e).map(|_| None),
+    };
+    let (source, destination) = (request.get(1).unwrap(), request.get(2).unwrap());
+    match move_element(storage, source, destination, from_left, to_left) {
+        Ok(Some(element)) => writer.write_bulk_sting(&Some(element)).map(|_| None),
+        Ok(None) => Ok(Some(Block::new(
+            vec![source.to_vec()],
+            timeout,
+            TimeoutReply::NullBulk,
+        ))),
+        Err(e) => writer.write_error(e.to_string()).map(|_| None),
+    }
+}
+
+/// Pops an element from `source` and pushes it to `destination`, which may be the same list.
+fn move_element(
+    storage: &mut Database,
+    source: &[u8],
+    destination: &[u8],
+    from_left: bool,
+    to_left: bool,
+) -> Result<Option<Vec<u8>>, WrongTypeError> {
+    if storage.get_list(source)?.is_none() {
+        return Ok(None);
+    }
+    storage.get_list(destination)?;
+
+    let list = storage.get_list_mut(source)?.unwrap();
+    let element = if from_left {
+        list.pop_front()
+    } else {
+        list.pop_back()
+    };
+    let Some(element) = element else {
+        return Ok(None);
+    };
+    storage.touch(source);
+    storage.remove_if_empty(source);
+
+    let list = storage.get_or_insert_list(destination)?;
+    if to_left {
+        list.push_front(element.clone());
+    } else {
+        list.push_back(element.clone());
+    }
+    storage.touch(destination);
+    Ok(Some(element))
+}
+
+/// Parses LEFT or RIGHT, returning whether it is the left side.
+fn parse_side(request: &Request, index: usize) -> Option<bool> {
+    match request.get_str(index)?.to_lowercase().as_str() {
+        "left" => Some(true),
+        "right" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn lrange(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("lrange"));
+    }
+
+    let (Some(start), Some(stop)) = (request.get_int(2), request.get_int(3)) else {
+        return writer.write_error(NOT_INTEGER);
+    };
+
+    let list = match storage.get_list(request.get(1).unwrap()) {
+        Ok(Some(list)) => list,
+        Ok(None) => return writer.write_array(&[NIL; 0]),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    let values: Vec<_> = match normalize_range(start, stop, list.len()) {
+        Some((start, stop)) => list.range(start..=stop).map(Some).collect(),
+        None => Vec::new(),
+    };
+    writer.write_array(&values)
+}
+
+pub fn llen(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("llen"));
+    }
+
+    match storage.get_list(request.get(1).unwrap()) {
+        Ok(list) => writer.write_integer(list.map_or(0, |list| list.len()) as i64),
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn lindex(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("lindex"));
+    }
+
+    let Some(index) = request.get_int(2) else {
+        return writer.write_error(NOT_INTEGER);
+    };
+
+    match storage.get_list(request.get(1).unwrap()) {
+        Ok(Some(list)) => {
+            let value = resolve_index(index, list.len()).and_then(|index| list.get(index));
+            writer.write_bulk_sting(&value)
+        }
+        Ok(None) => writer.write_bulk_sting(&NIL),
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn lset(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("lset"));
+    }
+
+    let Some(index) = request.get_int(2) else {
+        return writer.write_error(NOT_INTEGER);
+    };
+
+    let key = request.get(1).unwrap();
+    let list = match storage.get_list_mut(key) {
+        Ok(Some(list)) => list,
+        Ok(None) => return writer.write_error("no such key"),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    match resolve_index(index, list.len()) {
+        Some(index) => {
+            list[index] = request.get(3).unwrap().to_vec();
+            storage.touch(key);
+            writer.write_simple_string(OK)
+        }
+        None => writer.write_error("index out of range"),
+    }
+}
+
+pub fn lrem(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("lrem"));
+    }
+
+    let Some(count) = request.get_int(2) else {
+        return writer.write_error(NOT_INTEGER);
+    };
+
+    let key = request.get(1).unwrap();
+    let element = request.get(3).unwrap();
+    let list = match storage.get_list_mut(key) {
+        Ok(Some(list)) => list,
+        Ok(None) => return writer.write_integer(0),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    let limit = if count == 0 {
+        usize::MAX
+    } else {
+        count.unsigned_abs() as usize
+    };
+    let matches = (0..list.len()).filter(|index| list[*index] == element);
+    let mut positions: Vec<usize> = if count < 0 {
+        matches.rev().take(limit).collect()
+    } else {
+        matches.take(limit).collect()
+    };
+    positions.sort_unstable();
+
+    let mut index = 0;
+    list.retain(|_| {
+        let keep = positions.binary_search(&index).is_err();
+        index += 1;
+        keep
+    });
+    if !positions.is_empty() {
+        storage.touch(key);
+    }
+    storage.remove_if_empty(key);
+    writer.write_integer(positions.len() as i64)
+}
+
+pub fn ltrim(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("ltrim"));
+    }
+
+    let (Some(start), Some(stop)) = (request.get_int(2), request.get_int(3)) else {
+        return writer.write_error(NOT_INTEGER);
+    };
+
+    let key = request.get(1).unwrap();
+    let list = match storage.get_list_mut(key) {
+        Ok(Some(list)) => list,
+        Ok(None) => return writer.write_simple_string(OK),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    let len = list.len();
+    match normalize_range(start, stop, len) {
+        Some((start, stop)) => {
+            list.truncate(stop + 1);
+            list.drain(..start);
+        }
+        None => list.clear(),
+    }
+    if list.len() != len {
+        storage.touch(key);
+    }
+    storage.remove_if_empty(key);
+    writer.write_simple_string(OK)
+}
+
+pub fn linsert(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 5 {
+        return writer.write_error(wrong_arguments("linsert"));
+    }
+
+    let position = request.get_str(2).unwrap().to_lowercase();
+    let after = match position.as_str() {
+        "before" => false,
+        "after" => true,
+        _ => return writer.write_error(SYNTAX_ERROR),
+    };
+
+    let key = request.get(1).unwrap();
+    let list = match storage.get_list_mut(key) {
+        Ok(Some(list)) => list,
+        Ok(None) => return writer.write_integer(0),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    let pivot = request.get(3).unwrap();
+    match list.iter().position(|element| element == pivot) {
+        Some(index) => {
+            let index = if after { index + 1 } else { index };
+            list.insert(index, request.get(4).unwrap().to_vec());
+            let len = list.len();
+            storage.touch(key);
+            writer.write_integer(len as i64)
+        }
+        None => writer.write_integer(-1),
+    }
+}
+
+pub fn lpos(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 3 || request.len().is_multiple_of(2) {
+        return writer.write_error(wrong_arguments("lpos"));
+    }
+
+    let mut rank = 1;
+    let mut count = None;
+    let mut max_len = 0;
+    for index in (3..request.len()).step_by(2) {
+        let option = request.get_str(index).unwrap().to_lowercase();
+        let Some(value) = request.get_int(index + 1) else {
+            return writer.write_error(NOT_INTEGER);
+        };
+        match option.as_str() {
+            "rank" if value == 0 => {
+                return writer.write_error(
+                    "RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list",
+                )
+            }
+            "rank" => rank = value,
+            "count" if value < 0 => return writer.write_error("COUNT can't be negative"),
+            "count" => count = Some(value as usize),
+            "maxlen" if value < 0 => return writer.write_error("MAXLEN can't be negative"),
+            "maxlen" => max_len = value as usize,
+            _ => return writer.write_error(SYNTAX_ERROR),
+        }
+    }
+
+    let list = match storage.get_list(request.get(1).unwrap()) {
+        Ok(Some(list)) => list,
+        Ok(None) if count.is_some() => return writer.write_array(&[NIL; 0]),
+        Ok(None) => return writer.write_bulk_sting(&NIL),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    let element = request.get(2).unwrap();
+    let scanned = if max_len == 0 { list.len() } else { max_len };
+    let indexes: Box<dyn Iterator<Item = usize>> = if rank > 0 {
+        Box::new(0..list.len())
+    } else {
+        Box::new((0..list.len()).rev())
+    };
+    let limit = match count {
+        None => 1,
+        Some(0) => usize::MAX,
+        Some(count) => count,
+    };
+    let positions: Vec<usize> = indexes
+        .take(scanned)
+        .filter(|index| list[*index] == element)
+        .skip(rank.unsigned_abs() as usize - 1)
+        .take(limit)
+        .collect();
+
+    match count {
+        Some(_) => {
+            writer.write_array_header(positions.len())?;
+            for position in positions {
+                writer.write_integer(position as i64)?;
+            }
+            Ok(())
+        }
+        None => match positions.first() {
+            Some(position) => writer.write_integer(*position as i64),
+            None => writer.write_bulk_sting(&NIL),
+        },
+    }
+}
+
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    if index < 0 || index >= len as i64 {
+        None
+    } else {
+        Some(index as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::list::{linsert, lpop, lpos, lpush, lrange, lrem, ltrim, rpush};
+    use crate::redis::core::request::Request;
+    use crate::redis::rdb::Database;
+
+    #[test]
+    fn test_elements_are_pushed_popped_and_removed() {
+        let mut storage = Database::default();
+        let mut reply = Vec::new();
+        lpush(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["LPUSH", "l", "b", "a"]),
+        )
+        .unwrap();
+        rpush(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["RPUSH", "l", "a", "c", "a"]),
+        )
+        .unwrap();
+        lrange(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["LRANGE", "l", "-3", "100"]),
+        )
+        .unwrap();
+        lrem(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["LREM", "l", "-1", "a"]),
+        )
+        .unwrap();
+        linsert(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["LINSERT", "l", "AFTER", "x", "y"]),
+        )
+        .unwrap();
+        lpop(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["LPOP", "l", "2"]),
+        )
+        .unwrap();
+        assert_eq!(
+            reply,
+            b":2\r\n:5\r\n*3\r\n$1\r\na\r\n$1\r\nc\r\n$1\r\na\r\n:1\r\n:-1\r\n\
+              *2\r\n$1\r\na\r\n$1\r\nb\r\n"
+        );
+
+        let mut reply = Vec::new();
+        ltrim(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["LTRIM", "l", "5", "10"]),
+        )
+        .unwrap();
+        lpop(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["LPOP", "l"]),
+        )
+        .unwrap();
+        lpop(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["LPOP", "l", "1"]),
+        )
+        .unwrap();
+        assert_eq!(reply, b"+OK\r\n$-1\r\n*-1\r\n");
+        assert!(!storage.contains_key(b"l"));
+    }
+
+    #[test]
+    fn test_lpos_honours_rank_count_and_maxlen() {
+        let mut storage = Database::default();
+        let mut reply = Vec::new();
+        rpush(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["RPUSH", "l", "a", "b", "c", "1", "2", "3", "c", "c"]),
+        )
+        .unwrap();
+
+        let mut reply = Vec::new();
+        for arguments in [
+            &["LPOS", "l", "c"][..],
+            &["LPOS", "l", "c", "RANK", "-1"],
+            &["LPOS", "l", "c", "COUNT", "0", "RANK", "2"],
+            &["LPOS", "l", "c", "COUNT", "0", "MAXLEN", "3"],
+            &["LPOS", "l", "x"],
+            &["LPOS", "l", "c", "RANK", "0"],
+        ] {
+            lpos(&mut reply, &mut storage, &Request::from_strs(arguments)).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            ":2\r\n:7\r\n*2\r\n:6\r\n:7\r\n*1\r\n:2\r\n$-1\r\n\
+             -RANK can't be zero: use 1 to start from the first match, 2 from the second ... \
+             or use negative to start from the end of the list\r\n"
+        );
+    }
+
+    #[test]
+    fn test_commands_reject_other_types() {
+        let mut storage = Database::default();
+        storage.set(b"s".to_vec(), b"value".to_vec(), None);
+        let mut reply = Vec::new();
+        lpush(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["LPUSH", "s", "a"]),
+        )
+        .unwrap();
+        lrange(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["LRANGE", "s", "0", "-1"]),
+        )
+        .unwrap();
+        let wrong_type = "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n";
+        assert_eq!(String::from_utf8(reply).unwrap(), wrong_type.repeat(2));
+        assert_eq!(storage.get(b"s").unwrap(), Some(b"value".as_slice()));
+    }
+}
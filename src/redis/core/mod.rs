@@ -1,20 +1,40 @@
+mod blocking;
+mod commands;
 mod configuration;
+mod consumer_group;
 mod echo;
+mod errors;
+mod expire;
+mod float;
 mod get_config;
 mod get_keys;
 mod get_value;
+mod glob;
+mod hash;
 mod info;
+mod keyspace;
+mod list;
 mod ping;
 mod psync;
+mod pubsub;
+mod random;
+mod range;
 mod read_resp;
 mod replconf;
+mod replication;
 mod request;
 mod request_handler;
 mod save;
+mod scan;
+mod set;
 mod set_key_value;
+mod sorted_set;
+mod stream;
+mod string;
+mod transaction;
 mod write_resp;
 
 pub use configuration::Configuration;
-pub use read_resp::ReadResp;
+pub use read_resp::{Frame, ReadResp};
 pub use request_handler::RequestHandler;
 pub use write_resp::WriteResp;
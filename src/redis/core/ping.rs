@@ -1,5 +1,23 @@
+use crate::redis::core::errors::wrong_arguments;
+use crate::redis::core::request::Request;
 use crate::redis::core::WriteResp;
 
-pub fn ping(writer: &mut impl WriteResp) -> std::io::Result<()> {
-    writer.write_simple_string("PONG")
+/// Replies PONG or echoes the message, as a push-like array for a subscribed client.
+pub fn ping(
+    writer: &mut impl WriteResp,
+    subscribed: bool,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() > 2 {
+        return writer.write_error(wrong_arguments("ping"));
+    }
+
+    let message = request.get(1);
+    if subscribed {
+        writer.write_array(&[Some(b"pong".as_slice()), Some(message.unwrap_or_default())])
+    } else if let Some(message) = message {
+        writer.write_bulk_sting(&Some(message))
+    } else {
+        writer.write_simple_string("PONG")
+    }
 }
@@ -1,10 +1,33 @@
+use crate::redis::core::replication::Replication;
 use crate::redis::core::request::Request;
 use crate::redis::core::WriteResp;
+use crate::redis::rdb::RedisStorage;
+use mio::Token;
 
-pub fn psync(writer: &mut impl WriteResp, request: &Request) -> std::io::Result<()> {
+/// Sends a full copy of the dataset to a replica, after which it receives every write.
+pub fn psync(
+    writer: &mut impl WriteResp,
+    storage: &mut RedisStorage,
+    replication: &mut Replication,
+    client: Token,
+    request: &Request,
+) -> std::io::Result<()> {
     if request.len() != 3 {
         return writer.write_error("wrong number of arguments for 'psync' command");
     }
 
-    writer.write_simple_string("FULLRESYNC 8371b4fb1155b71f4a04d3e1bc3e18c4a990aeeb 0")
+    let rdb = match storage.snapshot() {
+        Ok(rdb) => rdb,
+        Err(e) => {
+            log::error!("error creating snapshot for replica: {}", e);
+            return writer.write_error("unable to create the RDB snapshot");
+        }
+    };
+    writer.write_simple_string(format!(
+        "FULLRESYNC 8371b4fb1155b71f4a04d3e1bc3e18c4a990aeeb {}",
+        replication.offset()
+    ))?;
+    writer.write_rdb(&rdb)?;
+    replication.add_replica(client);
+    Ok(())
 }
@@ -0,0 +1,407 @@
+use crate::redis::core::errors::wrong_arguments;
+use crate::redis::core::glob::glob_match;
+use crate::redis::core::request::Request;
+use crate::redis::core::WriteResp;
+use mio::Token;
+use std::collections::{HashMap, HashSet};
+
+/// The commands a client may still send once it subscribed to a channel or pattern.
+const SUBSCRIBED_COMMANDS: [&str; 9] = [
+    "subscribe",
+    "ssubscribe",
+    "unsubscribe",
+    "sunsubscribe",
+    "psubscribe",
+    "punsubscribe",
+    "ping",
+    "quit",
+    "reset",
+];
+
+#[derive(Default)]
+struct Subscriptions {
+    channels: HashSet<Vec<u8>>,
+    patterns: HashSet<Vec<u8>>,
+}
+
+impl Subscriptions {
+    fn len(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+}
+
+/// The channel registry: which clients listen to which channels and patterns.
+#[derive(Default)]
+pub struct PubSub {
+    channels: HashMap<Vec<u8>, HashSet<Token>>,
+    patterns: HashMap<Vec<u8>, HashSet<Token>>,
+    clients: HashMap<Token, Subscriptions>,
+}
+
+impl PubSub {
+    pub fn is_subscribed(&self, client: Token) -> bool {
+        self.clients.contains_key(&client)
+    }
+
+    /// Whether `command` is allowed for `client`, a subscribed client being restricted to
+    /// managing its subscriptions.
+    pub fn allows(&self, client: Token, command: &str) -> bool {
+        !self.is_subscribed(client) || SUBSCRIBED_COMMANDS.contains(&command)
+    }
+
+    /// Sends `message` to the clients subscribed to `channel` or to a pattern matching it,
+    /// appending the frames to their pending replies. Returns how many clients received it.
+    pub fn publish(
+        &self,
+        channel: &[u8],
+        message: &[u8],
+        replies: &mut HashMap<Token, Vec<u8>>,
+    ) -> std::io::Result<usize> {
+        let mut receivers = 0;
+        for client in self.channels.get(channel).into_iter().flatten() {
+            let reply = replies.entry(*client).or_default();
+            reply.write_array(&[Some(b"message".as_slice()), Some(channel), Some(message)])?;
+            receivers += 1;
+        }
+        for (pattern, clients) in &self.patterns {
+            if !glob_match(pattern, channel) {
+                continue;
+            }
+            for client in clients {
+                let reply = replies.entry(*client).or_default();
+                reply.write_array(&[
+                    Some(b"pmessage".as_slice()),
+                    Some(pattern),
+                    Some(channel),
+                    Some(message),
+                ])?;
+                receivers += 1;
+            }
+        }
+        Ok(receivers)
+    }
+
+    pub fn disconnect(&mut self, client: Token) {
+        let Some(subscriptions) = self.clients.remove(&client) else {
+            return;
+        };
+        for channel in subscriptions.channels {
+            remove_subscriber(&mut self.channels, &channel, client);
+        }
+        for pattern in subscriptions.patterns {
+            remove_subscriber(&mut self.patterns, &pattern, client);
+        }
+    }
+
+    /// Subscribes to a channel, or a pattern if `pattern` is set, and returns how many
+    /// subscriptions the client has.
+    fn subscribe(&mut self, client: Token, name: &[u8], pattern: bool) -> usize {
+        let subscriptions = self.clients.entry(client).or_default();
+        let (names, registry) = if pattern {
+            (&mut subscriptions.patterns, &mut self.patterns)
+        } else {
+            (&mut subscriptions.channels, &mut self.channels)
+        };
+        if names.insert(name.to_vec()) {
+            registry.entry(name.to_vec()).or_default().insert(client);
+        }
+        subscriptions.len()
+    }
+
+    /// Unsubscribes from a channel, or a pattern if `pattern` is set, and returns how many
+    /// subscriptions the client has left.
+    fn unsubscribe(&mut self, client: Token, name: &[u8], pattern: bool) -> usize {
+        let Some(subscriptions) = self.clients.get_mut(&client) else {
+            return 0;
+        };
+        let (names, registry) = if pattern {
+            (&mut subscriptions.patterns, &mut self.patterns)
+        } else {
+            (&mut subscriptions.channels, &mut self.channels)
+        };
+        if names.remove(name) {
+            remove_subscriber(registry, name, client);
+        }
+        let count = subscriptions.len();
+        if count == 0 {
+            self.clients.remove(&client);
+        }
+        count
+    }
+
+    /// The channels, or patterns if `pattern` is set, the client is subscribed to.
+    fn subscriptions(&self, client: Token, pattern: bool) -> Vec<Vec<u8>> {
+        let Some(subscriptions) = self.clients.get(&client) else {
+            return Vec::new();
+        };
+        let names = if pattern {
+            &subscriptions.patterns
+        } else {
+            &subscriptions.channels
+        };
+        names.iter().cloned().collect()
+    }
+}
+
+fn remove_subscriber(registry: &mut HashMap<Vec<u8>, HashSet<Token>>, name: &[u8], client: Token) {
+    if let Some(clients) = registry.get_mut(name) {
+        clients.remove(&client);
+        if clients.is_empty() {
+            registry.remove(name);
+        }
+    }
+}
+
+pub fn subscribe(
+    writer: &mut impl WriteResp,
+    pubsub: &mut PubSub,
+    client: Token,
+    request: &Request,
+) -> std::io::Result<()> {
+    add_subscriptions(writer, pubsub, client, request, false)
+}
+
+pub fn psubscribe(
+    writer: &mut impl WriteResp,
+    pubsub: &mut PubSub,
+    client: Token,
+    request: &Request,
+) -> std::io::Result<()> {
+    add_subscriptions(writer, pubsub, client, request, true)
+}
+
+fn add_subscriptions(
+    writer: &mut impl WriteResp,
+    pubsub: &mut PubSub,
+    client: Token,
+    request: &Request,
+    pattern: bool,
+) -> std::io::Result<()> {
+    let kind = if pattern { "psubscribe" } else { "subscribe" };
+    if request.len() < 2 {
+        return writer.write_error(wrong_arguments(kind));
+    }
+
+    for index in 1..request.len() {
+        let name = request.get(index).unwrap();
+        let count = pubsub.subscribe(client, name, pattern);
+        write_subscription(writer, kind, Some(name), count)?;
+    }
+    Ok(())
+}
+
+pub fn unsubscribe(
+    writer: &mut impl WriteResp,
+    pubsub: &mut PubSub,
+    client: Token,
+    request: &Request,
+) -> std::io::Result<()> {
+    remove_subscriptions(writer, pubsub, client, request, false)
+}
+
+pub fn punsubscribe(
+    writer: &mut impl WriteResp,
+    pubsub: &mut PubSub,
+    client: Token,
+    request: &Request,
+) -> std::io::Result<()> {
+    remove_subscriptions(writer, pubsub, client, request, true)
+}
+
+/// Removes the given subscriptions, or all of them when none is given, confirming each one.
+fn remove_subscriptions(
+    writer: &mut impl WriteResp,
+    pubsub: &mut PubSub,
+    client: Token,
+    request: &Request,
+    pattern: bool,
+) -> std::io::Result<()> {
+    let kind = if pattern {
+        "punsubscribe"
+    } else {
+        "unsubscribe"
+    };
+    let names = if request.len() > 1 {
+        (1..request.len())
+            .map(|index| request.get(index).unwrap().to_vec())
+            .collect()
+    } else {
+        pubsub.subscriptions(client, pattern)
+    };
+
+    if names.is_empty() {
+        let count = pubsub.clients.get(&client).map_or(0, Subscriptions::len);
+        return write_subscription(writer, kind, None, count);
+    }
+    for name in names {
+        let count = pubsub.unsubscribe(client, &name, pattern);
+        write_subscription(writer, kind, Some(&name), count)?;
+    }
+    Ok(())
+}
+
+fn write_subscription(
+    writer: &mut impl WriteResp,
+    kind: &str,
+    name: Option<&[u8]>,
+    count: usize,
+) -> std::io::Result<()> {
+    writer.write_array_header(3)?;
+    writer.write_bulk_sting(&Some(kind))?;
+    writer.write_bulk_sting(&name)?;
+    writer.write_integer(count as i64)
+}
+
+pub fn publish(
+    writer: &mut impl WriteResp,
+    pubsub: &PubSub,
+    replies: &mut HashMap<Token, Vec<u8>>,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("publish"));
+    }
+
+    let receivers = pubsub.publish(request.get(1).unwrap(), request.get(2).unwrap(), replies)?;
+    writer.write_integer(receivers as i64)
+}
+
+pub fn pubsub(
+    writer: &mut impl WriteResp,
+    pubsub: &PubSub,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 2 {
+        return writer.write_error(wrong_arguments("pubsub"));
+    }
+
+    let subcommand = request.get_str(1).unwrap().to_lowercase();
+    match subcommand.as_str() {
+        "channels" if request.len() <= 3 => {
+            let pattern = request.get(2);
+            let channels: Vec<_> = pubsub
+                .channels
+                .keys()
+                .filter(|channel| pattern.is_none_or(|pattern| glob_match(pattern, channel)))
+                .map(Some)
+                .collect();
+            writer.write_array(&channels)
+        }
+        "numsub" => {
+            writer.write_array_header((request.len() - 2) * 2)?;
+            for index in 2..request.len() {
+                let channel = request.get(index).unwrap();
+                writer.write_bulk_sting(&Some(channel))?;
+                let subscribers = pubsub.channels.get(channel).map_or(0, HashSet::len);
+                writer.write_integer(subscribers as i64)?;
+            }
+            Ok(())
+        }
+        "numpat" if request.len() == 2 => writer.write_integer(pubsub.patterns.len() as i64),
+        _ => writer.write_error(format!(
+            "unknown subcommand or wrong number of arguments for '{}'. Try PUBSUB HELP.",
+            request.get_str(1).unwrap()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::pubsub::{psubscribe, publish, pubsub, punsubscribe, PubSub};
+    use crate::redis::core::request::Request;
+    use mio::Token;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_publish_reaches_channel_and_pattern_subscribers() {
+        let mut pubsub = PubSub::default();
+        assert_eq!(pubsub.subscribe(Token(1), b"news", false), 1);
+        assert_eq!(pubsub.subscribe(Token(1), b"n*", true), 2);
+        pubsub.subscribe(Token(2), b"sport", false);
+
+        let mut replies = HashMap::new();
+        assert_eq!(pubsub.publish(b"news", b"hi", &mut replies).unwrap(), 2);
+        assert_eq!(
+            replies[&Token(1)],
+            b"*3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$2\r\nhi\r\n\
+              *4\r\n$8\r\npmessage\r\n$2\r\nn*\r\n$4\r\nnews\r\n$2\r\nhi\r\n"
+        );
+        assert!(!replies.contains_key(&Token(2)));
+
+        assert_eq!(pubsub.unsubscribe(Token(1), b"news", false), 1);
+        pubsub.disconnect(Token(1));
+        assert!(!pubsub.is_subscribed(Token(1)));
+        assert!(pubsub.patterns.is_empty());
+        assert!(pubsub.is_subscribed(Token(2)));
+    }
+
+    #[test]
+    fn test_psubscribe_delivers_matching_channels() {
+        let mut registry = PubSub::default();
+        let mut reply = Vec::new();
+        psubscribe(
+            &mut reply,
+            &mut registry,
+            Token(1),
+            &Request::from_strs(&["PSUBSCRIBE", "h?llo"]),
+        )
+        .unwrap();
+        psubscribe(
+            &mut reply,
+            &mut registry,
+            Token(1),
+            &Request::from_strs(&["PSUBSCRIBE", "h[ae]*"]),
+        )
+        .unwrap();
+        assert_eq!(
+            reply,
+            b"*3\r\n$10\r\npsubscribe\r\n$5\r\nh?llo\r\n:1\r\n\
+              *3\r\n$10\r\npsubscribe\r\n$6\r\nh[ae]*\r\n:2\r\n"
+        );
+        assert!(registry.allows(Token(1), "ping"));
+        assert!(!registry.allows(Token(1), "get"));
+
+        let mut reply = Vec::new();
+        let mut replies = HashMap::new();
+        for channel in ["hello", "hallway", "hxllo", "world"] {
+            let message = Request::from_strs(&["PUBLISH", channel, "hi"]);
+            publish(&mut reply, &registry, &mut replies, &message).unwrap();
+        }
+        pubsub(
+            &mut reply,
+            &registry,
+            &Request::from_strs(&["PUBSUB", "NUMPAT"]),
+        )
+        .unwrap();
+        assert_eq!(reply, b":2\r\n:1\r\n:1\r\n:0\r\n:2\r\n");
+        let delivered = String::from_utf8(replies.remove(&Token(1)).unwrap()).unwrap();
+        assert_eq!(delivered.matches("pmessage").count(), 4);
+        assert!(delivered.ends_with("$5\r\nhxllo\r\n$2\r\nhi\r\n"));
+        assert!(!delivered.contains("world"));
+
+        let mut reply = Vec::new();
+        let unsubscribe = Request::from_strs(&["PUNSUBSCRIBE", "h?llo"]);
+        punsubscribe(&mut reply, &mut registry, Token(1), &unsubscribe).unwrap();
+        punsubscribe(
+            &mut reply,
+            &mut registry,
+            Token(1),
+            &Request::from_strs(&["PUNSUBSCRIBE"]),
+        )
+        .unwrap();
+        punsubscribe(
+            &mut reply,
+            &mut registry,
+            Token(1),
+            &Request::from_strs(&["PUNSUBSCRIBE"]),
+        )
+        .unwrap();
+        assert_eq!(
+            reply,
+            b"*3\r\n$12\r\npunsubscribe\r\n$5\r\nh?llo\r\n:1\r\n\
+              *3\r\n$12\r\npunsubscribe\r\n$6\r\nh[ae]*\r\n:0\r\n\
+              *3\r\n$12\r\npunsubscribe\r\n$-1\r\n:0\r\n"
+        );
+        assert!(!registry.is_subscribed(Token(1)));
+    }
+}
@@ -0,0 +1,20 @@
+/// Resolves an inclusive `start..=stop` range that may use negative, from-the-end indexes
+/// against a sequence of `len` elements. Returns `None` when the range selects nothing.
+pub fn normalize_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    if start > stop || start >= len {
+        None
+    } else {
+        Some((start as usize, stop as usize))
+    }
+}
@@ -1,4 +1,8 @@
+/// The elements of a RESP frame along with its size in bytes.
+pub type Frame = (Vec<Vec<u8>>, usize);
+
 pub trait ReadResp {
     type Error;
-    fn read_resp(&self) -> Result<Vec<String>, Self::Error>;
+    /// Reads the complete frames received so far.
+    fn read_resp(&mut self) -> Result<Vec<Frame>, Self::Error>;
 }
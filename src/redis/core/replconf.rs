@@ -1,21 +1,35 @@
+use crate::redis::core::replication::Replication;
 use crate::redis::core::request::Request;
 use crate::redis::core::WriteResp;
+use mio::Token;
 
-pub fn replconf(writer: &mut impl WriteResp, request: &Request) -> std::io::Result<()> {
+pub fn replconf(
+    writer: &mut impl WriteResp,
+    replication: &mut Replication,
+    client: Token,
+    request: &Request,
+) -> std::io::Result<()> {
     if request.len() != 3 {
         return writer.write_error("wrong number of arguments for 'replconf' command");
     }
 
-    let config = request.get(1).unwrap().as_str();
-    match config {
+    let config = request.get_str(1).unwrap();
+    match config.to_lowercase().as_str() {
         "listening-port" => {
-            let port = request.get(2).unwrap().parse::<u16>();
+            let port = request.get_str(2).unwrap().parse::<u16>();
             match port {
                 Ok(_) => writer.write_simple_string("OK"),
                 Err(_) => writer.write_error("invalid port number"),
             }
         }
         "capa" => writer.write_simple_string("OK"),
+        // Replicas report the offset they reached without expecting a reply.
+        "ack" => {
+            if let Some(offset) = request.get_int(2) {
+                replication.acknowledge(client, offset as u64);
+            }
+            Ok(())
+        }
         _ => writer.write_error(format!("unknown config: '{}'", config)),
     }
 }
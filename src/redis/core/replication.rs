@@ -0,0 +1,781 @@
+use crate::redis::core::blocking::parse_timeout_millis;
+use crate::redis::core::errors::{wrong_arguments, NOT_INTEGER};
+use crate::redis::core::request::Request;
+use crate::redis::core::stream::xadd_id_index;
+use crate::redis::core::WriteResp;
+use crate::redis::rdb::{ConsumerGroup, Database, Set};
+use mio::Token;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const EXPIRATION_OPTIONS: [&str; 4] = ["ex", "px", "exat", "pxat"];
+
+/// What forwarding a write needs and that the write is about to destroy.
+pub enum Snapshot {
+    /// The members of the set SPOP removes at random.
+    Members(Set),
+    /// The consumer group XREADGROUP, XCLAIM or XAUTOCLAIM works on in each of its streams.
+    Groups(Vec<(Vec<u8>, ConsumerGroup)>),
+}
+
+/// A client blocked in WAIT until enough replicas acknowledge the offset it waits for.
+struct Waiter {
+    client: Token,
+    offset: u64,
+    replicas: i64,
+    deadline: Option<Instant>,
+}
+
+/// The replicas of this server, to which every write that modified the dataset is forwarded.
+#[derive(Default)]
+pub struct Replication {
+    /// The replicas along with the offset each last acknowledged.
+    replicas: HashMap<Token, u64>,
+    /// The database the replicas run the forwarded writes in, `None` until one is selected.
+    selected: Option<usize>,
+    /// The writes of the transaction being executed, forwarded wrapped in MULTI and EXEC once
+    /// it completes so that replicas apply them at once.
+    transaction: Option<Vec<u8>>,
+    /// The replication offset: how many bytes were forwarded to the replicas.
+    offset: u64,
+    /// How many bytes of the master's replication stream were applied, when replicating.
+    applied: u64,
+    waiters: Vec<Waiter>,
+}
+
+impl Replication {
+    pub fn add_replica(&mut self, client: Token) {
+        // The RDB file the replica received holds everything forwarded so far.
+        self.replicas.insert(client, self.offset);
+        // The new replica starts in database 0, wherever the others are.
+        self.selected = None;
+    }
+
+    pub fn disconnect(&mut self, client: Token) {
+        self.replicas.remove(&client);
+        self.waiters.retain(|waiter| waiter.client != client);
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn applied(&self) -> u64 {
+        self.applied
+    }
+
+    /// Sets where the master's replication stream starts, as told by FULLRESYNC.
+    pub fn set_applied(&mut self, offset: u64) {
+        self.applied = offset;
+    }
+
+    /// Records that `size` more bytes of the master's replication stream were applied.
+    pub fn advance(&mut self, size: usize) {
+        self.applied += size as u64;
+    }
+
+    /// Records the offset a replica reported with REPLCONF ACK.
+    pub fn acknowledge(&mut self, client: Token, offset: u64) {
+        if let Some(acknowledged) = self.replicas.get_mut(&client) {
+            *acknowledged = offset.max(*acknowledged);
+        }
+    }
+
+    /// How many replicas acknowledged `offset` or a later one.
+    fn acknowledged(&self, offset: u64) -> usize {
+        self.replicas
+            .values()
+            .filter(|acknowledged| **acknowledged >= offset)
+            .count()
+    }
+
+    pub fn is_waiting(&self, client: Token) -> bool {
+        self.waiters.iter().any(|waiter| waiter.client == client)
+    }
+
+    /// How long until the earliest WAIT deadline.
+    pub fn next_timeout(&self) -> Option<Duration> {
+        self.waiters
+            .iter()
+            .filter_map(|waiter| waiter.deadline)
+            .min()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Takes the clients waiting in WAIT that enough replicas acknowledged or whose timeout
+    /// elapsed, along with how many replicas acknowledged their offset.
+    pub fn take_answered(&mut self) -> Vec<(Token, usize)> {
+        let now = Instant::now();
+        let mut answered = Vec::new();
+        let mut index = 0;
+        while index < self.waiters.len() {
+            let waiter = &self.waiters[index];
+            let acknowledged = self.acknowledged(waiter.offset);
+            if acknowledged as i64 >= waiter.replicas
+                || waiter.deadline.is_some_and(|deadline| deadline <= now)
+            {
+                answered.push((self.waiters.remove(index).client, acknowledged));
+            } else {
+                index += 1;
+            }
+        }
+        answered
+    }
+
+    /// Captures what forwarding a write needs and that the write is about to destroy.
+    pub fn before_write(
+        &self,
+        command: &str,
+        request: &Request,
+        storage: &mut Database,
+    ) -> Option<Snapshot> {
+        if self.replicas.is_empty() {
+            return None;
+        }
+        match command {
+            "spop" => {
+                let set = storage.get_set(request.get(1)?).ok().flatten()?;
+                Some(Snapshot::Members(set.clone()))
+            }
+            "xreadgroup" | "xclaim" | "xautoclaim" => {
+                let GroupKeys { group, keys, .. } = consumer_group_keys(request)?;
+                let groups = keys
+                    .into_iter()
+                    .filter_map(|key| {
+                        let stream = storage.get_stream(key).ok().flatten()?;
+                        Some((key.to_vec(), stream.group(group)?.clone()))
+                    })
+                    .collect();
+                Some(Snapshot::Groups(groups))
+            }
+            _ => None,
+        }
+    }
+
+    /// Forwards a write that modified the dataset to the replicas, appending it to their
+    /// pending replies, rewritten so that it has the same effect on them.
+    pub fn propagate(
+        &mut self,
+        db: usize,
+        request: &Request,
+        storage: &mut Database,
+        before: Option<Snapshot>,
+        replies: &mut HashMap<Token, Vec<u8>>,
+    ) -> std::io::Result<()> {
+        if self.replicas.is_empty() {
+            return Ok(());
+        }
+
+        let mut frames = Vec::new();
+        if self.selected != Some(db) {
+            write_request(
+                &mut frames,
+                &Request::new(vec![b"SELECT".to_vec(), db.to_string().into_bytes()]),
+            )?;
+            self.selected = Some(db);
+        }
+        for request in rewrite(request, storage, before) {
+            write_request(&mut frames, &request)?;
+        }
+        match &mut self.transaction {
+            Some(transaction) => transaction.extend(frames),
+            None => self.send(&frames, replies),
+        }
+        Ok(())
+    }
+
+    /// Holds back the writes forwarded until `end_transaction`.
+    pub fn begin_transaction(&mut self) {
+        self.transaction = Some(Vec::new());
+    }
+
+    pub fn end_transaction(
+        &mut self,
+        replies: &mut HashMap<Token, Vec<u8>>,
+    ) -> std::io::Result<()> {
+        let Some(writes) = self.transaction.take().filter(|writes| !writes.is_empty()) else {
+            return Ok(());
+        };
+        let mut frames = Vec::new();
+        frames.write_array(&[Some("MULTI")])?;
+        frames.extend(writes);
+        frames.write_array(&[Some("EXEC")])?;
+        self.send(&frames, replies);
+        Ok(())
+    }
+
+    /// Asks every replica for the offset it reached.
+    fn request_acknowledgements(
+        &mut self,
+        replies: &mut HashMap<Token, Vec<u8>>,
+    ) -> std::io::Result<()> {
+        let mut frames = Vec::new();
+        frames.write_array(&[Some("REPLCONF"), Some("GETACK"), Some("*")])?;
+        self.send(&frames, replies);
+        Ok(())
+    }
+
+    fn send(&mut self, frames: &[u8], replies: &mut HashMap<Token, Vec<u8>>) {
+        if self.replicas.is_empty() {
+            return;
+        }
+        for replica in self.replicas.keys() {
+            replies
+                .entry(*replica)
+                .or_default()
+                .extend_from_slice(frames);
+        }
+        self.offset += frames.len() as u64;
+    }
+}
+
+/// Blocks until `numreplicas` replicas acknowledged every write forwarded so far, or the
+/// timeout elapses, and replies how many did.
+pub fn wait(
+    writer: &mut impl WriteResp,
+    replication: &mut Replication,
+    client: Token,
+    request: &Request,
+    replies: &mut HashMap<Token, Vec<u8>>,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("wait"));
+    }
+    let Some(replicas) = request.get_int(1) else {
+        return writer.write_error(NOT_INTEGER);
+    };
+    let timeout = match parse_timeout_millis(request.get(2).unwrap()) {
+        Ok(timeout) => timeout,
+        Err(e) => return writer.write_error(e),
+    };
+
+    let offset = replication.offset;
+    let acknowledged = replication.acknowledged(offset);
+    // Inside a transaction WAIT cannot block, it replies right away.
+    if acknowledged as i64 >= replicas || replication.transaction.is_some() {
+        return writer.write_integer(acknowledged as i64);
+    }
+    replication.waiters.push(Waiter {
+        client,
+        offset,
+        replicas,
+        deadline: timeout.and_then(|timeout| Instant::now().checked_add(timeout)),
+    });
+    replication.request_acknowledgements(replies)
+}
+
+fn write_request(writer: &mut impl WriteResp, request: &Request) -> std::io::Result<()> {
+    let arguments: Vec<_> = (0..request.len()).map(|index| request.get(index)).collect();
+    writer.write_array(&arguments)
+}
+
+/// Rewrites a write that succeeded into the commands that have the same effect on a replica
+/// whenever running it again would not: relative expirations become absolute ones, the result
+/// of INCRBYFLOAT is set as is, the members SPOP picked and the ID XADD generated are spelled
+/// out, and consumer group deliveries become explicit claims.
+fn rewrite(request: &Request, storage: &mut Database, before: Option<Snapshot>) -> Vec<Request> {
+    let command = request.get_str(0).unwrap().to_lowercase();
+    // Keyless writes such as FLUSHALL go through the last arm unchanged.
+    match (command.as_str(), request.get(1)) {
+        ("set", Some(key)) => {
+            let mut arguments = vec![
+                b"SET".to_vec(),
+                key.to_vec(),
+                request.get(2).unwrap().to_vec(),
+            ];
+            let mut index = 3;
+            let mut expires = false;
+            while index < request.len() {
+                let option = request.get_str(index).unwrap().to_lowercase();
+                if EXPIRATION_OPTIONS.contains(&option.as_str()) {
+                    expires = true;
+                    index += 2;
+                } else {
+                    // The reply GET asks for only matters to the client.
+                    if option != "get" {
+                        arguments.push(request.get(index).unwrap().to_vec());
+                    }
+                    index += 1;
+                }
+            }
+            if !expires {
+                return vec![Request::new(arguments)];
+            }
+            set_expiring(key, arguments, storage)
+        }
+        ("incrbyfloat", Some(key)) => {
+            let value = storage.get(key).ok().flatten().unwrap_or_default().to_vec();
+            vec![Request::new(vec![
+                b"SET".to_vec(),
+                key.to_vec(),
+                value,
+                b"KEEPTTL".to_vec(),
+            ])]
+        }
+        ("setex" | "psetex", Some(key)) => {
+            let arguments = vec![
+                b"SET".to_vec(),
+                key.to_vec(),
+                request.get(3).unwrap().to_vec(),
+            ];
+            set_expiring(key, arguments, storage)
+        }
+        ("expire" | "pexpire" | "expireat" | "pexpireat" | "getex", Some(key)) => {
+            let request = match storage.get_expire_time(key) {
+                Some(Some(time)) => vec![
+                    b"PEXPIREAT".to_vec(),
+                    key.to_vec(),
+                    time.to_string().into_bytes(),
+                ],
+                Some(None) => vec![b"PERSIST".to_vec(), key.to_vec()],
+                None => vec![b"DEL".to_vec(), key.to_vec()],
+            };
+            vec![Request::new(request)]
+        }
+        ("spop", Some(key)) => {
+            let Some(Snapshot::Members(before)) = before else {
+                return Vec::new();
+            };
+            let after = storage.get_set(key).ok().flatten();
+            let removed: Vec<_> = before
+                .into_iter()
+                .filter(|member| after.is_none_or(|after| !after.contains(member)))
+                .collect();
+            if removed.is_empty() {
+                return Vec::new();
+            }
+            let mut arguments = vec![b"SREM".to_vec(), key.to_vec()];
+            arguments.extend(removed);
+            vec![Request::new(arguments)]
+        }
+        ("xadd", Some(key)) => {
+            let Ok(Some(stream)) = storage.get_stream(key) else {
+                return vec![request.clone()];
+            };
+            let id = stream.last_id();
+            let index = xadd_id_index(request);
+            let mut arguments: Vec<_> = (0..request.len())
+                .map(|index| request.get(index).unwrap().to_vec())
+                .collect();
+            arguments[index] = id.to_string().into_bytes();
+            vec![Request::new(arguments)]
+        }
+        ("xreadgroup" | "xclaim" | "xautoclaim", _) => match before {
+            Some(Snapshot::Groups(groups)) => claims(request, storage, groups),
+            _ => Vec::new(),
+        },
+        _ => vec![request.clone()],
+    }
+}
+
+/// Spells out how a consumer group changed as the commands Redis forwards for it: an XCLAIM
+/// that forces each new or changed pending entry to the consumer, delivery time and count it
+/// now has, or removes a pending entry whose stream entry was deleted, and an XGROUP SETID if
+/// the last delivered ID moved.
+fn claims(
+    request: &Request,
+    storage: &mut Database,
+    groups: Vec<(Vec<u8>, ConsumerGroup)>,
+) -> Vec<Request> {
+    let GroupKeys {
+        group: name,
+        consumer,
+        ..
+    } = consumer_group_keys(request).unwrap();
+    let mut requests = Vec::new();
+    for (key, before) in groups {
+        let Some(after) = storage
+            .get_stream(&key)
+            .ok()
+            .flatten()
+            .and_then(|stream| stream.group(name))
+        else {
+            continue;
+        };
+        let claim = |consumer: &[u8], id: String| {
+            vec![
+                b"XCLAIM".to_vec(),
+                key.clone(),
+                name.to_vec(),
+                consumer.to_vec(),
+                b"0".to_vec(),
+                id.into_bytes(),
+            ]
+        };
+
+        for (id, entry) in &after.pending {
+            if before.pending.get(id) == Some(entry) {
+                continue;
+            }
+            let mut arguments = claim(&entry.consumer, id.to_string());
+            arguments.extend([
+                b"TIME".to_vec(),
+                entry.delivery_time.to_string().into_bytes(),
+                b"RETRYCOUNT".to_vec(),
+                entry.delivery_count.to_string().into_bytes(),
+                b"FORCE".to_vec(),
+                b"JUSTID".to_vec(),
+            ]);
+            requests.push(Request::new(arguments));
+        }
+        for id in before.pending.keys() {
+            if !after.pending.contains_key(id) {
+                let mut arguments = claim(consumer, id.to_string());
+                arguments.extend([b"FORCE".to_vec(), b"JUSTID".to_vec()]);
+                requests.push(Request::new(arguments));
+            }
+        }
+
+        if after.last_id != before.last_id || after.entries_read != before.entries_read {
+            let entries_read = after.entries_read.map_or(-1, |read| read as i64);
+            requests.push(Request::new(vec![
+                b"XGROUP".to_vec(),
+                b"SETID".to_vec(),
+                key.clone(),
+                name.to_vec(),
+                after.last_id.to_string().into_bytes(),
+                b"ENTRIESREAD".to_vec(),
+                entries_read.to_string().into_bytes(),
+            ]));
+        }
+    }
+    requests
+}
+
+/// The group, the consumer and the keys an XREADGROUP, XCLAIM or XAUTOCLAIM works on.
+struct GroupKeys<'a> {
+    group: &'a [u8],
+    consumer: &'a [u8],
+    keys: Vec<&'a [u8]>,
+}
+
+fn consumer_group_keys(request: &Request) -> Option<GroupKeys<'_>> {
+    if !request.get_str(0)?.eq_ignore_ascii_case("xreadgroup") {
+        return Some(GroupKeys {
+            group: request.get(2)?,
+            consumer: request.get(3)?,
+            keys: vec![request.get(1)?],
+        });
+    }
+
+    let mut group = None;
+    let mut index = 1;
+    loop {
+        match request.get_str(index)?.to_lowercase().as_str() {
+            "group" => {
+                group = Some((request.get(index + 1)?, request.get(index + 2)?));
+                index += 3;
+            }
+            "count" | "block" => index += 2,
+            "noack" => index += 1,
+            "streams" => break,
+            _ => return None,
+        }
+    }
+    let (group, consumer) = group?;
+    let streams = (request.len() - index - 1) / 2;
+    let keys = (index + 1..index + 1 + streams)
+        .map(|index| request.get(index).unwrap())
+        .collect();
+    Some(GroupKeys {
+        group,
+        consumer,
+        keys,
+    })
+}
+
+/// Completes a SET whose expiration options were left out with the absolute expiration of the
+/// key, or deletes the key if it already expired.
+fn set_expiring(key: &[u8], mut arguments: Vec<Vec<u8>>, storage: &mut Database) -> Vec<Request> {
+    match storage.get_expire_time(key) {
+        Some(time) => {
+            if let Some(time) = time {
+                arguments.push(b"PXAT".to_vec());
+                arguments.push(time.to_string().into_bytes());
+            }
+            vec![Request::new(arguments)]
+        }
+        None => vec![Request::new(vec![b"DEL".to_vec(), key.to_vec()])],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::consumer_group::{xautoclaim, xclaim, xgroup, xreadgroup};
+    use crate::redis::core::replication::{rewrite, wait, Replication};
+    use crate::redis::core::request::Request;
+    use crate::redis::core::stream::{xadd, xdel};
+    use crate::redis::core::string::incrbyfloat;
+    use crate::redis::rdb::{Database, StreamId};
+    use mio::Token;
+    use std::collections::HashMap;
+
+    fn arguments(requests: Vec<Request>) -> Vec<Vec<String>> {
+        requests
+            .iter()
+            .map(|request| {
+                (0..request.len())
+                    .map(|index| request.get_str(index).unwrap().to_string())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the delivery time and count of a pending entry of group `g` in stream `s`.
+    fn delivery(storage: &mut Database, id: StreamId) -> (String, String) {
+        let stream = storage.get_stream(b"s").unwrap().unwrap();
+        let entry = &stream.group(b"g").unwrap().pending[&id];
+        (
+            entry.delivery_time.to_string(),
+            entry.delivery_count.to_string(),
+        )
+    }
+
+    /// Fills stream `s` with entries 1-1 and 1-2 and creates group `g` before them.
+    fn stream_with_group(storage: &mut Database) {
+        let mut reply = Vec::new();
+        xadd(
+            &mut reply,
+            storage,
+            &Request::from_strs(&["XADD", "s", "1-1", "f", "v"]),
+        )
+        .unwrap();
+        xadd(
+            &mut reply,
+            storage,
+            &Request::from_strs(&["XADD", "s", "1-2", "f", "v"]),
+        )
+        .unwrap();
+        xgroup(
+            &mut reply,
+            storage,
+            &Request::from_strs(&["XGROUP", "CREATE", "s", "g", "0"]),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_writes_reach_replicas_with_absolute_expirations() {
+        let mut replication = Replication::default();
+        replication.add_replica(Token(1));
+        let mut storage = Database::default();
+        let mut replies = HashMap::new();
+
+        storage.set(b"key".to_vec(), b"value".to_vec(), Some(4102444800000));
+        let set = Request::from_strs(&["SET", "key", "value", "EX", "10", "GET"]);
+        replication
+            .propagate(2, &set, &mut storage, None, &mut replies)
+            .unwrap();
+        storage.remove(b"key");
+        let expire = Request::from_strs(&["EXPIRE", "key", "10"]);
+        replication
+            .propagate(2, &expire, &mut storage, None, &mut replies)
+            .unwrap();
+
+        assert_eq!(
+            replies[&Token(1)],
+            b"*2\r\n$6\r\nSELECT\r\n$1\r\n2\r\n\
+              *5\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n\
+              $4\r\nPXAT\r\n$13\r\n4102444800000\r\n\
+              *2\r\n$3\r\nDEL\r\n$3\r\nkey\r\n"
+        );
+    }
+
+    #[test]
+    fn test_wait_is_answered_once_enough_replicas_acknowledge() {
+        let mut replication = Replication::default();
+        replication.add_replica(Token(1));
+        replication.add_replica(Token(2));
+        let mut storage = Database::default();
+        let mut replies = HashMap::new();
+        let set = Request::from_strs(&["SET", "key", "value"]);
+        replication
+            .propagate(0, &set, &mut storage, None, &mut replies)
+            .unwrap();
+        let offset = replication.offset();
+
+        let mut reply = Vec::new();
+        wait(
+            &mut reply,
+            &mut replication,
+            Token(3),
+            &Request::from_strs(&["WAIT", "2", "0"]),
+            &mut replies,
+        )
+        .unwrap();
+        assert!(reply.is_empty());
+        assert!(replication.is_waiting(Token(3)));
+        assert!(
+            replies[&Token(1)].ends_with(b"*3\r\n$8\r\nREPLCONF\r\n$6\r\nGETACK\r\n$1\r\n*\r\n")
+        );
+
+        replication.acknowledge(Token(1), offset);
+        assert!(replication.take_answered().is_empty());
+        replication.acknowledge(Token(2), offset + 37);
+        assert_eq!(replication.take_answered(), vec![(Token(3), 2)]);
+        assert!(!replication.is_waiting(Token(3)));
+    }
+
+    #[test]
+    fn test_set_is_forwarded_without_get() {
+        let mut storage = Database::default();
+        let set = Request::from_strs(&["SET", "key", "value", "GET", "XX"]);
+        assert_eq!(
+            arguments(rewrite(&set, &mut storage, None)),
+            vec![vec!["SET", "key", "value", "XX"]]
+        );
+    }
+
+    #[test]
+    fn test_incrbyfloat_is_forwarded_as_its_result() {
+        let mut storage = Database::default();
+        storage.set(b"key".to_vec(), b"10.5".to_vec(), Some(4102444800000));
+        let increment = Request::from_strs(&["INCRBYFLOAT", "key", "0.1"]);
+        incrbyfloat(&mut Vec::new(), &mut storage, &increment).unwrap();
+        assert_eq!(
+            arguments(rewrite(&increment, &mut storage, None)),
+            vec![vec!["SET", "key", "10.6", "KEEPTTL"]]
+        );
+    }
+
+    #[test]
+    fn test_xreadgroup_is_forwarded_as_claims() {
+        let mut replication = Replication::default();
+        replication.add_replica(Token(1));
+        let mut storage = Database::default();
+        stream_with_group(&mut storage);
+
+        let read = Request::from_strs(&[
+            "XREADGROUP",
+            "GROUP",
+            "g",
+            "alice",
+            "COUNT",
+            "1",
+            "STREAMS",
+            "s",
+            ">",
+        ]);
+        let before = replication.before_write("xreadgroup", &read, &mut storage);
+        xreadgroup(&mut Vec::new(), &mut storage, &read).unwrap();
+        let (time, count) = delivery(&mut storage, StreamId::new(1, 1));
+        assert_eq!(
+            arguments(rewrite(&read, &mut storage, before)),
+            vec![
+                vec![
+                    "XCLAIM",
+                    "s",
+                    "g",
+                    "alice",
+                    "0",
+                    "1-1",
+                    "TIME",
+                    &time,
+                    "RETRYCOUNT",
+                    &count,
+                    "FORCE",
+                    "JUSTID",
+                ],
+                vec!["XGROUP", "SETID", "s", "g", "1-1", "ENTRIESREAD", "1"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_xclaim_is_forwarded_with_its_delivery() {
+        let mut replication = Replication::default();
+        replication.add_replica(Token(1));
+        let mut storage = Database::default();
+        stream_with_group(&mut storage);
+        let read = Request::from_strs(&["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", ">"]);
+        xreadgroup(&mut Vec::new(), &mut storage, &read).unwrap();
+
+        let claim = Request::from_strs(&["XCLAIM", "s", "g", "bob", "0", "1-2", "IDLE", "5000"]);
+        let before = replication.before_write("xclaim", &claim, &mut storage);
+        xclaim(&mut Vec::new(), &mut storage, &claim).unwrap();
+        let (time, count) = delivery(&mut storage, StreamId::new(1, 2));
+        assert_eq!(count, "2");
+        assert_eq!(
+            arguments(rewrite(&claim, &mut storage, before)),
+            vec![vec![
+                "XCLAIM",
+                "s",
+                "g",
+                "bob",
+                "0",
+                "1-2",
+                "TIME",
+                &time,
+                "RETRYCOUNT",
+                &count,
+                "FORCE",
+                "JUSTID",
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_xautoclaim_is_forwarded_with_deleted_entries() {
+        let mut replication = Replication::default();
+        replication.add_replica(Token(1));
+        let mut storage = Database::default();
+        stream_with_group(&mut storage);
+        let read = Request::from_strs(&["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", ">"]);
+        xreadgroup(&mut Vec::new(), &mut storage, &read).unwrap();
+        xdel(
+            &mut Vec::new(),
+            &mut storage,
+            &Request::from_strs(&["XDEL", "s", "1-1"]),
+        )
+        .unwrap();
+
+        let claim = Request::from_strs(&["XAUTOCLAIM", "s", "g", "carol", "0", "0-0"]);
+        let before = replication.before_write("xautoclaim", &claim, &mut storage);
+        xautoclaim(&mut Vec::new(), &mut storage, &claim).unwrap();
+        let (time, count) = delivery(&mut storage, StreamId::new(1, 2));
+        assert_eq!(
+            arguments(rewrite(&claim, &mut storage, before)),
+            vec![
+                vec![
+                    "XCLAIM",
+                    "s",
+                    "g",
+                    "carol",
+                    "0",
+                    "1-2",
+                    "TIME",
+                    &time,
+                    "RETRYCOUNT",
+                    &count,
+                    "FORCE",
+                    "JUSTID",
+                ],
+                vec!["XCLAIM", "s", "g", "carol", "0", "1-1", "FORCE", "JUSTID"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keyless_writes_are_forwarded_as_is() {
+        let mut replication = Replication::default();
+        replication.add_replica(Token(1));
+        let mut storage = Database::default();
+        let mut replies = HashMap::new();
+        for flush in [&["FLUSHALL"][..], &["FLUSHDB", "ASYNC"]] {
+            replication
+                .propagate(
+                    0,
+                    &Request::from_strs(flush),
+                    &mut storage,
+                    None,
+                    &mut replies,
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            replies[&Token(1)],
+            b"*2\r\n$6\r\nSELECT\r\n$1\r\n0\r\n*1\r\n$8\r\nFLUSHALL\r\n\
+              *2\r\n$7\r\nFLUSHDB\r\n$5\r\nASYNC\r\n"
+        );
+    }
+}
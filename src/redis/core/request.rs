@@ -1,18 +1,34 @@
-#[derive(Debug)]
+use std::borrow::Cow;
+
+#[derive(Debug, Clone)]
 pub struct Request {
-    value: Vec<String>,
+    value: Vec<Vec<u8>>,
 }
 
 impl Request {
-    pub fn new(value: Vec<String>) -> Self {
+    pub fn new(value: Vec<Vec<u8>>) -> Self {
         Self { value }
     }
 
-    pub fn get(&self, index: usize) -> Option<&String> {
-        self.value.get(index)
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        self.value.get(index).map(|x| x.as_slice())
+    }
+
+    pub fn get_str(&self, index: usize) -> Option<Cow<'_, str>> {
+        self.get(index).map(String::from_utf8_lossy)
+    }
+
+    pub fn get_int(&self, index: usize) -> Option<i64> {
+        std::str::from_utf8(self.get(index)?).ok()?.parse().ok()
     }
 
     pub fn len(&self) -> usize {
         self.value.len()
     }
+
+    /// Builds a request from text arguments, the way tests spell out commands.
+    #[cfg(test)]
+    pub fn from_strs(arguments: &[&str]) -> Self {
+        Self::new(arguments.iter().map(|x| x.as_bytes().to_vec()).collect())
+    }
 }
@@ -1,24 +1,80 @@
+use crate::redis::core::blocking::{BlockResult, BlockedClients};
+use crate::redis::core::commands::{check_command, is_write};
 use crate::redis::core::configuration::Configuration;
+use crate::redis::core::consumer_group::{
+    xack, xautoclaim, xclaim, xgroup, xinfo, xpending, xreadgroup,
+};
 use crate::redis::core::echo::echo;
+use crate::redis::core::errors::unknown_command;
+use crate::redis::core::expire::{
+    expire, expireat, expiretime, persist, pexpire, pexpireat, pexpiretime, pttl, ttl,
+};
 use crate::redis::core::get_config::get_config;
 use crate::redis::core::get_keys::get_keys;
 use crate::redis::core::get_value::get_value;
+use crate::redis::core::hash::{
+    hdel, hexists, hget, hgetall, hincrby, hincrbyfloat, hkeys, hlen, hmget, hrandfield, hscan,
+    hset, hsetnx, hstrlen, hvals,
+};
 use crate::redis::core::info::info;
+use crate::redis::core::keyspace::{
+    copy, dbsize, del, exists, flushall, flushdb, key_type, move_key, randomkey, rename, renamenx,
+    select, swapdb, touch, unlink,
+};
+use crate::redis::core::list::{
+    blmove, blpop, brpop, lindex, linsert, llen, lmove, lpop, lpos, lpush, lrange, lrem, lset,
+    ltrim, rpop, rpush,
+};
 use crate::redis::core::ping::ping;
 use crate::redis::core::psync::psync;
+use crate::redis::core::pubsub::{
+    psubscribe, publish, pubsub, punsubscribe, subscribe, unsubscribe, PubSub,
+};
 use crate::redis::core::read_resp::ReadResp;
 use crate::redis::core::replconf::replconf;
+use crate::redis::core::replication::{wait, Replication};
 use crate::redis::core::request::Request;
 use crate::redis::core::save::save;
+use crate::redis::core::scan::scan;
+use crate::redis::core::set::{
+    sadd, scard, sdiff, sdiffstore, sinter, sintercard, sinterstore, sismember, smembers,
+    smismember, smove, spop, srandmember, srem, sscan, sunion, sunionstore,
+};
 use crate::redis::core::set_key_value::set_key_value;
+use crate::redis::core::sorted_set::{
+    bzpopmax, bzpopmin, zadd, zcard, zcount, zdiffstore, zincrby, zinterstore, zlexcount, zmscore,
+    zpopmax, zpopmin, zrange, zrangestore, zrank, zrem, zremrangebylex, zremrangebyrank,
+    zremrangebyscore, zrevrank, zscan, zscore, zunionstore,
+};
+use crate::redis::core::stream::{xadd, xdel, xlen, xrange, xread, xrevrange, xtrim};
+use crate::redis::core::string::{
+    append, decr, decrby, getdel, getex, getrange, getset, incr, incrby, incrbyfloat, lcs, mget,
+    mset, msetnx, psetex, setex, setnx, setrange, strlen,
+};
+use crate::redis::core::transaction::{discard, multi, unwatch, watch, Transaction};
 use crate::redis::core::write_resp::WriteResp;
 use crate::redis::rdb::RedisStorage;
+use mio::Token;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::Display;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 pub struct RequestHandler {
     storage: RedisStorage,
     configuration: Rc<Configuration>,
+    blocked: BlockedClients,
+    /// Requests waiting for their client to be unblocked before they run.
+    queued: HashMap<Token, VecDeque<Request>>,
+    /// Replies to clients woken up while handling another client or a timeout.
+    replies: HashMap<Token, Vec<u8>>,
+    /// The database each client selected, those missing using database 0.
+    selected: HashMap<Token, usize>,
+    pubsub: PubSub,
+    transactions: HashMap<Token, Transaction>,
+    replication: Replication,
+    /// Clients that sent QUIT, to be closed once their replies are sent.
+    quitting: HashSet<Token>,
 }
 
 impl RequestHandler {
@@ -26,52 +82,541 @@ impl RequestHandler {
         Self {
             storage,
             configuration,
+            blocked: BlockedClients::default(),
+            queued: HashMap::new(),
+            replies: HashMap::new(),
+            selected: HashMap::new(),
+            pubsub: PubSub::default(),
+            transactions: HashMap::new(),
+            replication: Replication::default(),
+            quitting: HashSet::new(),
         }
     }
 
-    pub fn handle_request(
+    pub fn handle_requests(
         &mut self,
+        client: Token,
         stream: &mut (impl ReadResp + WriteResp),
     ) -> Result<(), Error> {
-        let request = stream.read_resp();
-        let request = match request {
-            Ok(request) => {
-                if request.is_empty() {
-                    return Err(Error {
-                        msg: "empty request".to_string(),
-                    });
-                }
-                Request::new(request)
-            }
-            Err(_) => {
-                return Err(Error {
-                    msg: "can not read request".to_string(),
-                })
+        let requests = stream.read_resp().map_err(|_| Error {
+            msg: "can not read request".to_string(),
+        })?;
+        self.queued.entry(client).or_default().extend(
+            requests
+                .into_iter()
+                .filter(|(message, _)| !message.is_empty())
+                .map(|(message, _)| Request::new(message)),
+        );
+        self.run_queued(client, stream)
+    }
+
+    /// Applies the writes streamed by the master this server replicates. Nothing is replied
+    /// but the acknowledgement of the bytes applied so far that REPLCONF GETACK asks for.
+    pub fn handle_master(
+        &mut self,
+        master: Token,
+        stream: &mut (impl ReadResp + WriteResp),
+    ) -> Result<(), Error> {
+        let frames = stream.read_resp().map_err(|_| Error {
+            msg: "can not read replication stream".to_string(),
+        })?;
+        for (message, size) in frames {
+            if message.is_empty() {
+                self.replication.advance(size);
+                continue;
             }
-        };
+            let request = Request::new(message);
+            log::info!("master: {:?}", request);
+            let result = if is_getack(&request) {
+                let offset = self.replication.applied().to_string();
+                stream.write_array(&[Some("REPLCONF"), Some("ACK"), Some(offset.as_str())])
+            } else {
+                // The writes were served on the master, so none of them blocks here.
+                self.execute(master, &mut std::io::sink(), &request)
+                    .map(|_| ())
+            };
+            result.map_err(|_| Error {
+                msg: "cannot write response".to_string(),
+            })?;
+            self.replication.advance(size);
+            self.serve_ready_keys()?;
+        }
+        Ok(())
+    }
+
+    /// Sets where the replication stream of the master this server replicates starts.
+    pub fn set_master_offset(&mut self, offset: u64) {
+        self.replication.set_applied(offset);
+    }
+
+    /// How long the event loop may wait before a blocked client times out.
+    pub fn next_timeout(&self) -> Option<Duration> {
+        match (self.blocked.next_timeout(), self.replication.next_timeout()) {
+            (Some(blocked), Some(waiting)) => Some(blocked.min(waiting)),
+            (blocked, waiting) => blocked.or(waiting),
+        }
+    }
+
+    /// Answers the blocked clients whose timeout has elapsed, and the clients in WAIT that
+    /// enough replicas acknowledged or whose timeout has elapsed.
+    pub fn handle_timeouts(&mut self) -> Result<(), Error> {
+        for (client, acknowledged) in self.replication.take_answered() {
+            let mut reply = Vec::new();
+            reply
+                .write_integer(acknowledged as i64)
+                .map_err(|_| Error {
+                    msg: "cannot write response".to_string(),
+                })?;
+            self.resume(client, reply)?;
+        }
+        for client in self.blocked.timed_out() {
+            let Some((_, on_timeout)) = self.blocked.unblock(client) else {
+                continue;
+            };
+            let mut reply = Vec::new();
+            on_timeout.write(&mut reply).map_err(|_| Error {
+                msg: "cannot write response".to_string(),
+            })?;
+            self.resume(client, reply)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes expired keys for at most `time_limit`.
+    pub fn active_expire_cycle(&mut self, time_limit: Duration) {
+        let deleted = self
+            .storage
+            .active_expire_cycle(Instant::now() + time_limit);
+        if deleted > 0 {
+            log::debug!("active expire cycle deleted {} keys", deleted);
+        }
+    }
+
+    /// Takes the replies that must be sent to clients other than the one being handled.
+    pub fn take_replies(&mut self) -> HashMap<Token, Vec<u8>> {
+        std::mem::take(&mut self.replies)
+    }
+
+    /// Whether the client sent QUIT, after which its connection must be closed.
+    pub fn has_quit(&self, client: Token) -> bool {
+        self.quitting.contains(&client)
+    }
+
+    pub fn disconnect(&mut self, client: Token) {
+        self.quitting.remove(&client);
+        self.blocked.unblock(client);
+        self.queued.remove(&client);
+        self.replies.remove(&client);
+        self.selected.remove(&client);
+        self.pubsub.disconnect(client);
+        self.replication.disconnect(client);
+        if let Some(mut transaction) = self.transactions.remove(&client) {
+            transaction.unwatch(&mut self.storage);
+        }
+    }
+
+    fn db(&self, client: Token) -> usize {
+        self.selected.get(&client).copied().unwrap_or(0)
+    }
+
+    /// Runs the requests of a client until there are none left or one of them blocks.
+    fn run_queued(&mut self, client: Token, stream: &mut impl WriteResp) -> Result<(), Error> {
+        while !self.blocked.is_blocked(client) && !self.replication.is_waiting(client) {
+            let Some(request) = self.queued.get_mut(&client).and_then(VecDeque::pop_front) else {
+                self.queued.remove(&client);
+                break;
+            };
+            self.handle_request(client, stream, request)?;
+        }
+        Ok(())
+    }
+
+    fn handle_request(
+        &mut self,
+        client: Token,
+        stream: &mut impl WriteResp,
+        request: Request,
+    ) -> Result<(), Error> {
         log::info!("{:?}", request);
-        let binding = request.get(0).unwrap().to_lowercase();
+        let block = self.execute(client, stream, &request).map_err(|_| Error {
+            msg: "cannot write response".to_string(),
+        })?;
+        if let Some(mut block) = block {
+            let request = block.request.take().unwrap_or(request);
+            self.blocked.block(client, self.db(client), request, block);
+        }
+        self.serve_ready_keys()
+    }
+
+    /// Executes again the requests of the clients blocked on keys that may have become ready,
+    /// in the order the clients blocked.
+    fn serve_ready_keys(&mut self) -> Result<(), Error> {
+        loop {
+            let keys = self.storage.take_ready_keys();
+            if keys.is_empty() {
+                return Ok(());
+            }
+            for (db, key) in keys {
+                for client in self.blocked.waiters(db, &key) {
+                    let Some(request) = self.blocked.request(client).cloned() else {
+                        continue;
+                    };
+                    let mut reply = Vec::new();
+                    let block = self
+                        .execute(client, &mut reply, &request)
+                        .map_err(|_| Error {
+                            msg: "cannot write response".to_string(),
+                        })?;
+                    if block.is_none() {
+                        self.blocked.unblock(client);
+                        self.resume(client, reply)?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Queues the reply of a client that was just unblocked and runs the requests it sent in
+    /// the meantime.
+    fn resume(&mut self, client: Token, reply: Vec<u8>) -> Result<(), Error> {
+        let mut replies = self.replies.remove(&client).unwrap_or_default();
+        replies.extend(reply);
+        self.run_queued(client, &mut replies)?;
+        let later = self.replies.entry(client).or_default();
+        replies.append(later);
+        *later = replies;
+        Ok(())
+    }
+
+    /// Executes a request, returning what to wait for if it is a blocking command that cannot
+    /// be served yet.
+    fn execute(
+        &mut self,
+        client: Token,
+        stream: &mut impl WriteResp,
+        request: &Request,
+    ) -> BlockResult {
+        let binding = request.get_str(0).unwrap().to_lowercase();
         let command = binding.as_str();
+        if !self.pubsub.allows(client, command) {
+            let message = format!(
+                "Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET \
+                 are allowed in this context",
+                command
+            );
+            return stream.write_error(message).map(|_| None);
+        }
+        if self
+            .transactions
+            .get(&client)
+            .is_some_and(|transaction| transaction.queues(command))
+        {
+            return self.queue(client, stream, command, request).map(|_| None);
+        }
+
+        let db = self.db(client);
+        let dirty = self.storage.dirty();
+        let before = self
+            .replication
+            .before_write(command, request, self.storage.database(db));
+        let block = self.dispatch(client, stream, command, request)?;
+        if self.storage.dirty() != dirty && is_write(command) {
+            self.replication.propagate(
+                db,
+                request,
+                self.storage.database(db),
+                before,
+                &mut self.replies,
+            )?;
+        }
+        Ok(block)
+    }
+
+    /// Runs a command, returning what to wait for if it is a blocking command that cannot be
+    /// served yet.
+    fn dispatch(
+        &mut self,
+        client: Token,
+        stream: &mut impl WriteResp,
+        command: &str,
+        request: &Request,
+    ) -> BlockResult {
+        let db = self.db(client);
+        let storage = self.storage.database(db);
         let result = match command {
-            "ping" => ping(stream),
-            "echo" => echo(stream, &request),
-            "get" => get_value(stream, &mut self.storage, &request),
-            "set" => set_key_value(stream, &mut self.storage, &request),
-            "config" => get_config(stream, &request, &self.configuration),
-            "keys" => get_keys(stream, &mut self.storage),
+            "blpop" => return blpop(stream, storage, request),
+            "brpop" => return brpop(stream, storage, request),
+            "blmove" => return blmove(stream, storage, request),
+            "bzpopmin" => return bzpopmin(stream, storage, request),
+            "bzpopmax" => return bzpopmax(stream, storage, request),
+            "xread" => return xread(stream, storage, request),
+            "xreadgroup" => return xreadgroup(stream, storage, request),
+            "ping" => ping(stream, self.pubsub.is_subscribed(client), request),
+            "echo" => echo(stream, request),
+            "get" => get_value(stream, storage, request),
+            "set" => set_key_value(stream, storage, request),
+            "del" => del(stream, storage, request),
+            "unlink" => unlink(stream, storage, request),
+            "exists" => exists(stream, storage, request),
+            "touch" => touch(stream, storage, request),
+            "type" => key_type(stream, storage, request),
+            "rename" => rename(stream, storage, request),
+            "renamenx" => renamenx(stream, storage, request),
+            "copy" => copy(stream, &mut self.storage, db, request),
+            "move" => move_key(stream, &mut self.storage, db, request),
+            "select" => select(
+                stream,
+                &self.storage,
+                self.selected.entry(client).or_default(),
+                request,
+            ),
+            "swapdb" => swapdb(stream, &mut self.storage, &self.blocked.keys(), request),
+            "flushdb" => flushdb(stream, storage, request),
+            "flushall" => flushall(stream, &mut self.storage, request),
+            "randomkey" => randomkey(stream, storage, request),
+            "dbsize" => dbsize(stream, storage, request),
+            "mget" => mget(stream, storage, request),
+            "mset" => mset(stream, storage, request),
+            "msetnx" => msetnx(stream, storage, request),
+            "append" => append(stream, storage, request),
+            "strlen" => strlen(stream, storage, request),
+            "getrange" => getrange(stream, storage, request),
+            "setrange" => setrange(stream, storage, request),
+            "getdel" => getdel(stream, storage, request),
+            "getex" => getex(stream, storage, request),
+            "getset" => getset(stream, storage, request),
+            "setnx" => setnx(stream, storage, request),
+            "setex" => setex(stream, storage, request),
+            "psetex" => psetex(stream, storage, request),
+            "lcs" => lcs(stream, storage, request),
+            "incr" => incr(stream, storage, request),
+            "decr" => decr(stream, storage, request),
+            "incrby" => incrby(stream, storage, request),
+            "decrby" => decrby(stream, storage, request),
+            "incrbyfloat" => incrbyfloat(stream, storage, request),
+            "expire" => expire(stream, storage, request),
+            "pexpire" => pexpire(stream, storage, request),
+            "expireat" => expireat(stream, storage, request),
+            "pexpireat" => pexpireat(stream, storage, request),
+            "ttl" => ttl(stream, storage, request),
+            "pttl" => pttl(stream, storage, request),
+            "expiretime" => expiretime(stream, storage, request),
+            "pexpiretime" => pexpiretime(stream, storage, request),
+            "persist" => persist(stream, storage, request),
+            "subscribe" => subscribe(stream, &mut self.pubsub, client, request),
+            "unsubscribe" => unsubscribe(stream, &mut self.pubsub, client, request),
+            "psubscribe" => psubscribe(stream, &mut self.pubsub, client, request),
+            "punsubscribe" => punsubscribe(stream, &mut self.pubsub, client, request),
+            "publish" => publish(stream, &self.pubsub, &mut self.replies, request),
+            "pubsub" => pubsub(stream, &self.pubsub, request),
+            "quit" => {
+                // The requests sent after QUIT are dropped along with the connection.
+                self.quitting.insert(client);
+                self.queued.remove(&client);
+                stream.write_simple_string("OK")
+            }
+            "reset" => self.reset(client, stream),
+            "multi" => multi(stream, self.transactions.entry(client).or_default()),
+            "exec" => self.exec(client, stream),
+            "discard" => discard(
+                stream,
+                self.transactions.entry(client).or_default(),
+                &mut self.storage,
+            ),
+            "watch" => watch(
+                stream,
+                self.transactions.entry(client).or_default(),
+                &mut self.storage,
+                db,
+                request,
+            ),
+            "unwatch" => unwatch(
+                stream,
+                self.transactions.entry(client).or_default(),
+                &mut self.storage,
+            ),
+            "config" => get_config(stream, request, &self.configuration),
+            "keys" => get_keys(stream, storage, request),
+            "scan" => scan(stream, storage, request),
             "save" => save(stream, &mut self.storage, &self.configuration),
-            "info" => info(stream, &request, &self.configuration),
-            "replconf" => replconf(stream, &request),
-            "psync" => psync(stream, &request),
-            _ => stream.write_error(format!("Unknown command '{}'", command)),
+            "info" => info(
+                stream,
+                request,
+                &self.configuration,
+                self.replication.offset(),
+            ),
+            "replconf" => replconf(stream, &mut self.replication, client, request),
+            "wait" => wait(
+                stream,
+                &mut self.replication,
+                client,
+                request,
+                &mut self.replies,
+            ),
+            "psync" => psync(
+                stream,
+                &mut self.storage,
+                &mut self.replication,
+                client,
+                request,
+            ),
+            "lpush" => lpush(stream, storage, request),
+            "rpush" => rpush(stream, storage, request),
+            "lpop" => lpop(stream, storage, request),
+            "rpop" => rpop(stream, storage, request),
+            "lrange" => lrange(stream, storage, request),
+            "llen" => llen(stream, storage, request),
+            "lindex" => lindex(stream, storage, request),
+            "lset" => lset(stream, storage, request),
+            "lrem" => lrem(stream, storage, request),
+            "ltrim" => ltrim(stream, storage, request),
+            "linsert" => linsert(stream, storage, request),
+            "lpos" => lpos(stream, storage, request),
+            "lmove" => lmove(stream, storage, request),
+            "hset" => hset(stream, storage, request),
+            "hsetnx" => hsetnx(stream, storage, request),
+            "hget" => hget(stream, storage, request),
+            "hmget" => hmget(stream, storage, request),
+            "hdel" => hdel(stream, storage, request),
+            "hgetall" => hgetall(stream, storage, request),
+            "hkeys" => hkeys(stream, storage, request),
+            "hvals" => hvals(stream, storage, request),
+            "hlen" => hlen(stream, storage, request),
+            "hexists" => hexists(stream, storage, request),
+            "hstrlen" => hstrlen(stream, storage, request),
+            "hincrby" => hincrby(stream, storage, request),
+            "hincrbyfloat" => hincrbyfloat(stream, storage, request),
+            "hrandfield" => hrandfield(stream, storage, request),
+            "hscan" => hscan(stream, storage, request),
+            "sadd" => sadd(stream, storage, request),
+            "srem" => srem(stream, storage, request),
+            "smembers" => smembers(stream, storage, request),
+            "sscan" => sscan(stream, storage, request),
+            "sismember" => sismember(stream, storage, request),
+            "smismember" => smismember(stream, storage, request),
+            "scard" => scard(stream, storage, request),
+            "spop" => spop(stream, storage, request),
+            "srandmember" => srandmember(stream, storage, request),
+            "smove" => smove(stream, storage, request),
+            "sinter" => sinter(stream, storage, request),
+            "sinterstore" => sinterstore(stream, storage, request),
+            "sintercard" => sintercard(stream, storage, request),
+            "sunion" => sunion(stream, storage, request),
+            "sunionstore" => sunionstore(stream, storage, request),
+            "sdiff" => sdiff(stream, storage, request),
+            "sdiffstore" => sdiffstore(stream, storage, request),
+            "zadd" => zadd(stream, storage, request),
+            "zincrby" => zincrby(stream, storage, request),
+            "zrem" => zrem(stream, storage, request),
+            "zcard" => zcard(stream, storage, request),
+            "zscore" => zscore(stream, storage, request),
+            "zscan" => zscan(stream, storage, request),
+            "zmscore" => zmscore(stream, storage, request),
+            "zrank" => zrank(stream, storage, request),
+            "zrevrank" => zrevrank(stream, storage, request),
+            "zrange" => zrange(stream, storage, request),
+            "zrangestore" => zrangestore(stream, storage, request),
+            "zcount" => zcount(stream, storage, request),
+            "zlexcount" => zlexcount(stream, storage, request),
+            "zpopmin" => zpopmin(stream, storage, request),
+            "zpopmax" => zpopmax(stream, storage, request),
+            "zremrangebyrank" => zremrangebyrank(stream, storage, request),
+            "zremrangebyscore" => zremrangebyscore(stream, storage, request),
+            "zremrangebylex" => zremrangebylex(stream, storage, request),
+            "zunionstore" => zunionstore(stream, storage, request),
+            "zinterstore" => zinterstore(stream, storage, request),
+            "zdiffstore" => zdiffstore(stream, storage, request),
+            "xadd" => xadd(stream, storage, request),
+            "xrange" => xrange(stream, storage, request),
+            "xrevrange" => xrevrange(stream, storage, request),
+            "xlen" => xlen(stream, storage, request),
+            "xdel" => xdel(stream, storage, request),
+            "xtrim" => xtrim(stream, storage, request),
+            "xgroup" => xgroup(stream, storage, request),
+            "xack" => xack(stream, storage, request),
+            "xpending" => xpending(stream, storage, request),
+            "xclaim" => xclaim(stream, storage, request),
+            "xautoclaim" => xautoclaim(stream, storage, request),
+            "xinfo" => xinfo(stream, storage, request),
+            _ => stream.write_error(unknown_command(command)),
         };
+        result.map(|_| None)
+    }
 
-        result.map_err(|_| Error {
-            msg: "cannot write response".to_string(),
-        })
+    /// Queues a command sent after MULTI, or aborts the transaction if the command cannot run.
+    fn queue(
+        &mut self,
+        client: Token,
+        stream: &mut impl WriteResp,
+        command: &str,
+        request: &Request,
+    ) -> std::io::Result<()> {
+        let transaction = self.transactions.get_mut(&client).unwrap();
+        match check_command(command, request) {
+            Ok(()) => {
+                transaction.queue(request.clone());
+                stream.write_simple_string("QUEUED")
+            }
+            Err(e) => {
+                transaction.abort();
+                stream.write_error(e)
+            }
+        }
+    }
+
+    /// Puts the client back in the state of a new connection: no transaction nor watched
+    /// keys, no subscriptions and database 0.
+    fn reset(&mut self, client: Token, stream: &mut impl WriteResp) -> std::io::Result<()> {
+        if let Some(mut transaction) = self.transactions.remove(&client) {
+            transaction.unwatch(&mut self.storage);
+        }
+        self.pubsub.disconnect(client);
+        self.selected.remove(&client);
+        stream.write_simple_string("RESET")
+    }
+
+    /// Runs the commands queued since MULTI back to back, unless one of them was rejected or a
+    /// watched key was modified.
+    fn exec(&mut self, client: Token, stream: &mut impl WriteResp) -> std::io::Result<()> {
+        let Some(transaction) = self
+            .transactions
+            .get_mut(&client)
+            .filter(|transaction| transaction.is_open())
+        else {
+            return stream.write_error("EXEC without MULTI");
+        };
+        let requests = transaction.take();
+        let dirty = transaction.is_dirty(&mut self.storage);
+        transaction.unwatch(&mut self.storage);
+
+        let Some(requests) = requests else {
+            return stream
+                .write_error("EXECABORT Transaction discarded because of previous errors.");
+        };
+        if dirty {
+            return stream.write_null_array();
+        }
+        stream.write_array_header(requests.len())?;
+        self.replication.begin_transaction();
+        for request in &requests {
+            // Blocking commands cannot wait inside a transaction, they time out right away.
+            if let Some(block) = self.execute(client, stream, request)? {
+                block.on_timeout.write(stream)?;
+            }
+        }
+        self.replication.end_transaction(&mut self.replies)
     }
 }
 
+fn is_getack(request: &Request) -> bool {
+    request
+        .get_str(0)
+        .is_some_and(|command| command.eq_ignore_ascii_case("replconf"))
+        && request
+            .get_str(1)
+            .is_some_and(|option| option.eq_ignore_ascii_case("getack"))
+}
+
 #[derive(thiserror::Error, Debug)]
 pub struct Error {
     msg: String,
@@ -82,3 +627,376 @@ impl Display for Error {
         write!(f, "{}", self.msg)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::configuration::Configuration;
+    use crate::redis::core::request::Request;
+    use crate::redis::core::request_handler::RequestHandler;
+    use crate::redis::core::{Frame, ReadResp, WriteResp};
+    use crate::redis::rdb::RedisStorage;
+    use crate::redis::reader::{MessageReader, MessageReaderError};
+    use mio::Token;
+    use std::io::Write;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    /// A connection whose incoming bytes are parsed like a socket's and whose replies are kept.
+    #[derive(Default)]
+    struct TestStream {
+        reader: MessageReader,
+        written: Vec<u8>,
+    }
+
+    impl TestStream {
+        fn send(&mut self, requests: &[&[&str]]) {
+            for arguments in requests {
+                let arguments: Vec<_> = arguments.iter().map(Some).collect();
+                let mut bytes = Vec::new();
+                bytes.write_array(&arguments).unwrap();
+                self.reader.extend(&bytes);
+            }
+        }
+
+        fn take_written(&mut self) -> String {
+            String::from_utf8(std::mem::take(&mut self.written)).unwrap()
+        }
+    }
+
+    impl ReadResp for TestStream {
+        type Error = MessageReaderError;
+        fn read_resp(&mut self) -> Result<Vec<Frame>, MessageReaderError> {
+            let mut frames = Vec::new();
+            while let Some(frame) = self.reader.next_frame()? {
+                frames.push(frame);
+            }
+            Ok(frames)
+        }
+    }
+
+    impl Write for TestStream {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.written.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn handler() -> RequestHandler {
+        let configuration = Configuration::new(None, None, 6379, None, 0, 16);
+        RequestHandler::new(RedisStorage::new(16), Rc::new(configuration))
+    }
+
+    /// Sends requests from a client, returning what it was replied right away.
+    fn send(handler: &mut RequestHandler, client: Token, requests: &[&[&str]]) -> String {
+        let mut stream = TestStream::default();
+        stream.send(requests);
+        handler.handle_requests(client, &mut stream).unwrap();
+        stream.take_written()
+    }
+
+    /// The replies owed to clients that were woken up, by client number.
+    fn woken(handler: &mut RequestHandler) -> Vec<(usize, String)> {
+        let mut replies: Vec<_> = handler
+            .take_replies()
+            .into_iter()
+            .map(|(client, reply)| (client.0, String::from_utf8(reply).unwrap()))
+            .collect();
+        replies.sort();
+        replies
+    }
+
+    fn run(handler: &mut RequestHandler, client: Token, requests: &[&[&str]]) -> String {
+        let mut reply = Vec::new();
+        for arguments in requests {
+            let request = Request::from_strs(arguments);
+            handler.execute(client, &mut reply, &request).unwrap();
+        }
+        String::from_utf8(reply).unwrap()
+    }
+
+    #[test]
+    fn test_exec_aborts_on_errors_while_queueing_only() {
+        let mut handler = handler();
+        let client = Token(1);
+
+        let reply = run(
+            &mut handler,
+            client,
+            &[
+                &["MULTI"],
+                &["SET", "a", "1"],
+                &["GET"],
+                &["NOSUCH"],
+                &["EXEC"],
+                &["GET", "a"],
+            ],
+        );
+        assert_eq!(
+            reply,
+            "+OK\r\n+QUEUED\r\n-wrong number of arguments for 'get' command\r\n\
+             -Unknown command 'nosuch'\r\n\
+             -EXECABORT Transaction discarded because of previous errors.\r\n$-1\r\n"
+        );
+
+        // Errors while running do not stop the other commands.
+        let reply = run(
+            &mut handler,
+            client,
+            &[
+                &["MULTI"],
+                &["SET", "a", "1"],
+                &["LPUSH", "a", "x"],
+                &["INCR", "a"],
+                &["EXEC"],
+            ],
+        );
+        assert_eq!(
+            reply,
+            "+OK\r\n+QUEUED\r\n+QUEUED\r\n+QUEUED\r\n*3\r\n+OK\r\n\
+             -WRONGTYPE Operation against a key holding the wrong kind of value\r\n:2\r\n"
+        );
+
+        let reply = run(
+            &mut handler,
+            client,
+            &[&["WATCH", "a"], &["MULTI"], &["INCR", "a"]],
+        );
+        assert_eq!(reply, "+OK\r\n+OK\r\n+QUEUED\r\n");
+        run(&mut handler, Token(2), &[&["SET", "a", "5"]]);
+        let reply = run(&mut handler, client, &[&["EXEC"], &["GET", "a"]]);
+        assert_eq!(reply, "*-1\r\n$1\r\n5\r\n");
+    }
+
+    #[test]
+    fn test_blocked_clients_are_served_first_come_first_served() {
+        let mut handler = handler();
+        for client in [3, 1, 2] {
+            assert_eq!(
+                send(&mut handler, Token(client), &[&["BLPOP", "l", "0"]]),
+                ""
+            );
+        }
+
+        assert_eq!(
+            send(&mut handler, Token(9), &[&["RPUSH", "l", "a", "b"]]),
+            ":2\r\n"
+        );
+        assert_eq!(
+            woken(&mut handler),
+            vec![
+                (1, "*2\r\n$1\r\nl\r\n$1\r\nb\r\n".to_string()),
+                (3, "*2\r\n$1\r\nl\r\n$1\r\na\r\n".to_string()),
+            ]
+        );
+
+        send(&mut handler, Token(9), &[&["RPUSH", "l", "c"]]);
+        assert_eq!(
+            woken(&mut handler),
+            vec![(2, "*2\r\n$1\r\nl\r\n$1\r\nc\r\n".to_string())]
+        );
+        assert_eq!(send(&mut handler, Token(9), &[&["EXISTS", "l"]]), ":0\r\n");
+    }
+
+    #[test]
+    fn test_blocked_clients_time_out() {
+        let mut handler = handler();
+        send(&mut handler, Token(1), &[&["BLPOP", "l", "0.01"]]);
+        send(
+            &mut handler,
+            Token(2),
+            &[&["BLMOVE", "l", "m", "LEFT", "LEFT", "0.01"]],
+        );
+        send(
+            &mut handler,
+            Token(3),
+            &[&["XREAD", "BLOCK", "10", "STREAMS", "s", "$"]],
+        );
+        send(&mut handler, Token(4), &[&["BLPOP", "l", "0"]]);
+        assert!(handler.next_timeout().unwrap() <= Duration::from_millis(10));
+
+        std::thread::sleep(Duration::from_millis(20));
+        handler.handle_timeouts().unwrap();
+        assert_eq!(
+            woken(&mut handler),
+            vec![
+                (1, "*-1\r\n".to_string()),
+                (2, "$-1\r\n".to_string()),
+                (3, "*-1\r\n".to_string()),
+            ]
+        );
+        assert_eq!(handler.next_timeout(), None);
+
+        // The client without a timeout is still waiting.
+        send(&mut handler, Token(9), &[&["RPUSH", "l", "a"]]);
+        assert_eq!(
+            woken(&mut handler),
+            vec![(4, "*2\r\n$1\r\nl\r\n$1\r\na\r\n".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_moved_elements_wake_clients_blocked_on_the_destination() {
+        let mut handler = handler();
+        send(
+            &mut handler,
+            Token(1),
+            &[&["BLMOVE", "src", "dst", "RIGHT", "LEFT", "0"]],
+        );
+        send(&mut handler, Token(2), &[&["BLPOP", "dst", "0"]]);
+        send(&mut handler, Token(3), &[&["BZPOPMIN", "z", "0"]]);
+
+        assert_eq!(
+            send(&mut handler, Token(9), &[&["RPUSH", "src", "x", "y"]]),
+            ":2\r\n"
+        );
+        assert_eq!(
+            woken(&mut handler),
+            vec![
+                (1, "$1\r\ny\r\n".to_string()),
+                (2, "*2\r\n$3\r\ndst\r\n$1\r\ny\r\n".to_string()),
+            ]
+        );
+
+        send(
+            &mut handler,
+            Token(9),
+            &[&["ZADD", "z", "2", "b", "1", "a"]],
+        );
+        assert_eq!(
+            woken(&mut handler),
+            vec![(3, "*3\r\n$1\r\nz\r\n$1\r\na\r\n$1\r\n1\r\n".to_string())]
+        );
+        assert_eq!(
+            send(
+                &mut handler,
+                Token(9),
+                &[&["LRANGE", "src", "0", "-1"], &["EXISTS", "dst"]]
+            ),
+            "*1\r\n$1\r\nx\r\n:0\r\n"
+        );
+    }
+
+    #[test]
+    fn test_pipelined_requests_wait_for_the_blocked_command() {
+        let mut handler = handler();
+        let reply = send(
+            &mut handler,
+            Token(1),
+            &[
+                &["GET", "k"],
+                &["BLPOP", "l", "0"],
+                &["GET", "k"],
+                &["PING"],
+            ],
+        );
+        assert_eq!(reply, "$-1\r\n");
+
+        send(&mut handler, Token(9), &[&["SET", "k", "v"]]);
+        assert_eq!(woken(&mut handler), vec![]);
+
+        // Requests sent while blocked join the queue behind the ones already waiting.
+        assert_eq!(send(&mut handler, Token(1), &[&["ECHO", "later"]]), "");
+        send(&mut handler, Token(9), &[&["LPUSH", "l", "a"]]);
+        assert_eq!(
+            woken(&mut handler),
+            vec![(
+                1,
+                "*2\r\n$1\r\nl\r\n$1\r\na\r\n$1\r\nv\r\n+PONG\r\n$5\r\nlater\r\n".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_replica_acknowledges_the_bytes_it_applied() {
+        let mut handler = handler();
+        let mut master = TestStream::default();
+        let ack = |offset: usize| {
+            format!(
+                "*3\r\n$8\r\nREPLCONF\r\n$3\r\nACK\r\n${}\r\n{offset}\r\n",
+                offset.to_string().len()
+            )
+        };
+
+        // SELECT, SET and PING take 23, 29 and 14 bytes, none of them is replied to.
+        master.send(&[
+            &["SELECT", "0"],
+            &["SET", "foo", "1"],
+            &["PING"],
+            &["REPLCONF", "GETACK", "*"],
+        ]);
+        handler.handle_master(Token(0), &mut master).unwrap();
+        assert_eq!(master.take_written(), ack(66));
+
+        // The 37 bytes of the previous GETACK count from then on, as do the 23 of INCR.
+        master.send(&[&["INCR", "foo"], &["REPLCONF", "GETACK", "*"]]);
+        handler.handle_master(Token(0), &mut master).unwrap();
+        assert_eq!(master.take_written(), ack(126));
+        assert_eq!(
+            send(&mut handler, Token(1), &[&["GET", "foo"]]),
+            "$1\r\n2\r\n"
+        );
+
+        // The offset carries on from the one the replication started at.
+        handler.set_master_offset(1000);
+        master.send(&[&["REPLCONF", "GETACK", "*"]]);
+        handler.handle_master(Token(0), &mut master).unwrap();
+        assert_eq!(master.take_written(), ack(1000));
+    }
+
+    #[test]
+    fn test_quit_drops_the_requests_that_follow() {
+        let mut handler = handler();
+        let reply = send(
+            &mut handler,
+            Token(1),
+            &[&["PING"], &["QUIT"], &["SET", "a", "1"]],
+        );
+        assert_eq!(reply, "+PONG\r\n+OK\r\n");
+        assert!(handler.has_quit(Token(1)));
+        assert_eq!(send(&mut handler, Token(2), &[&["EXISTS", "a"]]), ":0\r\n");
+
+        handler.disconnect(Token(1));
+        assert!(!handler.has_quit(Token(1)));
+    }
+
+    #[test]
+    fn test_reset_restores_a_fresh_connection() {
+        let mut handler = handler();
+        let client = Token(1);
+        run(
+            &mut handler,
+            client,
+            &[&["SELECT", "1"], &["SET", "a", "1"]],
+        );
+        let reply = run(
+            &mut handler,
+            client,
+            &[&["WATCH", "a"], &["MULTI"], &["RESET"], &["GET", "a"]],
+        );
+        assert_eq!(reply, "+OK\r\n+OK\r\n+RESET\r\n$-1\r\n");
+
+        let reply = run(
+            &mut handler,
+            client,
+            &[
+                &["SUBSCRIBE", "c"],
+                &["GET", "a"],
+                &["RESET"],
+                &["GET", "a"],
+            ],
+        );
+        assert_eq!(
+            reply,
+            "*3\r\n$9\r\nsubscribe\r\n$1\r\nc\r\n:1\r\n\
+             -Can't execute 'get': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET \
+             are allowed in this context\r\n+RESET\r\n$-1\r\n"
+        );
+        assert_eq!(
+            run(&mut handler, Token(2), &[&["PUBLISH", "c", "m"]]),
+            ":0\r\n"
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use crate::redis::core::errors::{wrong_arguments, NOT_INTEGER, SYNTAX_ERROR};
+use crate::redis::core::glob::glob_match;
+use crate::redis::core::request::Request;
+use crate::redis::core::WriteResp;
+use crate::redis::rdb::Database;
+
+const DEFAULT_COUNT: usize = 10;
+const TYPE_NAMES: [&str; 6] = ["string", "list", "hash", "set", "zset", "stream"];
+
+/// The options shared by SCAN, HSCAN, SSCAN and ZSCAN.
+pub struct ScanOptions<'a> {
+    pub cursor: u64,
+    pub pattern: Option<&'a [u8]>,
+    /// How many elements to visit, before they are filtered by pattern or type.
+    pub count: usize,
+    /// SCAN only: the type of the keys to return.
+    pub type_name: Option<String>,
+    /// HSCAN only: whether to leave the values out.
+    pub no_values: bool,
+}
+
+impl<'a> ScanOptions<'a> {
+    /// Parses the cursor at `index` and the options that follow it.
+    pub fn parse(request: &'a Request, index: usize, command: &str) -> Result<Self, String> {
+        if request.len() <= index {
+            return Err(wrong_arguments(command));
+        }
+
+        let cursor = request
+            .get_str(index)
+            .and_then(|cursor| cursor.parse::<u64>().ok())
+            .ok_or("invalid cursor")?;
+        let mut options = Self {
+            cursor,
+            pattern: None,
+            count: DEFAULT_COUNT,
+            type_name: None,
+            no_values: false,
+        };
+
+        let mut index = index + 1;
+        while index < request.len() {
+            let option = request.get_str(index).unwrap().to_lowercase();
+            let has_argument = index + 1 < request.len();
+            match option.as_str() {
+                "match" if has_argument => options.pattern = request.get(index + 1),
+                "count" if has_argument => match request.get_int(index + 1) {
+                    Some(count) if count >= 1 => options.count = count as usize,
+                    Some(_) => return Err(SYNTAX_ERROR.to_string()),
+                    None => return Err(NOT_INTEGER.to_string()),
+                },
+                "type" if has_argument && command == "scan" => {
+                    let type_name = request.get_str(index + 1).unwrap().to_lowercase();
+                    if !TYPE_NAMES.contains(&type_name.as_str()) {
+                        return Err(format!("unknown type name '{}'", type_name));
+                    }
+                    options.type_name = Some(type_name);
+                }
+                "novalues" if command == "hscan" => {
+                    options.no_values = true;
+                    index += 1;
+                    continue;
+                }
+                _ => return Err(SYNTAX_ERROR.to_string()),
+            }
+            index += 2;
+        }
+        Ok(options)
+    }
+
+    pub fn matches(&self, element: &[u8]) -> bool {
+        self.pattern
+            .is_none_or(|pattern| glob_match(pattern, element))
+    }
+}
+
+/// Writes the cursor to continue from followed by the elements found.
+pub fn write_scan_reply(
+    writer: &mut impl WriteResp,
+    cursor: u64,
+    elements: &[Option<impl AsRef<[u8]>>],
+) -> std::io::Result<()> {
+    writer.write_array_header(2)?;
+    writer.write_bulk_sting(&Some(cursor.to_string()))?;
+    writer.write_array(elements)
+}
+
+pub fn scan(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    let options = match ScanOptions::parse(request, 1, "scan") {
+        Ok(options) => options,
+        Err(e) => return writer.write_error(e),
+    };
+
+    let (cursor, keys) = storage.scan(options.cursor, options.count);
+    let keys: Vec<_> = keys.iter().map(Vec::as_slice).collect();
+    let values = storage.get_values(&keys);
+    let keys: Vec<_> = keys
+        .iter()
+        .zip(values)
+        .filter(|(key, value)| {
+            options.matches(key)
+                && options.type_name.as_ref().is_none_or(|type_name| {
+                    value.is_some_and(|value| value.type_name() == type_name)
+                })
+        })
+        .map(|(key, _)| Some(*key))
+        .collect();
+    write_scan_reply(writer, cursor, &keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::hash::{hscan, hset};
+    use crate::redis::core::request::Request;
+    use crate::redis::core::scan::scan;
+    use crate::redis::rdb::Database;
+    use std::collections::HashSet;
+
+    fn run_scan(storage: &mut Database, arguments: &[&str]) -> String {
+        let mut reply = Vec::new();
+        scan(&mut reply, storage, &Request::from_strs(arguments)).unwrap();
+        String::from_utf8(reply).unwrap()
+    }
+
+    /// Splits a scan reply into the cursor and the elements.
+    fn parse_reply(reply: &str) -> (String, Vec<String>) {
+        let lines: Vec<_> = reply.split("\r\n").collect();
+        let elements = lines[4..].iter().skip(1).step_by(2).map(|x| x.to_string());
+        (lines[2].to_string(), elements.collect())
+    }
+
+    #[test]
+    fn test_scan_filters_by_type_and_pattern() {
+        let mut storage = Database::default();
+        storage.set(b"s1".to_vec(), b"v".to_vec(), None);
+        storage.set(b"s2".to_vec(), b"v".to_vec(), None);
+        storage
+            .get_or_insert_list(b"l1")
+            .unwrap()
+            .push_back(b"v".to_vec());
+        storage
+            .get_or_insert_set(b"t1")
+            .unwrap()
+            .insert(b"v".to_vec());
+
+        let (cursor, mut keys) = parse_reply(&run_scan(
+            &mut storage,
+            &["SCAN", "0", "COUNT", "100", "TYPE", "STRING"],
+        ));
+        keys.sort();
+        assert_eq!(
+            (cursor.as_str(), keys),
+            ("0", vec!["s1".into(), "s2".into()])
+        );
+
+        let (_, keys) = parse_reply(&run_scan(
+            &mut storage,
+            &["SCAN", "0", "COUNT", "100", "MATCH", "*1", "TYPE", "list"],
+        ));
+        assert_eq!(keys, vec!["l1".to_string()]);
+
+        let (_, keys) = parse_reply(&run_scan(&mut storage, &["SCAN", "0", "TYPE", "zset"]));
+        assert!(keys.is_empty());
+    }
+
+    #[test]
+    fn test_scan_rejects_invalid_arguments() {
+        let mut storage = Database::default();
+        let mut reply = String::new();
+        for arguments in [
+            &["SCAN", "0", "COUNT", "0"][..],
+            &["SCAN", "0", "COUNT", "x"],
+            &["SCAN", "x"],
+            &["SCAN", "-1"],
+            &["SCAN", "0", "TYPE", "nosuch"],
+            &["SCAN", "0", "NOVALUES"],
+            &["SCAN"],
+        ] {
+            reply += &run_scan(&mut storage, arguments);
+        }
+        assert_eq!(
+            reply,
+            "-syntax error\r\n-value is not an integer or out of range\r\n-invalid cursor\r\n\
+             -invalid cursor\r\n-unknown type name 'nosuch'\r\n-syntax error\r\n\
+             -wrong number of arguments for 'scan' command\r\n"
+        );
+    }
+
+    #[test]
+    fn test_hscan_leaves_values_out_with_novalues() {
+        let mut storage = Database::default();
+        let mut reply = Vec::new();
+        let request = Request::from_strs(&["HSET", "h", "a", "1"]);
+        hset(&mut reply, &mut storage, &request).unwrap();
+
+        for (arguments, expected) in [
+            (
+                &["HSCAN", "h", "0"][..],
+                "*2\r\n$1\r\n0\r\n*2\r\n$1\r\na\r\n$1\r\n1\r\n",
+            ),
+            (
+                &["HSCAN", "h", "0", "NOVALUES"],
+                "*2\r\n$1\r\n0\r\n*1\r\n$1\r\na\r\n",
+            ),
+            (&["HSCAN", "h", "0", "TYPE", "hash"], "-syntax error\r\n"),
+        ] {
+            let mut reply = Vec::new();
+            hscan(&mut reply, &mut storage, &Request::from_strs(arguments)).unwrap();
+            assert_eq!(String::from_utf8(reply).unwrap(), expected, "{arguments:?}");
+        }
+    }
+
+    #[test]
+    fn test_scan_returns_every_key_across_resizes() {
+        let mut storage = Database::default();
+        let keys: HashSet<_> = (0..100).map(|i| format!("key:{i}")).collect();
+        for key in &keys {
+            storage.set(key.as_bytes().to_vec(), b"v".to_vec(), None);
+        }
+
+        let mut seen = HashSet::new();
+        let mut cursor = "0".to_string();
+        let mut calls = 0;
+        loop {
+            let reply = run_scan(&mut storage, &["SCAN", &cursor, "COUNT", "5"]);
+            let (next, elements) = parse_reply(&reply);
+            seen.extend(elements);
+            calls += 1;
+            // Grow the table well past its size, then shrink it back, midway through.
+            if calls == 3 {
+                for i in 0..1000 {
+                    storage.set(format!("extra:{i}").into_bytes(), b"v".to_vec(), None);
+                }
+            } else if calls == 8 {
+                for i in 0..1000 {
+                    storage.remove(format!("extra:{i}").as_bytes());
+                }
+            }
+            if next == "0" {
+                break;
+            }
+            cursor = next;
+        }
+
+        assert!(
+            keys.is_subset(&seen),
+            "missing {:?}",
+            keys.difference(&seen)
+        );
+    }
+}
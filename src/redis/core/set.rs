@@ -0,0 +1,692 @@
+use crate::redis::core::errors::{wrong_arguments, COUNT_OUT_OF_RANGE, NOT_INTEGER, SYNTAX_ERROR};
+use crate::redis::core::random::random_index;
+use crate::redis::core::request::Request;
+use crate::redis::core::scan::{write_scan_reply, ScanOptions};
+use crate::redis::core::WriteResp;
+use crate::redis::rdb::{Database, Set, Value};
+
+const NIL: Option<&[u8]> = None;
+
+pub fn sadd(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments("sadd"));
+    }
+
+    let key = request.get(1).unwrap();
+    let set = match storage.get_or_insert_set(key) {
+        Ok(set) => set,
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let added = (2..request.len())
+        .filter(|index| set.insert(request.get(*index).unwrap().to_vec()))
+        .count();
+    if added > 0 {
+        storage.touch(key);
+    }
+    writer.write_integer(added as i64)
+}
+
+pub fn srem(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments("srem"));
+    }
+
+    let key = request.get(1).unwrap();
+    let set = match storage.get_set_mut(key) {
+        Ok(Some(set)) => set,
+        Ok(None) => return writer.write_integer(0),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let removed = (2..request.len())
+        .filter(|index| set.remove(request.get(*index).unwrap()))
+        .count();
+    if removed > 0 {
+        storage.touch(key);
+    }
+    storage.remove_if_empty(key);
+    writer.write_integer(removed as i64)
+}
+
+pub fn smembers(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("smembers"));
+    }
+
+    match storage.get_set(request.get(1).unwrap()) {
+        Ok(set) => {
+            let members: Vec<_> = set.into_iter().flatten().map(Some).collect();
+            writer.write_array(&members)
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn sismember(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("sismember"));
+    }
+
+    match storage.get_set(request.get(1).unwrap()) {
+        Ok(set) => {
+            let exists = set.is_some_and(|set| set.contains(request.get(2).unwrap()));
+            writer.write_integer(exists as i64)
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn smismember(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments("smismember"));
+    }
+
+    match storage.get_set(request.get(1).unwrap()) {
+        Ok(set) => {
+            writer.write_array_header(request.len() - 2)?;
+            for index in 2..request.len() {
+                let exists = set.is_some_and(|set| set.contains(request.get(index).unwrap()));
+                writer.write_integer(exists as i64)?;
+            }
+            Ok(())
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn scard(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("scard"));
+    }
+
+    match storage.get_set(request.get(1).unwrap()) {
+        Ok(set) => writer.write_integer(set.map_or(0, |set| set.len()) as i64),
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn spop(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 && request.len() != 3 {
+        return writer.write_error(wrong_arguments("spop"));
+    }
+
+    let count = match request.get(2) {
+        None => None,
+        Some(_) => match request.get_int(2) {
+            Some(count) if count >= 0 => Some(count as usize),
+            Some(_) => return writer.write_error("value is out of range, must be positive"),
+            None => return writer.write_error(NOT_INTEGER),
+        },
+    };
+
+    let key = request.get(1).unwrap();
+    let set = match storage.get_set_mut(key) {
+        Ok(Some(set)) => set,
+        Ok(None) if count.is_some() => return writer.write_array(&[NIL; 0]),
+        Ok(None) => return writer.write_bulk_sting(&NIL),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    let popped: Vec<_> = set
+        .random_members(count.unwrap_or(1), random_index)
+        .into_iter()
+        .cloned()
+        .collect();
+    for member in &popped {
+        set.remove(member);
+    }
+    if !popped.is_empty() {
+        storage.touch(key);
+    }
+    storage.remove_if_empty(key);
+
+    match count {
+        Some(_) => {
+            let popped: Vec<_> = popped.into_iter().map(Some).collect();
+            writer.write_array(&popped)
+        }
+        None => writer.write_bulk_sting(&popped.into_iter().next()),
+    }
+}
+
+pub fn srandmember(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 && request.len() != 3 {
+        return writer.write_error(wrong_arguments("srandmember"));
+    }
+
+    let count = match request.get(2) {
+        None => None,
+        Some(_) => match request.get_int(2) {
+            Some(i64::MIN) => return writer.write_error(COUNT_OUT_OF_RANGE),
+            Some(count) => Some(count),
+            None => return writer.write_error(NOT_INTEGER),
+        },
+    };
+
+    let set = match storage.get_set(request.get(1).unwrap()) {
+        Ok(Some(set)) => set,
+        Ok(None) if count.is_some() => return writer.write_array(&[NIL; 0]),
+        Ok(None) => return writer.write_bulk_sting(&NIL),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    let Some(count) = count else {
+        return writer.write_bulk_sting(&set.random_member(random_index));
+    };
+    if count >= 0 {
+        let members: Vec<_> = set
+            .random_members(count as usize, random_index)
+            .into_iter()
+            .map(Some)
+            .collect();
+        return writer.write_array(&members);
+    }
+    // The same member may come up several times, so the reply is written as it is drawn.
+    writer.write_array_header(count.unsigned_abs() as usize)?;
+    for _ in 0..count.unsigned_abs() {
+        writer.write_bulk_sting(&set.random_member(random_index))?;
+    }
+    Ok(())
+}
+
+pub fn smove(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("smove"));
+    }
+
+    let source = request.get(1).unwrap();
+    let destination = request.get(2).unwrap();
+    let member = request.get(3).unwrap();
+
+    let sets = match storage.get_sets(&[source, destination]) {
+        Ok(sets) => sets,
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    if !sets[0].is_some_and(|set| set.contains(member)) {
+        return writer.write_integer(0);
+    }
+    if source == destination {
+        return writer.write_integer(1);
+    }
+
+    if let Ok(Some(set)) = storage.get_set_mut(source) {
+        set.remove(member);
+    }
+    storage.touch(source);
+    storage.remove_if_empty(source);
+    if let Ok(set) = storage.get_or_insert_set(destination) {
+        set.insert(member.to_vec());
+    }
+    storage.touch(destination);
+    writer.write_integer(1)
+}
+
+pub fn sinter(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    combine(writer, storage, request, "sinter", Operation::Inter, false)
+}
+
+pub fn sinterstore(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    combine(
+        writer,
+        storage,
+        request,
+        "sinterstore",
+        Operation::Inter,
+        true,
+    )
+}
+
+pub fn sunion(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    combine(writer, storage, request, "sunion", Operation::Union, false)
+}
+
+pub fn sunionstore(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    combine(
+        writer,
+        storage,
+        request,
+        "sunionstore",
+        Operation::Union,
+        true,
+    )
+}
+
+pub fn sdiff(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    combine(writer, storage, request, "sdiff", Operation::Diff, false)
+}
+
+pub fn sdiffstore(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    combine(
+        writer,
+        storage,
+        request,
+        "sdiffstore",
+        Operation::Diff,
+        true,
+    )
+}
+
+pub fn sintercard(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments("sintercard"));
+    }
+
+    let num_keys = match request.get_int(1) {
+        Some(num_keys) if num_keys > 0 => num_keys as usize,
+        Some(_) => return writer.write_error("numkeys should be greater than 0"),
+        None => return writer.write_error(NOT_INTEGER),
+    };
+    if num_keys > request.len() - 2 {
+        return writer.write_error("Number of keys can't be greater than number of args");
+    }
+
+    let mut limit = 0;
+    let mut index = 2 + num_keys;
+    while index < request.len() {
+        let option = request.get_str(index).unwrap();
+        if !option.eq_ignore_ascii_case("limit") || index + 1 >= request.len() {
+            return writer.write_error(SYNTAX_ERROR);
+        }
+        limit = match request.get_int(index + 1) {
+            Some(limit) if limit >= 0 => limit as usize,
+            Some(_) => return writer.write_error("LIMIT can't be negative"),
+            None => return writer.write_error(NOT_INTEGER),
+        };
+        index += 2;
+    }
+
+    let keys: Vec<_> = (2..2 + num_keys)
+        .map(|index| request.get(index).unwrap())
+        .collect();
+    let sets = match storage.get_sets(&keys) {
+        Ok(sets) => sets,
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    let Some(sets) = sets.into_iter().collect::<Option<Vec<_>>>() else {
+        return writer.write_integer(0);
+    };
+    let (smallest, others) = split_smallest(&sets);
+    let count = smallest
+        .iter()
+        .filter(|member| others.iter().all(|set| set.contains(member)))
+        .take(if limit == 0 { usize::MAX } else { limit })
+        .count();
+    writer.write_integer(count as i64)
+}
+
+enum Operation {
+    Inter,
+    Union,
+    Diff,
+}
+
+pub fn sscan(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    let options = match ScanOptions::parse(request, 2, "sscan") {
+        Ok(options) => options,
+        Err(e) => return writer.write_error(e),
+    };
+
+    let set = match storage.get_set(request.get(1).unwrap()) {
+        Ok(Some(set)) => set,
+        Ok(None) => return write_scan_reply(writer, 0, &[NIL; 0]),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let (cursor, members) = set.scan(options.cursor, options.count);
+    let members: Vec<_> = members
+        .into_iter()
+        .filter(|member| options.matches(member))
+        .map(Some)
+        .collect();
+    write_scan_reply(writer, cursor, &members)
+}
+
+fn combine(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+    command: &str,
+    operation: Operation,
+    store: bool,
+) -> std::io::Result<()> {
+    let first_key = if store { 2 } else { 1 };
+    if request.len() <= first_key {
+        return writer.write_error(wrong_arguments(command));
+    }
+
+    let keys: Vec<_> = (first_key..request.len())
+        .map(|index| request.get(index).unwrap())
+        .collect();
+    let sets = match storage.get_sets(&keys) {
+        Ok(sets) => sets,
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    let result: Set = match operation {
+        Operation::Inter => match sets.into_iter().collect::<Option<Vec<_>>>() {
+            None => Set::new(),
+            Some(sets) => {
+                let (smallest, others) = split_smallest(&sets);
+                smallest
+                    .iter()
+                    .filter(|member| others.iter().all(|set| set.contains(member)))
+                    .cloned()
+                    .collect()
+            }
+        },
+        Operation::Union => sets.into_iter().flatten().flatten().cloned().collect(),
+        Operation::Diff => {
+            let mut sets = sets.into_iter();
+            match sets.next().flatten() {
+                None => Set::new(),
+                Some(first) => {
+                    let others: Vec<_> = sets.flatten().collect();
+                    first
+                        .iter()
+                        .filter(|member| !others.iter().any(|set| set.contains(member)))
+                        .cloned()
+                        .collect()
+                }
+            }
+        }
+    };
+
+    if !store {
+        let members: Vec<_> = result.iter().map(Some).collect();
+        return writer.write_array(&members);
+    }
+
+    let destination = request.get(1).unwrap();
+    let len = result.len();
+    if result.is_empty() {
+        storage.remove(destination);
+    } else {
+        storage.insert(destination.to_vec(), Value::Set(result));
+    }
+    writer.write_integer(len as i64)
+}
+
+/// Splits `sets` into the one with the fewest members and the rest, so intersections only
+/// need to walk the smallest set.
+fn split_smallest<'a>(sets: &[&'a Set]) -> (&'a Set, Vec<&'a Set>) {
+    let smallest = (0..sets.len())
+        .min_by_key(|index| sets[*index].len())
+        .unwrap();
+    let others = (0..sets.len())
+        .filter(|index| *index != smallest)
+        .map(|index| sets[index])
+        .collect();
+    (sets[smallest], others)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::request::Request;
+    use crate::redis::core::set::{
+        sadd, sdiffstore, sintercard, sinterstore, smove, spop, srandmember, sunion, sunionstore,
+    };
+    use crate::redis::rdb::Database;
+
+    fn members(storage: &mut Database, key: &[u8]) -> Vec<String> {
+        let mut members: Vec<_> = storage
+            .get_set(key)
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .map(|member| String::from_utf8(member.clone()).unwrap())
+            .collect();
+        members.sort();
+        members
+    }
+
+    #[test]
+    fn test_set_algebra() {
+        let mut storage = Database::default();
+        let mut reply = Vec::new();
+        sadd(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SADD", "a", "1", "2", "3", "1"]),
+        )
+        .unwrap();
+        sadd(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SADD", "b", "2", "3", "4"]),
+        )
+        .unwrap();
+        sinterstore(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SINTERSTORE", "i", "a", "b"]),
+        )
+        .unwrap();
+        sunionstore(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SUNIONSTORE", "u", "a", "b", "x"]),
+        )
+        .unwrap();
+        sdiffstore(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SDIFFSTORE", "d", "a", "b"]),
+        )
+        .unwrap();
+        sintercard(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SINTERCARD", "2", "a", "b", "LIMIT", "1"]),
+        )
+        .unwrap();
+        assert_eq!(reply, b":3\r\n:3\r\n:2\r\n:4\r\n:1\r\n:1\r\n");
+        assert_eq!(members(&mut storage, b"i"), vec!["2", "3"]);
+        assert_eq!(members(&mut storage, b"u"), vec!["1", "2", "3", "4"]);
+        assert_eq!(members(&mut storage, b"d"), vec!["1"]);
+
+        // An empty result deletes the destination.
+        let mut reply = Vec::new();
+        sinterstore(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SINTERSTORE", "i", "a", "x"]),
+        )
+        .unwrap();
+        assert_eq!(reply, b":0\r\n");
+        assert!(!storage.contains_key(b"i"));
+    }
+
+    #[test]
+    fn test_members_move_and_pop() {
+        let mut storage = Database::default();
+        let mut reply = Vec::new();
+        sadd(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SADD", "a", "1", "2"]),
+        )
+        .unwrap();
+
+        let mut reply = Vec::new();
+        smove(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SMOVE", "a", "b", "1"]),
+        )
+        .unwrap();
+        smove(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SMOVE", "a", "b", "1"]),
+        )
+        .unwrap();
+        smove(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SMOVE", "a", "a", "2"]),
+        )
+        .unwrap();
+        spop(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SPOP", "a", "5"]),
+        )
+        .unwrap();
+        spop(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SPOP", "a", "-1"]),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            ":1\r\n:0\r\n:1\r\n*1\r\n$1\r\n2\r\n-value is out of range, must be positive\r\n"
+        );
+        assert!(!storage.contains_key(b"a"));
+        assert_eq!(members(&mut storage, b"b"), vec!["1"]);
+    }
+
+    #[test]
+    fn test_commands_reject_other_types() {
+        let mut storage = Database::default();
+        storage.set(b"s".to_vec(), b"value".to_vec(), None);
+        let mut reply = Vec::new();
+        sadd(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SADD", "b", "1"]),
+        )
+        .unwrap();
+
+        let mut reply = Vec::new();
+        sadd(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SADD", "s", "1"]),
+        )
+        .unwrap();
+        sunion(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SUNION", "b", "s"]),
+        )
+        .unwrap();
+        smove(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SMOVE", "b", "s", "1"]),
+        )
+        .unwrap();
+        let wrong_type = "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n";
+        assert_eq!(String::from_utf8(reply).unwrap(), wrong_type.repeat(3));
+        assert_eq!(members(&mut storage, b"b"), vec!["1"]);
+    }
+
+    #[test]
+    fn test_srandmember_counts() {
+        let mut storage = Database::default();
+        let mut reply = Vec::new();
+        sadd(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SADD", "a", "1", "2", "3"]),
+        )
+        .unwrap();
+
+        let mut reply = Vec::new();
+        srandmember(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SRANDMEMBER", "a", "2"]),
+        )
+        .unwrap();
+        let reply = String::from_utf8(reply).unwrap();
+        let members: Vec<_> = reply.split("\r\n").skip(2).step_by(2).take(2).collect();
+        assert!(reply.starts_with("*2\r\n"));
+        assert_ne!(members[0], members[1]);
+
+        let mut reply = Vec::new();
+        srandmember(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SRANDMEMBER", "a", "-7"]),
+        )
+        .unwrap();
+        assert!(reply.starts_with(b"*7\r\n"));
+        let mut reply = Vec::new();
+        let extreme = Request::from_strs(&["SRANDMEMBER", "a", "-9223372036854775808"]);
+        srandmember(&mut reply, &mut storage, &extreme).unwrap();
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            "-value is out of range, value must between -9223372036854775807 and \
+             9223372036854775807\r\n"
+        );
+    }
+}
@@ -1,40 +1,188 @@
+use crate::redis::core::errors::{wrong_arguments, NOT_INTEGER, SYNTAX_ERROR};
 use crate::redis::core::request::Request;
 use crate::redis::core::WriteResp;
-use crate::redis::rdb::RedisStorage;
+use crate::redis::rdb::Database;
+use chrono::Utc;
 
-const OK: Option<&str> = Some("OK");
+const OK: &str = "OK";
+const INVALID_EXPIRE_TIME: &str = "invalid expire time in 'set' command";
+
+#[derive(PartialEq)]
+enum Condition {
+    Always,
+    IfMissing,
+    IfExists,
+}
+
+#[derive(PartialEq)]
+enum Expiration {
+    None,
+    KeepTtl,
+    /// Unix time in milliseconds.
+    At(u64),
+}
 
 pub fn set_key_value(
     writer: &mut impl WriteResp,
-    storage: &mut RedisStorage,
+    storage: &mut Database,
     request: &Request,
 ) -> std::io::Result<()> {
     if request.len() < 3 {
-        return writer.write_error("wrong number of arguments for 'set' command");
+        return writer.write_error(wrong_arguments("set"));
     }
 
-    let key = request.get(1).unwrap().to_string();
-    let value = request.get(2).unwrap().to_string();
-    let px = match request.get(3) {
-        None => None,
-        Some(value) => {
-            let arg_name = value.to_lowercase();
-            if "px" != arg_name {
-                return writer.write_error(format!("unknown argument: '{}'", value));
+    let mut condition = Condition::Always;
+    let mut expiration = Expiration::None;
+    let mut get = false;
+    let mut index = 3;
+    while index < request.len() {
+        let option = request.get_str(index).unwrap().to_lowercase();
+        match option.as_str() {
+            "nx" if condition != Condition::IfExists => condition = Condition::IfMissing,
+            "xx" if condition != Condition::IfMissing => condition = Condition::IfExists,
+            "get" => get = true,
+            "keepttl" if expiration == Expiration::None => expiration = Expiration::KeepTtl,
+            "ex" | "px" | "exat" | "pxat"
+                if expiration == Expiration::None && index + 1 < request.len() =>
+            {
+                let Some(value) = request.get_int(index + 1) else {
+                    return writer.write_error(NOT_INTEGER);
+                };
+                match expire_at(&option, value) {
+                    Some(time) => expiration = Expiration::At(time),
+                    None => return writer.write_error(INVALID_EXPIRE_TIME),
+                }
+                index += 1;
             }
+            _ => return writer.write_error(SYNTAX_ERROR),
+        }
+        index += 1;
+    }
 
-            let arg_value = request.get(4);
-            if arg_value.is_none() {
-                return writer.write_error("wrong number of arguments for 'set' command");
-            }
+    let key = request.get(1).unwrap();
+    let previous = if get {
+        match storage.get(key) {
+            Ok(previous) => Some(previous.map(<[u8]>::to_vec)),
+            Err(e) => return writer.write_error(e.to_string()),
+        }
+    } else {
+        None
+    };
 
-            let px = arg_value.unwrap();
-            match px.parse::<u64>() {
-                Ok(px) => Some(px),
-                Err(_) => return writer.write_error(format!("invalid px value: '{}'", value)),
-            }
+    let exists = storage.contains_key(key);
+    let allowed = match condition {
+        Condition::Always => true,
+        Condition::IfMissing => !exists,
+        Condition::IfExists => exists,
+    };
+    if allowed {
+        let value = request.get(2).unwrap().to_vec();
+        match expiration {
+            Expiration::None => storage.set(key.to_vec(), value, None),
+            Expiration::KeepTtl => storage.set_keep_ttl(key.to_vec(), value),
+            Expiration::At(time) => storage.set(key.to_vec(), value, Some(time)),
         }
+    }
+
+    match previous {
+        Some(previous) => writer.write_bulk_sting(&previous),
+        None if allowed => writer.write_simple_string(OK),
+        None => writer.write_bulk_sting(&None::<&[u8]>),
+    }
+}
+
+/// Converts the value of an EX, PX, EXAT or PXAT option to Unix milliseconds, if it is valid.
+pub(super) fn expire_at(option: &str, value: i64) -> Option<u64> {
+    if value <= 0 {
+        return None;
+    }
+    let milliseconds = match option {
+        "ex" | "exat" => value.checked_mul(1000)?,
+        _ => value,
+    };
+    let milliseconds = match option {
+        "ex" | "px" => milliseconds.checked_add(Utc::now().timestamp_millis())?,
+        _ => milliseconds,
     };
-    storage.set(key, value, px);
-    writer.write_bulk_sting(&OK)
+    Some(milliseconds as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::request::Request;
+    use crate::redis::core::set_key_value::set_key_value;
+    use crate::redis::rdb::Database;
+
+    #[test]
+    fn test_conditions_and_get() {
+        let mut storage = Database::default();
+        let mut reply = Vec::new();
+        for arguments in [
+            &["SET", "k", "1", "XX"][..],
+            &["SET", "k", "1", "NX"],
+            &["SET", "k", "2", "NX", "GET"],
+            &["SET", "k", "3", "XX", "GET"],
+            &["SET", "k", "4", "NX", "XX"],
+            &["SET", "x", "1", "GET"],
+        ] {
+            set_key_value(&mut reply, &mut storage, &Request::from_strs(arguments)).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            "$-1\r\n+OK\r\n$1\r\n1\r\n$1\r\n1\r\n-syntax error\r\n$-1\r\n"
+        );
+        assert_eq!(storage.get(b"k").unwrap(), Some(b"3".as_slice()));
+        assert_eq!(storage.get(b"x").unwrap(), Some(b"1".as_slice()));
+
+        let mut reply = Vec::new();
+        storage
+            .get_or_insert_list(b"l")
+            .unwrap()
+            .push_back(b"a".to_vec());
+        set_key_value(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SET", "l", "1", "GET"]),
+        )
+        .unwrap();
+        assert_eq!(
+            reply,
+            b"-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"
+        );
+        assert!(storage.get_list(b"l").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_expirations() {
+        let mut storage = Database::default();
+        let mut reply = Vec::new();
+        for arguments in [
+            &["SET", "k", "1", "PXAT", "4102444800000"][..],
+            &["SET", "k", "2", "KEEPTTL"],
+            &["SET", "k", "3", "EX", "0"],
+            &["SET", "k", "3", "EX", "ten"],
+            &["SET", "k", "3", "EX", "10", "KEEPTTL"],
+            &["SET", "k", "3", "EX", "10", "PX", "10"],
+            &["SET", "k", "3", "EX"],
+        ] {
+            set_key_value(&mut reply, &mut storage, &Request::from_strs(arguments)).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            "+OK\r\n+OK\r\n-invalid expire time in 'set' command\r\n\
+             -value is not an integer or out of range\r\n-syntax error\r\n-syntax error\r\n\
+             -syntax error\r\n"
+        );
+        assert_eq!(storage.get(b"k").unwrap(), Some(b"2".as_slice()));
+        assert_eq!(storage.get_expire_time(b"k"), Some(Some(4102444800000)));
+
+        let mut reply = Vec::new();
+        set_key_value(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["SET", "k", "4"]),
+        )
+        .unwrap();
+        assert_eq!(storage.get_expire_time(b"k"), Some(None));
+    }
 }
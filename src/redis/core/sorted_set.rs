@@ -0,0 +1,1012 @@
+use crate::redis::core::blocking::{parse_timeout_seconds, Block, BlockResult, TimeoutReply};
+use crate::redis::core::errors::{wrong_arguments, NOT_INTEGER, SYNTAX_ERROR};
+use crate::redis::core::float::{format_score, parse_float};
+use crate::redis::core::range::normalize_range;
+use crate::redis::core::request::Request;
+use crate::redis::core::scan::{write_scan_reply, ScanOptions};
+use crate::redis::core::WriteResp;
+use crate::redis::rdb::{Database, LexBound, ScoreBound, SortedSet, Value, WrongTypeError};
+use std::collections::HashMap;
+
+const NIL: Option<&[u8]> = None;
+const NOT_FLOAT: &str = "value is not a valid float";
+const NAN_SCORE: &str = "resulting score is not a number (NaN)";
+
+pub fn zadd(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 4 {
+        return writer.write_error(wrong_arguments("zadd"));
+    }
+
+    let (mut nx, mut xx, mut gt, mut lt, mut ch, mut incr) =
+        (false, false, false, false, false, false);
+    let mut index = 2;
+    while index < request.len() {
+        match request.get_str(index).unwrap().to_lowercase().as_str() {
+            "nx" => nx = true,
+            "xx" => xx = true,
+            "gt" => gt = true,
+            "lt" => lt = true,
+            "ch" => ch = true,
+            "incr" => incr = true,
+            _ => break,
+        }
+        index += 1;
+    }
+
+    let arguments = request.len() - index;
+    if arguments == 0 || !arguments.is_multiple_of(2) {
+        return writer.write_error(SYNTAX_ERROR);
+    }
+    if nx && xx {
+        return writer.write_error("XX and NX options at the same time are not compatible");
+    }
+    if (gt && lt) || (nx && (gt || lt)) {
+        return writer.write_error("GT, LT, and/or NX options at the same time are not compatible");
+    }
+    if incr && arguments > 2 {
+        return writer.write_error("INCR option supports a single increment-element pair");
+    }
+
+    let mut elements = Vec::with_capacity(arguments / 2);
+    for index in (index..request.len()).step_by(2) {
+        let Some(score) = parse_float(request.get(index).unwrap()) else {
+            return writer.write_error(NOT_FLOAT);
+        };
+        elements.push((score, request.get(index + 1).unwrap()));
+    }
+
+    let key = request.get(1).unwrap();
+    if xx && matches!(storage.get_sorted_set(key), Ok(None)) {
+        // Nothing can be updated, so the key must not even be created.
+        return if incr {
+            writer.write_bulk_sting(&NIL)
+        } else {
+            writer.write_integer(0)
+        };
+    }
+    let sorted_set = match storage.get_or_insert_sorted_set(key) {
+        Ok(sorted_set) => sorted_set,
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    let mut added = 0;
+    let mut changed = 0;
+    let mut result = None;
+    for (score, member) in elements {
+        match sorted_set.score(member) {
+            Some(current) => {
+                if nx {
+                    continue;
+                }
+                let score = if incr { current + score } else { score };
+                if score.is_nan() {
+                    storage.remove_if_empty(key);
+                    return writer.write_error(NAN_SCORE);
+                }
+                if (gt && score <= current) || (lt && score >= current) {
+                    continue;
+                }
+                if score != current {
+                    sorted_set.insert(member.to_vec(), score);
+                    changed += 1;
+                }
+                result = Some(score);
+            }
+            None => {
+                if xx {
+                    continue;
+                }
+                sorted_set.insert(member.to_vec(), score);
+                added += 1;
+                result = Some(score);
+            }
+        }
+    }
+    if added + changed > 0 {
+        storage.touch(key);
+    }
+    storage.remove_if_empty(key);
+
+    if incr {
+        writer.write_bulk_sting(&result.map(format_score))
+    } else if ch {
+        writer.write_integer(added + changed)
+    } else {
+        writer.write_integer(added)
+    }
+}
+
+pub fn zincrby(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("zincrby"));
+    }
+
+    let Some(increment) = parse_float(request.get(2).unwrap()) else {
+        return writer.write_error(NOT_FLOAT);
+    };
+
+    let key = request.get(1).unwrap();
+    let sorted_set = match storage.get_or_insert_sorted_set(key) {
+        Ok(sorted_set) => sorted_set,
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let member = request.get(3).unwrap();
+    let score = sorted_set.score(member).unwrap_or(0.0) + increment;
+    if score.is_nan() {
+        storage.remove_if_empty(key);
+        return writer.write_error(NAN_SCORE);
+    }
+    sorted_set.insert(member.to_vec(), score);
+    storage.touch(key);
+    writer.write_bulk_sting(&Some(format_score(score)))
+}
+
+pub fn zrem(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments("zrem"));
+    }
+
+    let key = request.get(1).unwrap();
+    let sorted_set = match storage.get_sorted_set_mut(key) {
+        Ok(Some(sorted_set)) => sorted_set,
+        Ok(None) => return writer.write_integer(0),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let removed = (2..request.len())
+        .filter(|index| sorted_set.remove(request.get(*index).unwrap()).is_some())
+        .count();
+    if removed > 0 {
+        storage.touch(key);
+    }
+    storage.remove_if_empty(key);
+    writer.write_integer(removed as i64)
+}
+
+pub fn zcard(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("zcard"));
+    }
+
+    match storage.get_sorted_set(request.get(1).unwrap()) {
+        Ok(sorted_set) => writer.write_integer(sorted_set.map_or(0, |x| x.len()) as i64),
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn zscore(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("zscore"));
+    }
+
+    match storage.get_sorted_set(request.get(1).unwrap()) {
+        Ok(sorted_set) => {
+            let score = sorted_set.and_then(|x| x.score(request.get(2).unwrap()));
+            writer.write_bulk_sting(&score.map(format_score))
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn zmscore(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments("zmscore"));
+    }
+
+    match storage.get_sorted_set(request.get(1).unwrap()) {
+        Ok(sorted_set) => {
+            let scores: Vec<_> = (2..request.len())
+                .map(|index| {
+                    sorted_set
+                        .and_then(|x| x.score(request.get(index).unwrap()))
+                        .map(format_score)
+                })
+                .collect();
+            writer.write_array(&scores)
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn zrank(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    rank(writer, storage, request, "zrank", false)
+}
+
+pub fn zrevrank(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    rank(writer, storage, request, "zrevrank", true)
+}
+
+fn rank(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+    command: &str,
+    reverse: bool,
+) -> std::io::Result<()> {
+    if request.len() != 3 && request.len() != 4 {
+        return writer.write_error(wrong_arguments(command));
+    }
+
+    let with_score = match request.get_str(3) {
+        None => false,
+        Some(option) if option.eq_ignore_ascii_case("withscore") => true,
+        Some(_) => return writer.write_error(SYNTAX_ERROR),
+    };
+
+    let sorted_set = match storage.get_sorted_set(request.get(1).unwrap()) {
+        Ok(sorted_set) => sorted_set,
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let member = request.get(2).unwrap();
+    let Some((sorted_set, rank)) = sorted_set.and_then(|x| x.rank(member).map(|rank| (x, rank)))
+    else {
+        return if with_score {
+            writer.write_null_array()
+        } else {
+            writer.write_bulk_sting(&NIL)
+        };
+    };
+    let rank = if reverse {
+        sorted_set.len() - 1 - rank
+    } else {
+        rank
+    };
+
+    if with_score {
+        writer.write_array_header(2)?;
+        writer.write_integer(rank as i64)?;
+        writer.write_bulk_sting(&sorted_set.score(member).map(format_score))
+    } else {
+        writer.write_integer(rank as i64)
+    }
+}
+
+pub fn zrange(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 4 {
+        return writer.write_error(wrong_arguments("zrange"));
+    }
+
+    let query = match RangeQuery::parse(request, 2, true) {
+        Ok(query) => query,
+        Err(e) => return writer.write_error(e),
+    };
+    let sorted_set = match storage.get_sorted_set(request.get(1).unwrap()) {
+        Ok(Some(sorted_set)) => sorted_set,
+        Ok(None) => return writer.write_array(&[NIL; 0]),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let members = match query.execute(sorted_set) {
+        Ok(members) => members,
+        Err(e) => return writer.write_error(e),
+    };
+    write_members(writer, &members, query.with_scores)
+}
+
+pub fn zrangestore(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 5 {
+        return writer.write_error(wrong_arguments("zrangestore"));
+    }
+
+    let query = match RangeQuery::parse(request, 3, false) {
+        Ok(query) => query,
+        Err(e) => return writer.write_error(e),
+    };
+    let result: SortedSet = match storage.get_sorted_set(request.get(2).unwrap()) {
+        Ok(Some(sorted_set)) => match query.execute(sorted_set) {
+            Ok(members) => members
+                .into_iter()
+                .map(|(member, score)| (member.to_vec(), score))
+                .collect(),
+            Err(e) => return writer.write_error(e),
+        },
+        Ok(None) => SortedSet::default(),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    store(writer, storage, request.get(1).unwrap(), result)
+}
+
+pub fn zcount(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("zcount"));
+    }
+
+    let (Some(min), Some(max)) = (
+        parse_score_bound(request.get(2).unwrap()),
+        parse_score_bound(request.get(3).unwrap()),
+    ) else {
+        return writer.write_error("min or max is not a float");
+    };
+    match storage.get_sorted_set(request.get(1).unwrap()) {
+        Ok(sorted_set) => {
+            let count = sorted_set.map_or(0, |x| x.range_by_score(&min, &max).len());
+            writer.write_integer(count as i64)
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn zlexcount(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("zlexcount"));
+    }
+
+    let (Some(min), Some(max)) = (
+        parse_lex_bound(request.get(2).unwrap()),
+        parse_lex_bound(request.get(3).unwrap()),
+    ) else {
+        return writer.write_error("min or max not valid string range item");
+    };
+    match storage.get_sorted_set(request.get(1).unwrap()) {
+        Ok(sorted_set) => {
+            let count = sorted_set.map_or(0, |x| x.range_by_lex(&min, &max).len());
+            writer.write_integer(count as i64)
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn zpopmin(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    pop(writer, storage, request, "zpopmin", false)
+}
+
+pub fn zpopmax(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    pop(writer, storage, request, "zpopmax", true)
+}
+
+fn pop(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+    command: &str,
+    max: bool,
+) -> std::io::Result<()> {
+    if request.len() != 2 && request.len() != 3 {
+        return writer.write_error(wrong_arguments(command));
+    }
+
+    let count = match request.get(2) {
+        None => 1,
+        Some(_) => match request.get_int(2) {
+            Some(count) if count >= 0 => count as usize,
+            Some(_) => return writer.write_error("value is out of range, must be positive"),
+            None => return writer.write_error(NOT_INTEGER),
+        },
+    };
+
+    let key = request.get(1).unwrap();
+    let sorted_set = match storage.get_sorted_set_mut(key) {
+        Ok(Some(sorted_set)) => sorted_set,
+        Ok(None) => return writer.write_array(&[NIL; 0]),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let popped: Vec<_> = (0..count)
+        .map_while(|_| {
+            if max {
+                sorted_set.pop_max()
+            } else {
+                sorted_set.pop_min()
+            }
+        })
+        .collect();
+    if !popped.is_empty() {
+        storage.touch(key);
+    }
+    storage.remove_if_empty(key);
+
+    let popped: Vec<_> = popped
+        .iter()
+        .map(|(member, score)| (member.as_slice(), *score))
+        .collect();
+    write_members(writer, &popped, true)
+}
+
+pub fn bzpopmin(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> BlockResult {
+    blocking_pop(writer, storage, request, "bzpopmin", false)
+}
+
+pub fn bzpopmax(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> BlockResult {
+    blocking_pop(writer, storage, request, "bzpopmax", true)
+}
+
+/// Pops from the first non-empty sorted set among the keys, or blocks until one of them gets
+/// a member.
+fn blocking_pop(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+    command: &str,
+    max: bool,
+) -> BlockResult {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments(command)).map(|_| None);
+    }
+
+    let timeout = match parse_timeout_seconds(request.get(request.len() - 1).unwrap()) {
+        Ok(timeout) => timeout,
+        Err(e) => return writer.write_error(e).map(|_| None),
+    };
+
+    let keys: Vec<_> = (1..request.len() - 1)
+        .map(|index| request.get(index).unwrap())
+        .collect();
+    for key in &keys {
+        let sorted_set = match storage.get_sorted_set_mut(key) {
+            Ok(Some(sorted_set)) => sorted_set,
+            Ok(None) => continue,
+            Err(e) => return writer.write_error(e.to_string()).map(|_| None),
+        };
+        let popped = if max {
+            sorted_set.pop_max()
+        } else {
+            sorted_set.pop_min()
+        };
+        let Some((member, score)) = popped else {
+            continue;
+        };
+        storage.touch(key);
+        storage.remove_if_empty(key);
+        return writer
+            .write_array(&[
+                Some(key.to_vec()),
+                Some(member),
+                Some(format_score(score).into_bytes()),
+            ])
+            .map(|_| None);
+    }
+
+    let keys = keys.into_iter().map(<[u8]>::to_vec).collect();
+    Ok(Some(Block::new(keys, timeout, TimeoutReply::NullArray)))
+}
+
+pub fn zremrangebyrank(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("zremrangebyrank"));
+    }
+
+    let (Some(start), Some(stop)) = (request.get_int(2), request.get_int(3)) else {
+        return writer.write_error(NOT_INTEGER);
+    };
+    remove_range(
+        writer,
+        storage,
+        request,
+        |sorted_set| match normalize_range(start, stop, sorted_set.len()) {
+            Some((start, stop)) => sorted_set
+                .range_by_rank(start, stop + 1)
+                .map(|(member, _)| member.to_vec())
+                .collect(),
+            None => Vec::new(),
+        },
+    )
+}
+
+pub fn zremrangebyscore(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("zremrangebyscore"));
+    }
+
+    let (Some(min), Some(max)) = (
+        parse_score_bound(request.get(2).unwrap()),
+        parse_score_bound(request.get(3).unwrap()),
+    ) else {
+        return writer.write_error("min or max is not a float");
+    };
+    remove_range(writer, storage, request, |sorted_set| {
+        sorted_set
+            .range_by_score(&min, &max)
+            .into_iter()
+            .map(|(member, _)| member.to_vec())
+            .collect()
+    })
+}
+
+pub fn zremrangebylex(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("zremrangebylex"));
+    }
+
+    let (Some(min), Some(max)) = (
+        parse_lex_bound(request.get(2).unwrap()),
+        parse_lex_bound(request.get(3).unwrap()),
+    ) else {
+        return writer.write_error("min or max not valid string range item");
+    };
+    remove_range(writer, storage, request, |sorted_set| {
+        sorted_set
+            .range_by_lex(&min, &max)
+            .into_iter()
+            .map(|(member, _)| member.to_vec())
+            .collect()
+    })
+}
+
+fn remove_range(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+    select: impl FnOnce(&SortedSet) -> Vec<Vec<u8>>,
+) -> std::io::Result<()> {
+    let key = request.get(1).unwrap();
+    let sorted_set = match storage.get_sorted_set_mut(key) {
+        Ok(Some(sorted_set)) => sorted_set,
+        Ok(None) => return writer.write_integer(0),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let members = select(sorted_set);
+    for member in &members {
+        sorted_set.remove(member);
+    }
+    if !members.is_empty() {
+        storage.touch(key);
+    }
+    storage.remove_if_empty(key);
+    writer.write_integer(members.len() as i64)
+}
+
+pub fn zunionstore(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    combine(writer, storage, request, "zunionstore", Operation::Union)
+}
+
+pub fn zinterstore(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    combine(writer, storage, request, "zinterstore", Operation::Inter)
+}
+
+pub fn zdiffstore(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    combine(writer, storage, request, "zdiffstore", Operation::Diff)
+}
+
+#[derive(PartialEq)]
+enum Operation {
+    Union,
+    Inter,
+    Diff,
+}
+
+enum Aggregate {
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    fn apply(&self, current: f64, score: f64) -> f64 {
+        match self {
+            Aggregate::Sum => zero_if_nan(current + score),
+            Aggregate::Min => current.min(score),
+            Aggregate::Max => current.max(score),
+        }
+    }
+}
+
+pub fn zscan(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    let options = match ScanOptions::parse(request, 2, "zscan") {
+        Ok(options) => options,
+        Err(e) => return writer.write_error(e),
+    };
+
+    let sorted_set = match storage.get_sorted_set(request.get(1).unwrap()) {
+        Ok(Some(sorted_set)) => sorted_set,
+        Ok(None) => return write_scan_reply(writer, 0, &[NIL; 0]),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let (cursor, members) = sorted_set.scan(options.cursor, options.count);
+
+    let mut values = Vec::new();
+    for (member, score) in members {
+        if options.matches(member) {
+            values.push(Some(member.to_vec()));
+            values.push(Some(format_score(score).into_bytes()));
+        }
+    }
+    write_scan_reply(writer, cursor, &values)
+}
+
+fn combine(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+    command: &str,
+    operation: Operation,
+) -> std::io::Result<()> {
+    if request.len() < 4 {
+        return writer.write_error(wrong_arguments(command));
+    }
+
+    let num_keys = match request.get_int(2) {
+        Some(num_keys) if num_keys > 0 => num_keys as usize,
+        Some(_) => {
+            return writer.write_error(format!(
+                "at least 1 input key is needed for '{}' command",
+                command
+            ))
+        }
+        None => return writer.write_error(NOT_INTEGER),
+    };
+    if 3 + num_keys > request.len() {
+        return writer.write_error(SYNTAX_ERROR);
+    }
+
+    let mut weights = vec![1.0; num_keys];
+    let mut aggregate = Aggregate::Sum;
+    let mut index = 3 + num_keys;
+    while index < request.len() {
+        let option = request.get_str(index).unwrap().to_lowercase();
+        match option.as_str() {
+            "weights" if operation != Operation::Diff && index + num_keys < request.len() => {
+                for (offset, weight) in weights.iter_mut().enumerate() {
+                    match parse_float(request.get(index + 1 + offset).unwrap()) {
+                        Some(value) => *weight = value,
+                        None => return writer.write_error("weight value is not a float"),
+                    }
+                }
+                index += 1 + num_keys;
+            }
+            "aggregate" if operation != Operation::Diff && index + 1 < request.len() => {
+                let value = request.get_str(index + 1).unwrap().to_lowercase();
+                aggregate = match value.as_str() {
+                    "sum" => Aggregate::Sum,
+                    "min" => Aggregate::Min,
+                    "max" => Aggregate::Max,
+                    _ => return writer.write_error(SYNTAX_ERROR),
+                };
+                index += 2;
+            }
+            _ => return writer.write_error(SYNTAX_ERROR),
+        }
+    }
+
+    let keys: Vec<_> = (3..3 + num_keys)
+        .map(|index| request.get(index).unwrap())
+        .collect();
+    let mut inputs = Vec::with_capacity(num_keys);
+    for value in storage.get_values(&keys) {
+        let input: Option<HashMap<&[u8], f64>> = match value {
+            None => None,
+            Some(Value::SortedSet(sorted_set)) => Some(sorted_set.iter().collect()),
+            Some(Value::Set(set)) => Some(set.iter().map(|x| (x.as_slice(), 1.0)).collect()),
+            Some(_) => return writer.write_error(WrongTypeError.to_string()),
+        };
+        inputs.push(input);
+    }
+
+    let mut result: HashMap<&[u8], f64> = HashMap::new();
+    match operation {
+        Operation::Union => {
+            for (input, weight) in inputs.iter().zip(&weights) {
+                for (member, score) in input.iter().flatten() {
+                    let score = zero_if_nan(score * weight);
+                    result
+                        .entry(member)
+                        .and_modify(|current| *current = aggregate.apply(*current, score))
+                        .or_insert(score);
+                }
+            }
+        }
+        Operation::Inter => {
+            if let Some(inputs) = inputs
+                .iter()
+                .map(Option::as_ref)
+                .collect::<Option<Vec<_>>>()
+            {
+                for (member, score) in inputs[0] {
+                    let mut total = zero_if_nan(score * weights[0]);
+                    let mut in_all = true;
+                    for (input, weight) in inputs.iter().zip(&weights).skip(1) {
+                        match input.get(member) {
+                            Some(score) => {
+                                total = aggregate.apply(total, zero_if_nan(score * weight))
+                            }
+                            None => {
+                                in_all = false;
+                                break;
+                            }
+                        }
+                    }
+                    if in_all {
+                        result.insert(member, total);
+                    }
+                }
+            }
+        }
+        Operation::Diff => {
+            if let Some(first) = &inputs[0] {
+                for (member, score) in first {
+                    if !inputs[1..].iter().flatten().any(|x| x.contains_key(member)) {
+                        result.insert(member, *score);
+                    }
+                }
+            }
+        }
+    }
+
+    let result: SortedSet = result
+        .into_iter()
+        .map(|(member, score)| (member.to_vec(), score))
+        .collect();
+    store(writer, storage, request.get(1).unwrap(), result)
+}
+
+fn store(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    destination: &[u8],
+    result: SortedSet,
+) -> std::io::Result<()> {
+    let len = result.len();
+    if result.is_empty() {
+        storage.remove(destination);
+    } else {
+        storage.insert(destination.to_vec(), Value::SortedSet(result));
+    }
+    writer.write_integer(len as i64)
+}
+
+enum RangeBy {
+    Rank,
+    Score,
+    Lex,
+}
+
+struct RangeQuery<'a> {
+    by: RangeBy,
+    start: &'a [u8],
+    stop: &'a [u8],
+    reverse: bool,
+    limit: Option<(i64, i64)>,
+    with_scores: bool,
+}
+
+impl<'a> RangeQuery<'a> {
+    /// Parses `start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count] [WITHSCORES]`
+    /// starting at `index`.
+    fn parse(request: &'a Request, index: usize, allow_scores: bool) -> Result<Self, String> {
+        let mut query = RangeQuery {
+            by: RangeBy::Rank,
+            start: request.get(index).unwrap(),
+            stop: request.get(index + 1).unwrap(),
+            reverse: false,
+            limit: None,
+            with_scores: false,
+        };
+
+        let mut index = index + 2;
+        while index < request.len() {
+            let option = request.get_str(index).unwrap().to_lowercase();
+            match option.as_str() {
+                "byscore" => query.by = RangeBy::Score,
+                "bylex" => query.by = RangeBy::Lex,
+                "rev" => query.reverse = true,
+                "withscores" if allow_scores => query.with_scores = true,
+                "limit" if index + 2 < request.len() => {
+                    let (Some(offset), Some(count)) =
+                        (request.get_int(index + 1), request.get_int(index + 2))
+                    else {
+                        return Err(NOT_INTEGER.to_string());
+                    };
+                    query.limit = Some((offset, count));
+                    index += 2;
+                }
+                _ => return Err(SYNTAX_ERROR.to_string()),
+            }
+            index += 1;
+        }
+
+        if query.limit.is_some() && matches!(query.by, RangeBy::Rank) {
+            return Err(
+                "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                    .to_string(),
+            );
+        }
+        if query.with_scores && matches!(query.by, RangeBy::Lex) {
+            return Err(
+                "syntax error, WITHSCORES not supported in combination with BYLEX".to_string(),
+            );
+        }
+        Ok(query)
+    }
+
+    fn execute<'s>(&self, sorted_set: &'s SortedSet) -> Result<Vec<(&'s [u8], f64)>, String> {
+        let (min, max) = if self.reverse {
+            (self.stop, self.start)
+        } else {
+            (self.start, self.stop)
+        };
+
+        let mut members = match self.by {
+            RangeBy::Rank => {
+                let (Some(start), Some(stop)) = (parse_int(self.start), parse_int(self.stop))
+                else {
+                    return Err(NOT_INTEGER.to_string());
+                };
+                let Some((start, stop)) = normalize_range(start, stop, sorted_set.len()) else {
+                    return Ok(Vec::new());
+                };
+                if self.reverse {
+                    let len = sorted_set.len();
+                    sorted_set
+                        .range_by_rank(len - 1 - stop, len - start)
+                        .rev()
+                        .collect()
+                } else {
+                    sorted_set.range_by_rank(start, stop + 1).collect()
+                }
+            }
+            RangeBy::Score => {
+                let (Some(min), Some(max)) = (parse_score_bound(min), parse_score_bound(max))
+                else {
+                    return Err("min or max is not a float".to_string());
+                };
+                let mut members = sorted_set.range_by_score(&min, &max);
+                if self.reverse {
+                    members.reverse();
+                }
+                members
+            }
+            RangeBy::Lex => {
+                let (Some(min), Some(max)) = (parse_lex_bound(min), parse_lex_bound(max)) else {
+                    return Err("min or max not valid string range item".to_string());
+                };
+                let mut members = sorted_set.range_by_lex(&min, &max);
+                if self.reverse {
+                    members.reverse();
+                }
+                members
+            }
+        };
+
+        if let Some((offset, count)) = self.limit {
+            if offset < 0 {
+                return Ok(Vec::new());
+            }
+            members.drain(..(offset as usize).min(members.len()));
+            if count >= 0 {
+                members.truncate(count as usize);
+            }
+        }
+        Ok(members)
+    }
+}
+
+fn write_members(
+    writer: &mut impl WriteResp,
+    members: &[(&[u8], f64)],
+    with_scores: bool,
+) -> std::io::Result<()> {
+    if !with_scores {
+        let members: Vec<_> = members.iter().map(|(member, _)| Some(member)).collect();
+        return writer.write_array(&members);
+    }
+
+    writer.write_array_header(members.len() * 2)?;
+    for (member, score) in members {
+        writer.write_bulk_sting(&Some(member))?;
+        writer.write_bulk_sting(&Some(format_score(*score)))?;
+    }
+    Ok(())
+}
+
+fn parse_int(value: &[u8]) -> Option<i64> {
+    std::str::from_utf8(value).ok()?.parse().ok()
+}
+
+fn parse_score_bound(value: &[u8]) -> Option<ScoreBound> {
+    match value.strip_prefix(b"(") {
+        Some(value) => Some(ScoreBound {
+            value: parse_float(value)?,
+            exclusive: true,
+        }),
+        None => Some(ScoreBound {
+            value: parse_float(value)?,
+            exclusive: false,
+        }),
+    }
+}
+
+fn parse_lex_bound(value: &[u8]) -> Option<LexBound> {
+    match value.split_first() {
+        Some((b'-', [])) => Some(LexBound::Min),
+        Some((b'+', [])) => Some(LexBound::Max),
+        Some((b'[', value)) => Some(LexBound::Inclusive(value.to_vec())),
+        Some((b'(', value)) => Some(LexBound::Exclusive(value.to_vec())),
+        _ => None,
+    }
+}
+
+fn zero_if_nan(score: f64) -> f64 {
+    if score.is_nan() {
+        0.0
+    } else {
+        score
+    }
+}
@@ -0,0 +1,697 @@
+use crate::redis::core::blocking::{parse_timeout_millis, Block, BlockResult, TimeoutReply};
+use crate::redis::core::errors::{wrong_arguments, NOT_INTEGER, SYNTAX_ERROR};
+use crate::redis::core::request::Request;
+use crate::redis::core::WriteResp;
+use crate::redis::rdb::{
+    Database, Stream, StreamFields, StreamId, Trim, TrimThreshold, STREAM_NODE_MAX_ENTRIES,
+};
+use chrono::Utc;
+
+const NIL: Option<&[u8]> = None;
+pub(super) const INVALID_ID: &str = "Invalid stream ID specified as stream command argument";
+const EQUAL_OR_SMALLER: &str =
+    "The ID specified in XADD is equal or smaller than the target stream top item";
+
+pub fn xadd(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 5 {
+        return writer.write_error(wrong_arguments("xadd"));
+    }
+
+    let (no_make_stream, trim, index) = match parse_xadd_options(request) {
+        Ok(options) => options,
+        Err(e) => return writer.write_error(e),
+    };
+
+    let arguments = request.len().saturating_sub(index + 1);
+    if arguments == 0 || !arguments.is_multiple_of(2) {
+        return writer.write_error(wrong_arguments("xadd"));
+    }
+    let Some(id) = IdSpec::parse(request.get(index).unwrap()) else {
+        return writer.write_error(INVALID_ID);
+    };
+    let fields: StreamFields = (index + 1..request.len())
+        .step_by(2)
+        .map(|index| {
+            let field = request.get(index).unwrap().to_vec();
+            (field, request.get(index + 1).unwrap().to_vec())
+        })
+        .collect();
+
+    let key = request.get(1).unwrap();
+    let last_id = match storage.get_stream(key) {
+        Ok(Some(stream)) => stream.last_id(),
+        Ok(None) if no_make_stream => return writer.write_bulk_sting(&NIL),
+        Ok(None) => StreamId::MIN,
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let id = match id.resolve(last_id) {
+        Ok(id) => id,
+        Err(e) => return writer.write_error(e),
+    };
+
+    let stream = storage.get_or_insert_stream(key).unwrap();
+    stream.add(id, fields);
+    if let Some(trim) = trim {
+        stream.trim(&trim);
+    }
+    storage.touch(key);
+    writer.write_bulk_sting(&Some(id.to_string()))
+}
+
+pub fn xrange(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    range(writer, storage, request, "xrange", false)
+}
+
+pub fn xrevrange(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    range(writer, storage, request, "xrevrange", true)
+}
+
+fn range(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+    command: &str,
+    reverse: bool,
+) -> std::io::Result<()> {
+    if request.len() != 4 && request.len() != 6 {
+        return writer.write_error(wrong_arguments(command));
+    }
+
+    let (start, end) = if reverse { (3, 2) } else { (2, 3) };
+    let (Some(start), Some(end)) = (
+        parse_range_start(request.get(start).unwrap()),
+        parse_range_end(request.get(end).unwrap()),
+    ) else {
+        return writer.write_error(INVALID_ID);
+    };
+
+    let count = match request.get_str(4) {
+        None => None,
+        Some(option) if option.eq_ignore_ascii_case("count") => match request.get_int(5) {
+            Some(count) => Some(count.max(0) as usize),
+            None => return writer.write_error(NOT_INTEGER),
+        },
+        Some(_) => return writer.write_error(SYNTAX_ERROR),
+    };
+
+    let stream = match storage.get_stream(request.get(1).unwrap()) {
+        Ok(Some(stream)) => stream,
+        Ok(None) => return writer.write_array(&[NIL; 0]),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let (Some(start), Some(end)) = (start, end) else {
+        return writer.write_array(&[NIL; 0]);
+    };
+    let count = count.unwrap_or(usize::MAX);
+    let entries: Vec<_> = if reverse {
+        stream.range(start, end).rev().take(count).collect()
+    } else {
+        stream.range(start, end).take(count).collect()
+    };
+    write_entries(writer, &entries)
+}
+
+pub fn xread(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> BlockResult {
+    let mut count = 0;
+    let mut block = None;
+    let mut index = 1;
+    loop {
+        let Some(option) = request.get_str(index) else {
+            return writer.write_error(SYNTAX_ERROR).map(|_| None);
+        };
+        match option.to_lowercase().as_str() {
+            "count" if index + 1 < request.len() => {
+                match request.get_int(index + 1) {
+                    Some(value) => count = value.max(0) as usize,
+                    None => return writer.write_error(NOT_INTEGER).map(|_| None),
+                }
+                index += 2;
+            }
+            "block" if index + 1 < request.len() => {
+                match parse_timeout_millis(request.get(index + 1).unwrap()) {
+                    Ok(timeout) => block = Some(timeout),
+                    Err(e) => return writer.write_error(e).map(|_| None),
+                }
+                index += 2;
+            }
+            "streams" => {
+                index += 1;
+                break;
+            }
+            _ => return writer.write_error(SYNTAX_ERROR).map(|_| None),
+        }
+    }
+
+    let arguments = request.len() - index;
+    if arguments == 0 || !arguments.is_multiple_of(2) {
+        return writer
+            .write_error(
+                "Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified.",
+            )
+            .map(|_| None);
+    }
+
+    let streams = arguments / 2;
+    let mut reads = Vec::with_capacity(streams);
+    for offset in 0..streams {
+        let key = request.get(index + offset).unwrap();
+        let last_id = match storage.get_stream(key) {
+            Ok(stream) => stream.map_or(StreamId::MIN, |stream| stream.last_id()),
+            Err(e) => return writer.write_error(e.to_string()).map(|_| None),
+        };
+        let id = match request.get(index + streams + offset).unwrap() {
+            b"$" => last_id,
+            id => match StreamId::parse(id, 0) {
+                Some(id) => id,
+                None => return writer.write_error(INVALID_ID).map(|_| None),
+            },
+        };
+        reads.push((key, id));
+    }
+
+    let count = if count == 0 { usize::MAX } else { count };
+    let mut results = Vec::new();
+    for (key, id) in &reads {
+        let (Ok(Some(stream)), Some(start)) = (storage.get_stream(key), id.next()) else {
+            continue;
+        };
+        let entries: Vec<_> = stream
+            .range(start, StreamId::MAX)
+            .take(count)
+            .map(|(id, fields)| (*id, fields.clone()))
+            .collect();
+        if !entries.is_empty() {
+            results.push((*key, entries));
+        }
+    }
+
+    if results.is_empty() {
+        let Some(timeout) = block else {
+            return writer.write_null_array().map(|_| None);
+        };
+        // Once blocked, `$` must keep meaning the last ID at the time of the call.
+        let mut arguments: Vec<_> = (0..request.len())
+            .map(|index| request.get(index).unwrap().to_vec())
+            .collect();
+        for (offset, (_, id)) in reads.iter().enumerate() {
+            arguments[index + streams + offset] = id.to_string().into_bytes();
+        }
+        let keys = reads.iter().map(|(key, _)| key.to_vec()).collect();
+        let mut block = Block::new(keys, timeout, TimeoutReply::NullArray);
+        block.request = Some(Request::new(arguments));
+        return Ok(Some(block));
+    }
+
+    writer.write_array_header(results.len())?;
+    for (key, entries) in results {
+        writer.write_array_header(2)?;
+        writer.write_bulk_sting(&Some(key))?;
+        let entries: Vec<_> = entries.iter().map(|(id, fields)| (id, fields)).collect();
+        write_entries(writer, &entries)?;
+    }
+    Ok(None)
+}
+
+pub fn xlen(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("xlen"));
+    }
+
+    match storage.get_stream(request.get(1).unwrap()) {
+        Ok(stream) => writer.write_integer(stream.map_or(0, Stream::len) as i64),
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn xdel(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments("xdel"));
+    }
+
+    let mut ids = Vec::with_capacity(request.len() - 2);
+    for index in 2..request.len() {
+        match StreamId::parse(request.get(index).unwrap(), 0) {
+            Some(id) => ids.push(id),
+            None => return writer.write_error(INVALID_ID),
+        }
+    }
+
+    let key = request.get(1).unwrap();
+    match storage.get_stream_mut(key) {
+        Ok(Some(stream)) => {
+            let removed = ids.iter().filter(|id| stream.remove(id)).count();
+            if removed > 0 {
+                storage.touch(key);
+            }
+            writer.write_integer(removed as i64)
+        }
+        Ok(None) => writer.write_integer(0),
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn xtrim(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 4 {
+        return writer.write_error(wrong_arguments("xtrim"));
+    }
+
+    let mut index = 2;
+    let trim = match request.get_str(index).unwrap().to_lowercase().as_str() {
+        "maxlen" | "minid" => match parse_trim(request, &mut index) {
+            Ok(trim) => trim,
+            Err(e) => return writer.write_error(e),
+        },
+        _ => return writer.write_error(SYNTAX_ERROR),
+    };
+    if index != request.len() {
+        return writer.write_error(SYNTAX_ERROR);
+    }
+
+    let key = request.get(1).unwrap();
+    match storage.get_stream_mut(key) {
+        Ok(Some(stream)) => {
+            let trimmed = stream.trim(&trim);
+            if trimmed > 0 {
+                storage.touch(key);
+            }
+            writer.write_integer(trimmed as i64)
+        }
+        Ok(None) => writer.write_integer(0),
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+/// Writes entries as an array of `[id, [field, value, ...]]` pairs.
+pub(super) fn write_entries(
+    writer: &mut impl WriteResp,
+    entries: &[(&StreamId, &StreamFields)],
+) -> std::io::Result<()> {
+    writer.write_array_header(entries.len())?;
+    for (id, fields) in entries {
+        write_entry(writer, id, fields)?;
+    }
+    Ok(())
+}
+
+pub(super) fn write_entry(
+    writer: &mut impl WriteResp,
+    id: &StreamId,
+    fields: &StreamFields,
+) -> std::io::Result<()> {
+    writer.write_array_header(2)?;
+    writer.write_bulk_sting(&Some(id.to_string()))?;
+    writer.write_array_header(fields.len() * 2)?;
+    for (field, value) in fields {
+        writer.write_bulk_sting(&Some(field))?;
+        writer.write_bulk_sting(&Some(value))?;
+    }
+    Ok(())
+}
+
+/// Parses the NOMKSTREAM and trimming options of XADD, returning them along with the index of
+/// the entry ID that follows.
+fn parse_xadd_options(request: &Request) -> Result<(bool, Option<Trim>, usize), String> {
+    let mut no_make_stream = false;
+    let mut trim = None;
+    let mut index = 2;
+    while index < request.len() {
+        let option = request.get_str(index).unwrap().to_lowercase();
+        match option.as_str() {
+            "nomkstream" => {
+                no_make_stream = true;
+                index += 1;
+            }
+            "maxlen" | "minid" => trim = Some(parse_trim(request, &mut index)?),
+            _ => break,
+        }
+    }
+    Ok((no_make_stream, trim, index))
+}
+
+/// The index of the entry ID in an XADD request that succeeded.
+pub(super) fn xadd_id_index(request: &Request) -> usize {
+    parse_xadd_options(request).map_or(2, |(_, _, index)| index)
+}
+
+/// Parses `<MAXLEN | MINID> [= | ~] threshold [LIMIT count]` starting at `index`, leaving
+/// `index` after the last consumed argument.
+fn parse_trim(request: &Request, index: &mut usize) -> Result<Trim, String> {
+    let strategy = request.get_str(*index).unwrap().to_lowercase();
+    *index += 1;
+
+    let mut approximate = false;
+    match request.get(*index) {
+        Some(b"~") => {
+            approximate = true;
+            *index += 1;
+        }
+        Some(b"=") => *index += 1,
+        _ => {}
+    }
+
+    let Some(threshold) = request.get(*index) else {
+        return Err(SYNTAX_ERROR.to_string());
+    };
+    let threshold = if strategy == "maxlen" {
+        match request.get_int(*index) {
+            Some(max_len) if max_len >= 0 => TrimThreshold::MaxLen(max_len as usize),
+            Some(_) => return Err("The MAXLEN argument must be >= 0.".to_string()),
+            None => return Err(NOT_INTEGER.to_string()),
+        }
+    } else {
+        match StreamId::parse(threshold, 0) {
+            Some(min_id) => TrimThreshold::MinId(min_id),
+            None => return Err(INVALID_ID.to_string()),
+        }
+    };
+    *index += 1;
+
+    let mut limit = if approximate {
+        100 * STREAM_NODE_MAX_ENTRIES
+    } else {
+        0
+    };
+    if request
+        .get_str(*index)
+        .is_some_and(|option| option.eq_ignore_ascii_case("limit"))
+    {
+        limit = match request.get_int(*index + 1) {
+            Some(limit) if limit >= 0 => limit as usize,
+            Some(_) => return Err("The LIMIT argument must be >= 0.".to_string()),
+            None => return Err(NOT_INTEGER.to_string()),
+        };
+        if !approximate {
+            return Err(
+                "syntax error, LIMIT cannot be used without the special ~ option".to_string(),
+            );
+        }
+        *index += 2;
+    }
+
+    Ok(Trim {
+        threshold,
+        approximate,
+        limit,
+    })
+}
+
+/// Parses the start of a range: `-`, an ID with the sequence defaulting to 0, or an exclusive
+/// `(`ID. The inner `None` stands for an exclusive bound past the last possible ID.
+pub(super) fn parse_range_start(value: &[u8]) -> Option<Option<StreamId>> {
+    match value {
+        b"-" => Some(Some(StreamId::MIN)),
+        b"+" => Some(Some(StreamId::MAX)),
+        _ => match value.strip_prefix(b"(") {
+            Some(value) => Some(StreamId::parse(value, 0)?.next()),
+            None => Some(Some(StreamId::parse(value, 0)?)),
+        },
+    }
+}
+
+/// Parses the end of a range: `+`, an ID with the sequence defaulting to the maximum, or an
+/// exclusive `(`ID.
+pub(super) fn parse_range_end(value: &[u8]) -> Option<Option<StreamId>> {
+    match value {
+        b"-" => Some(Some(StreamId::MIN)),
+        b"+" => Some(Some(StreamId::MAX)),
+        _ => match value.strip_prefix(b"(") {
+            Some(value) => Some(StreamId::parse(value, u64::MAX)?.previous()),
+            None => Some(Some(StreamId::parse(value, u64::MAX)?)),
+        },
+    }
+}
+
+/// The ID argument of XADD: `*`, `<ms>-*` or an explicit ID.
+enum IdSpec {
+    Auto,
+    AutoSequence(u64),
+    Explicit(StreamId),
+}
+
+impl IdSpec {
+    fn parse(value: &[u8]) -> Option<Self> {
+        if value == b"*" {
+            return Some(IdSpec::Auto);
+        }
+        match value.strip_suffix(b"-*") {
+            Some(ms) if !ms.contains(&b'-') => {
+                Some(IdSpec::AutoSequence(StreamId::parse(ms, 0)?.ms))
+            }
+            Some(_) => None,
+            None => Some(IdSpec::Explicit(StreamId::parse(value, 0)?)),
+        }
+    }
+
+    /// Picks the ID of a new entry, which must be greater than the stream's last ID.
+    fn resolve(&self, last_id: StreamId) -> Result<StreamId, &'static str> {
+        match self {
+            IdSpec::Auto => {
+                let now = Utc::now().timestamp_millis() as u64;
+                if now > last_id.ms {
+                    Ok(StreamId::new(now, 0))
+                } else {
+                    last_id.next().ok_or(
+                        "The stream has exhausted the last possible ID, unable to add more items",
+                    )
+                }
+            }
+            IdSpec::AutoSequence(ms) => {
+                if *ms > last_id.ms {
+                    Ok(StreamId::new(*ms, 0))
+                } else if *ms == last_id.ms && last_id.seq < u64::MAX {
+                    Ok(StreamId::new(*ms, last_id.seq + 1))
+                } else {
+                    Err(EQUAL_OR_SMALLER)
+                }
+            }
+            IdSpec::Explicit(id) => {
+                if *id == StreamId::MIN {
+                    Err("The ID specified in XADD must be greater than 0-0")
+                } else if *id <= last_id {
+                    Err(EQUAL_OR_SMALLER)
+                } else {
+                    Ok(*id)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::request::Request;
+    use crate::redis::core::stream::{xadd, xlen, xrange, xread, xrevrange, xtrim};
+    use crate::redis::rdb::Database;
+
+    fn add(storage: &mut Database, arguments: &[&str]) -> String {
+        let mut reply = Vec::new();
+        xadd(&mut reply, storage, &Request::from_strs(arguments)).unwrap();
+        String::from_utf8(reply).unwrap()
+    }
+
+    fn entries(ids: &[&str]) -> String {
+        let mut reply = format!("*{}\r\n", ids.len());
+        for id in ids {
+            reply += &format!(
+                "*2\r\n${}\r\n{}\r\n*2\r\n$1\r\nf\r\n$1\r\nv\r\n",
+                id.len(),
+                id
+            );
+        }
+        reply
+    }
+
+    #[test]
+    fn test_xadd_validates_and_generates_ids() {
+        let mut storage = Database::default();
+        assert_eq!(
+            add(&mut storage, &["XADD", "s", "1-1", "f", "v"]),
+            "$3\r\n1-1\r\n"
+        );
+        for id in ["1-1", "0-5", "1-0", "0-*"] {
+            assert_eq!(
+                add(&mut storage, &["XADD", "s", id, "f", "v"]),
+                "-The ID specified in XADD is equal or smaller than the target stream top item\r\n",
+                "{id}"
+            );
+        }
+        assert_eq!(
+            add(&mut storage, &["XADD", "s", "1-*", "f", "v"]),
+            "$3\r\n1-2\r\n"
+        );
+        assert_eq!(
+            add(&mut storage, &["XADD", "s", "5-*", "f", "v"]),
+            "$3\r\n5-0\r\n"
+        );
+        assert_eq!(
+            add(&mut storage, &["XADD", "other", "0-0", "f", "v"]),
+            "-The ID specified in XADD must be greater than 0-0\r\n"
+        );
+        assert_eq!(
+            add(&mut storage, &["XADD", "s", "1-x", "f", "v"]),
+            "-Invalid stream ID specified as stream command argument\r\n"
+        );
+        assert_eq!(
+            add(&mut storage, &["XADD", "s", "NOMKSTREAM", "*", "f"]),
+            "-wrong number of arguments for 'xadd' command\r\n"
+        );
+        assert_eq!(
+            add(
+                &mut storage,
+                &["XADD", "missing", "NOMKSTREAM", "*", "f", "v"]
+            ),
+            "$-1\r\n"
+        );
+        assert!(!storage.contains_key(b"missing"));
+
+        let reply = add(&mut storage, &["XADD", "s", "*", "f", "v"]);
+        let id = reply.lines().nth(1).unwrap();
+        let (ms, seq) = id.split_once('-').unwrap();
+        assert!(ms.parse::<u64>().unwrap() > 5);
+        assert_eq!(seq, "0");
+    }
+
+    #[test]
+    fn test_streams_are_trimmed_by_length_and_id() {
+        let mut storage = Database::default();
+        for id in ["1-0", "2-0", "3-0", "4-0"] {
+            add(&mut storage, &["XADD", "s", "MAXLEN", "3", id, "f", "v"]);
+        }
+        let mut reply = Vec::new();
+        xrange(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["XRANGE", "s", "-", "+"]),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            entries(&["2-0", "3-0", "4-0"])
+        );
+
+        let mut reply = Vec::new();
+        for arguments in [
+            &["XTRIM", "s", "MINID", "3"][..],
+            &["XTRIM", "s", "MINID", "=", "3"],
+            &["XTRIM", "s", "MAXLEN", "-1"],
+            &["XTRIM", "s", "MAXLEN", "1", "LIMIT", "10"],
+            &["XTRIM", "s", "MAXLEN", "0"],
+        ] {
+            xtrim(&mut reply, &mut storage, &Request::from_strs(arguments)).unwrap();
+        }
+        xlen(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["XLEN", "s"]),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            ":1\r\n:0\r\n-The MAXLEN argument must be >= 0.\r\n\
+             -syntax error, LIMIT cannot be used without the special ~ option\r\n:2\r\n:0\r\n"
+        );
+        assert!(storage.contains_key(b"s"));
+    }
+
+    #[test]
+    fn test_ranges_honour_exclusive_bounds() {
+        let mut storage = Database::default();
+        for id in ["1-0", "2-0", "3-0"] {
+            add(&mut storage, &["XADD", "s", id, "f", "v"]);
+        }
+
+        for (arguments, expected) in [
+            (&["XRANGE", "s", "(1-0", "+"][..], entries(&["2-0", "3-0"])),
+            (&["XRANGE", "s", "-", "(3"], entries(&["1-0", "2-0", "3-0"])),
+            (&["XRANGE", "s", "-", "(3-0"], entries(&["1-0", "2-0"])),
+            (&["XRANGE", "s", "(1", "(3-0"], entries(&["2-0"])),
+            (&["XRANGE", "s", "2", "+", "COUNT", "1"], entries(&["2-0"])),
+            (
+                &[
+                    "XRANGE",
+                    "s",
+                    "(18446744073709551615-18446744073709551615",
+                    "+",
+                ],
+                entries(&[]),
+            ),
+            (&["XREVRANGE", "s", "(3-0", "-"], entries(&["2-0", "1-0"])),
+            (
+                &["XREVRANGE", "s", "+", "(2-0", "COUNT", "5"],
+                entries(&["3-0"]),
+            ),
+        ] {
+            let mut reply = Vec::new();
+            let request = Request::from_strs(arguments);
+            if arguments[0] == "XRANGE" {
+                xrange(&mut reply, &mut storage, &request).unwrap();
+            } else {
+                xrevrange(&mut reply, &mut storage, &request).unwrap();
+            }
+            assert_eq!(String::from_utf8(reply).unwrap(), expected, "{arguments:?}");
+        }
+    }
+
+    #[test]
+    fn test_xread_from_the_last_id() {
+        let mut storage = Database::default();
+        for id in ["1-0", "2-0"] {
+            add(&mut storage, &["XADD", "s", id, "f", "v"]);
+        }
+
+        let mut reply = Vec::new();
+        let request = Request::from_strs(&["XREAD", "COUNT", "1", "STREAMS", "s", "1"]);
+        assert!(xread(&mut reply, &mut storage, &request).unwrap().is_none());
+        let request = Request::from_strs(&["XREAD", "STREAMS", "s", "$"]);
+        assert!(xread(&mut reply, &mut storage, &request).unwrap().is_none());
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            format!("*1\r\n*2\r\n$1\r\ns\r\n{}*-1\r\n", entries(&["2-0"]))
+        );
+
+        let request = Request::from_strs(&["XREAD", "BLOCK", "0", "STREAMS", "s", "$"]);
+        let block = xread(&mut Vec::new(), &mut storage, &request)
+            .unwrap()
+            .unwrap();
+        assert_eq!(block.keys, vec![b"s".to_vec()]);
+        assert_eq!(block.timeout, None);
+        let request = block.request.unwrap();
+        assert_eq!(request.get(5), Some(b"2-0".as_slice()));
+
+        add(&mut storage, &["XADD", "s", "3-0", "f", "v"]);
+        let mut reply = Vec::new();
+        assert!(xread(&mut reply, &mut storage, &request).unwrap().is_none());
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            format!("*1\r\n*2\r\n$1\r\ns\r\n{}", entries(&["3-0"]))
+        );
+    }
+}
@@ -0,0 +1,771 @@
+use crate::redis::core::errors::{wrong_arguments, NOT_INTEGER, SYNTAX_ERROR};
+use crate::redis::core::float::{format_float, parse_float};
+use crate::redis::core::request::Request;
+use crate::redis::core::set_key_value::expire_at;
+use crate::redis::core::WriteResp;
+use crate::redis::rdb::{Database, Value};
+use chrono::Utc;
+
+const NIL: Option<&[u8]> = None;
+const OK: &str = "OK";
+const NOT_FLOAT: &str = "value is not a valid float";
+/// The largest string Redis accepts by default, its proto-max-bulk-len.
+const MAX_STRING_LENGTH: usize = 512 * 1024 * 1024;
+
+pub fn incr(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("incr"));
+    }
+    increment(writer, storage, request.get(1).unwrap(), 1)
+}
+
+pub fn decr(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("decr"));
+    }
+    increment(writer, storage, request.get(1).unwrap(), -1)
+}
+
+pub fn incrby(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("incrby"));
+    }
+    let Some(increment_by) = request.get_int(2) else {
+        return writer.write_error(NOT_INTEGER);
+    };
+    increment(writer, storage, request.get(1).unwrap(), increment_by)
+}
+
+pub fn decrby(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("decrby"));
+    }
+    let Some(decrement) = request.get_int(2) else {
+        return writer.write_error(NOT_INTEGER);
+    };
+    let Some(increment_by) = decrement.checked_neg() else {
+        return writer.write_error("decrement would overflow");
+    };
+    increment(writer, storage, request.get(1).unwrap(), increment_by)
+}
+
+/// Adds `increment_by` to the integer stored at `key`, a missing key counting as 0, and keeps
+/// its expiration.
+fn increment(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    key: &[u8],
+    increment_by: i64,
+) -> std::io::Result<()> {
+    let current: i64 = match storage.get(key) {
+        Ok(None) => 0,
+        Ok(Some(value)) => match std::str::from_utf8(value).ok().and_then(|x| x.parse().ok()) {
+            Some(value) => value,
+            None => return writer.write_error(NOT_INTEGER),
+        },
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let Some(value) = current.checked_add(increment_by) else {
+        return writer.write_error("increment or decrement would overflow");
+    };
+    storage.set_keep_ttl(key.to_vec(), value.to_string().into_bytes());
+    writer.write_integer(value)
+}
+
+pub fn incrbyfloat(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("incrbyfloat"));
+    }
+
+    let Some(increment_by) = parse_float(request.get(2).unwrap()) else {
+        return writer.write_error(NOT_FLOAT);
+    };
+    let key = request.get(1).unwrap();
+    let current = match storage.get(key) {
+        Ok(None) => 0.0,
+        Ok(Some(value)) => match parse_float(value) {
+            Some(value) => value,
+            None => return writer.write_error(NOT_FLOAT),
+        },
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    let value = current + increment_by;
+    if !value.is_finite() {
+        return writer.write_error("increment would produce NaN or Infinity");
+    }
+    let value = format_float(value).into_bytes();
+    storage.set_keep_ttl(key.to_vec(), value.clone());
+    writer.write_bulk_sting(&Some(value))
+}
+
+pub fn append(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("append"));
+    }
+
+    let key = request.get(1).unwrap();
+    match storage.get_or_insert_string(key) {
+        Ok(value) => {
+            value.extend_from_slice(request.get(2).unwrap());
+            let len = value.len();
+            storage.touch(key);
+            writer.write_integer(len as i64)
+        }
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn strlen(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("strlen"));
+    }
+
+    match storage.get(request.get(1).unwrap()) {
+        Ok(value) => writer.write_integer(value.map_or(0, |value| value.len()) as i64),
+        Err(e) => writer.write_error(e.to_string()),
+    }
+}
+
+pub fn getrange(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("getrange"));
+    }
+
+    let (Some(start), Some(end)) = (request.get_int(2), request.get_int(3)) else {
+        return writer.write_error(NOT_INTEGER);
+    };
+    let value = match storage.get(request.get(1).unwrap()) {
+        Ok(value) => value.unwrap_or_default(),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+
+    // Unlike list ranges, indexes before the start of the string are clamped to 0 on both ends.
+    let len = value.len() as i64;
+    if (start < 0 && end < 0 && start > end) || len == 0 {
+        return writer.write_bulk_sting(&Some(b""));
+    }
+    let start = if start < 0 { len + start } else { start }.max(0);
+    let end = if end < 0 { len + end } else { end }.clamp(0, len - 1);
+    if start > end {
+        return writer.write_bulk_sting(&Some(b""));
+    }
+    writer.write_bulk_sting(&Some(&value[start as usize..=end as usize]))
+}
+
+pub fn setrange(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments("setrange"));
+    }
+
+    let offset = match request.get_int(2) {
+        Some(offset) if offset >= 0 => offset as usize,
+        Some(_) => return writer.write_error("offset is out of range"),
+        None => return writer.write_error(NOT_INTEGER),
+    };
+    let key = request.get(1).unwrap();
+    let update = request.get(3).unwrap();
+
+    // An empty update neither creates the key nor pads it.
+    if update.is_empty() {
+        return match storage.get(key) {
+            Ok(value) => writer.write_integer(value.map_or(0, |value| value.len()) as i64),
+            Err(e) => writer.write_error(e.to_string()),
+        };
+    }
+    if offset + update.len() > MAX_STRING_LENGTH {
+        return writer.write_error("string exceeds maximum allowed size (proto-max-bulk-len)");
+    }
+
+    let value = match storage.get_or_insert_string(key) {
+        Ok(value) => value,
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    if value.len() < offset + update.len() {
+        value.resize(offset + update.len(), 0);
+    }
+    value[offset..offset + update.len()].copy_from_slice(update);
+    let len = value.len();
+    storage.touch(key);
+    writer.write_integer(len as i64)
+}
+
+pub fn getdel(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 2 {
+        return writer.write_error(wrong_arguments("getdel"));
+    }
+
+    let key = request.get(1).unwrap();
+    let value = match storage.get(key) {
+        Ok(value) => value.map(<[u8]>::to_vec),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    if value.is_some() {
+        storage.remove(key);
+    }
+    writer.write_bulk_sting(&value)
+}
+
+pub fn getex(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 2 {
+        return writer.write_error(wrong_arguments("getex"));
+    }
+
+    // `None` leaves the expiration alone, `Some(None)` removes it.
+    let mut expiration = None;
+    let mut index = 2;
+    while index < request.len() {
+        let option = request.get_str(index).unwrap().to_lowercase();
+        match option.as_str() {
+            "persist" if expiration.is_none() => expiration = Some(None),
+            "ex" | "px" | "exat" | "pxat" if expiration.is_none() && index + 1 < request.len() => {
+                let Some(value) = request.get_int(index + 1) else {
+                    return writer.write_error(NOT_INTEGER);
+                };
+                match expire_at(&option, value) {
+                    Some(time) => expiration = Some(Some(time)),
+                    None => return writer.write_error("invalid expire time in 'getex' command"),
+                }
+                index += 1;
+            }
+            _ => return writer.write_error(SYNTAX_ERROR),
+        }
+        index += 1;
+    }
+
+    let key = request.get(1).unwrap();
+    let value = match storage.get(key) {
+        Ok(Some(value)) => value.to_vec(),
+        Ok(None) => return writer.write_bulk_sting(&NIL),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    match expiration {
+        Some(Some(time)) if time as i64 <= Utc::now().timestamp_millis() => {
+            storage.remove(key);
+        }
+        Some(time) => {
+            storage.set_expire_time(key, time);
+        }
+        None => {}
+    }
+    writer.write_bulk_sting(&Some(value))
+}
+
+pub fn getset(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("getset"));
+    }
+
+    let key = request.get(1).unwrap();
+    let previous = match storage.get(key) {
+        Ok(previous) => previous.map(<[u8]>::to_vec),
+        Err(e) => return writer.write_error(e.to_string()),
+    };
+    storage.set(key.to_vec(), request.get(2).unwrap().to_vec(), None);
+    writer.write_bulk_sting(&previous)
+}
+
+pub fn setnx(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() != 3 {
+        return writer.write_error(wrong_arguments("setnx"));
+    }
+
+    let key = request.get(1).unwrap();
+    if storage.contains_key(key) {
+        return writer.write_integer(0);
+    }
+    storage.set(key.to_vec(), request.get(2).unwrap().to_vec(), None);
+    writer.write_integer(1)
+}
+
+pub fn setex(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    set_with_expiration(writer, storage, request, "setex")
+}
+
+pub fn psetex(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    set_with_expiration(writer, storage, request, "psetex")
+}
+
+fn set_with_expiration(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+    command: &str,
+) -> std::io::Result<()> {
+    if request.len() != 4 {
+        return writer.write_error(wrong_arguments(command));
+    }
+
+    let Some(value) = request.get_int(2) else {
+        return writer.write_error(NOT_INTEGER);
+    };
+    let option = if command == "setex" { "ex" } else { "px" };
+    let Some(time) = expire_at(option, value) else {
+        return writer.write_error(format!("invalid expire time in '{}' command", command));
+    };
+    let key = request.get(1).unwrap().to_vec();
+    storage.set(key, request.get(3).unwrap().to_vec(), Some(time));
+    writer.write_simple_string(OK)
+}
+
+pub fn mget(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 2 {
+        return writer.write_error(wrong_arguments("mget"));
+    }
+
+    let keys: Vec<_> = (1..request.len())
+        .map(|index| request.get(index).unwrap())
+        .collect();
+    let values: Vec<_> = storage
+        .get_values(&keys)
+        .into_iter()
+        .map(|value| match value {
+            Some(Value::String(value)) => Some(value),
+            _ => None,
+        })
+        .collect();
+    writer.write_array(&values)
+}
+
+pub fn mset(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 3 || request.len().is_multiple_of(2) {
+        return writer.write_error(wrong_arguments("mset"));
+    }
+
+    set_pairs(storage, request);
+    writer.write_simple_string(OK)
+}
+
+pub fn msetnx(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 3 || request.len().is_multiple_of(2) {
+        return writer.write_error(wrong_arguments("msetnx"));
+    }
+
+    // Nothing is set if any of the keys already exists.
+    let exists = (1..request.len())
+        .step_by(2)
+        .any(|index| storage.contains_key(request.get(index).unwrap()));
+    if exists {
+        return writer.write_integer(0);
+    }
+    set_pairs(storage, request);
+    writer.write_integer(1)
+}
+
+/// Stores the key and value pairs following the command name.
+fn set_pairs(storage: &mut Database, request: &Request) {
+    for index in (1..request.len()).step_by(2) {
+        let key = request.get(index).unwrap().to_vec();
+        storage.set(key, request.get(index + 1).unwrap().to_vec(), None);
+    }
+}
+
+pub fn lcs(
+    writer: &mut impl WriteResp,
+    storage: &mut Database,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 3 {
+        return writer.write_error(wrong_arguments("lcs"));
+    }
+
+    let (mut len, mut idx, mut with_match_len) = (false, false, false);
+    let mut min_match_len = 0;
+    let mut index = 3;
+    while index < request.len() {
+        let option = request.get_str(index).unwrap().to_lowercase();
+        match option.as_str() {
+            "len" => len = true,
+            "idx" => idx = true,
+            "withmatchlen" => with_match_len = true,
+            "minmatchlen" if index + 1 < request.len() => {
+                match request.get_int(index + 1) {
+                    Some(value) => min_match_len = value.max(0) as usize,
+                    None => return writer.write_error(NOT_INTEGER),
+                }
+                index += 1;
+            }
+            _ => return writer.write_error(SYNTAX_ERROR),
+        }
+        index += 1;
+    }
+    if len && idx {
+        return writer.write_error("If you want both the length and indexes, please just use IDX.");
+    }
+
+    let keys = [request.get(1).unwrap(), request.get(2).unwrap()];
+    let mut strings = Vec::with_capacity(2);
+    for value in storage.get_values(&keys) {
+        match value {
+            None => strings.push(Vec::new()),
+            Some(Value::String(value)) => strings.push(value.clone()),
+            Some(_) => return writer.write_error("The specified keys must contain string values"),
+        }
+    }
+    let (a, b) = (&strings[0], &strings[1]);
+
+    // The table holds a u32 length for every pair of prefixes.
+    let table_size = (a.len() + 1)
+        .checked_mul(b.len() + 1)
+        .and_then(|cells| cells.checked_mul(std::mem::size_of::<u32>()));
+    if table_size.is_none_or(|size| size > MAX_STRING_LENGTH) {
+        return writer.write_error(
+            "Insufficient memory, transient memory for LCS exceeds proto-max-bulk-len",
+        );
+    }
+    let common = LongestCommonSubsequence::new(a, b);
+
+    if len {
+        return writer.write_integer(common.len() as i64);
+    }
+    if !idx {
+        return writer.write_bulk_sting(&Some(common.subsequence()));
+    }
+
+    let matches: Vec<_> = common
+        .matches()
+        .into_iter()
+        .filter(|(a_range, _)| a_range.1 - a_range.0 + 1 >= min_match_len)
+        .collect();
+    writer.write_array_header(4)?;
+    writer.write_bulk_sting(&Some("matches"))?;
+    writer.write_array_header(matches.len())?;
+    for (a_range, b_range) in matches {
+        writer.write_array_header(if with_match_len { 3 } else { 2 })?;
+        for (start, end) in [a_range, b_range] {
+            writer.write_array_header(2)?;
+            writer.write_integer(start as i64)?;
+            writer.write_integer(end as i64)?;
+        }
+        if with_match_len {
+            writer.write_integer((a_range.1 - a_range.0 + 1) as i64)?;
+        }
+    }
+    writer.write_bulk_sting(&Some("len"))?;
+    writer.write_integer(common.len() as i64)
+}
+
+type MatchRange = (usize, usize);
+
+/// The dynamic programming table of the longest common subsequence of two strings, where
+/// `lengths[i][j]` is the length of the LCS of `a[..i]` and `b[..j]`.
+struct LongestCommonSubsequence<'a> {
+    a: &'a [u8],
+    b: &'a [u8],
+    lengths: Vec<Vec<u32>>,
+}
+
+impl<'a> LongestCommonSubsequence<'a> {
+    fn new(a: &'a [u8], b: &'a [u8]) -> Self {
+        let mut lengths = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+        for i in 1..=a.len() {
+            for j in 1..=b.len() {
+                lengths[i][j] = if a[i - 1] == b[j - 1] {
+                    lengths[i - 1][j - 1] + 1
+                } else {
+                    lengths[i - 1][j].max(lengths[i][j - 1])
+                };
+            }
+        }
+        Self { a, b, lengths }
+    }
+
+    fn len(&self) -> usize {
+        self.lengths[self.a.len()][self.b.len()] as usize
+    }
+
+    fn subsequence(&self) -> Vec<u8> {
+        let mut subsequence = Vec::with_capacity(self.len());
+        self.backtrack(|i, _| subsequence.push(self.a[i]));
+        subsequence.reverse();
+        subsequence
+    }
+
+    /// The contiguous matching ranges in `a` and `b`, both inclusive, from the end of the
+    /// strings to their start like Redis reports them.
+    fn matches(&self) -> Vec<(MatchRange, MatchRange)> {
+        let mut matches: Vec<(MatchRange, MatchRange)> = Vec::new();
+        let mut last: Option<(usize, usize)> = None;
+        self.backtrack(|i, j| {
+            match (last, matches.last_mut()) {
+                (Some((last_i, last_j)), Some((a_range, b_range)))
+                    if last_i == i + 1 && last_j == j + 1 =>
+                {
+                    a_range.0 = i;
+                    b_range.0 = j;
+                }
+                _ => matches.push(((i, i), (j, j))),
+            }
+            last = Some((i, j));
+        });
+        matches
+    }
+
+    /// Walks the table back from the end, calling `on_match` with the positions in `a` and `b`
+    /// of every byte of the subsequence, last first.
+    fn backtrack(&self, mut on_match: impl FnMut(usize, usize)) {
+        let (mut i, mut j) = (self.a.len(), self.b.len());
+        while i > 0 && j > 0 {
+            if self.a[i - 1] == self.b[j - 1] {
+                on_match(i - 1, j - 1);
+                i -= 1;
+                j -= 1;
+            } else if self.lengths[i - 1][j] > self.lengths[i][j - 1] {
+                i -= 1;
+            } else {
+                j -= 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::request::Request;
+    use crate::redis::core::string::{
+        decrby, incr, incrby, incrbyfloat, lcs, mget, mset, msetnx, LongestCommonSubsequence,
+    };
+    use crate::redis::rdb::Database;
+
+    #[test]
+    fn test_longest_common_subsequence() {
+        let common = LongestCommonSubsequence::new(b"ohmytext", b"mynewtext");
+        assert_eq!(common.len(), 6);
+        assert_eq!(common.subsequence(), b"mytext");
+        assert_eq!(common.matches(), vec![((4, 7), (5, 8)), ((2, 3), (0, 1))]);
+    }
+
+    #[test]
+    fn test_lcs_table_is_bounded_in_bytes() {
+        let mut storage = Database::default();
+        // 11587 * 11587 cells fit in 512 MB as bytes but not as u32 lengths.
+        storage.set(b"a".to_vec(), vec![b'x'; 11586], None);
+        storage.set(b"b".to_vec(), vec![b'y'; 11586], None);
+        let mut reply = Vec::new();
+        let request = Request::new(vec![b"LCS".to_vec(), b"a".to_vec(), b"b".to_vec()]);
+        lcs(&mut reply, &mut storage, &request).unwrap();
+        assert_eq!(
+            reply,
+            b"-Insufficient memory, transient memory for LCS exceeds proto-max-bulk-len\r\n"
+        );
+    }
+
+    #[test]
+    fn test_counters_refuse_to_overflow() {
+        let mut storage = Database::default();
+        storage.set(
+            b"n".to_vec(),
+            b"9223372036854775806".to_vec(),
+            Some(4102444800000),
+        );
+        let mut reply = Vec::new();
+        incr(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["INCR", "n"]),
+        )
+        .unwrap();
+        incr(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["INCR", "n"]),
+        )
+        .unwrap();
+        incrby(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["INCRBY", "n", "-1"]),
+        )
+        .unwrap();
+        decrby(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["DECRBY", "m", "-9223372036854775808"]),
+        )
+        .unwrap();
+        incrby(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["INCRBY", "m", "9223372036854775808"]),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            ":9223372036854775807\r\n-increment or decrement would overflow\r\n\
+             :9223372036854775806\r\n-decrement would overflow\r\n\
+             -value is not an integer or out of range\r\n"
+        );
+        assert_eq!(storage.get_expire_time(b"n"), Some(Some(4102444800000)));
+        assert!(!storage.contains_key(b"m"));
+    }
+
+    #[test]
+    fn test_counters_check_the_value() {
+        let mut storage = Database::default();
+        storage.set(b"s".to_vec(), b"1.5".to_vec(), None);
+        storage
+            .get_or_insert_list(b"l")
+            .unwrap()
+            .push_back(b"1".to_vec());
+        let mut reply = Vec::new();
+        incr(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["INCR", "s"]),
+        )
+        .unwrap();
+        incr(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["INCR", "l"]),
+        )
+        .unwrap();
+        incrbyfloat(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["INCRBYFLOAT", "s", "0.1"]),
+        )
+        .unwrap();
+        incrbyfloat(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["INCRBYFLOAT", "s", "inf"]),
+        )
+        .unwrap();
+        incrbyfloat(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["INCRBYFLOAT", "s", "x"]),
+        )
+        .unwrap();
+        incrbyfloat(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["INCRBYFLOAT", "f", "3.0e3"]),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            "-value is not an integer or out of range\r\n\
+             -WRONGTYPE Operation against a key holding the wrong kind of value\r\n\
+             $3\r\n1.6\r\n-increment would produce NaN or Infinity\r\n\
+             -value is not a valid float\r\n$4\r\n3000\r\n"
+        );
+    }
+
+    #[test]
+    fn test_msetnx_sets_all_keys_or_none() {
+        let mut storage = Database::default();
+        storage
+            .get_or_insert_list(b"l")
+            .unwrap()
+            .push_back(b"1".to_vec());
+        let mut reply = Vec::new();
+        mset(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["MSET", "a", "1", "b", "2", "a", "3"]),
+        )
+        .unwrap();
+        msetnx(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["MSETNX", "c", "1", "b", "4"]),
+        )
+        .unwrap();
+        msetnx(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["MSETNX", "c", "1", "d", "2"]),
+        )
+        .unwrap();
+        mset(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["MSET", "a", "1", "b"]),
+        )
+        .unwrap();
+        mget(
+            &mut reply,
+            &mut storage,
+            &Request::from_strs(&["MGET", "a", "b", "c", "d", "l", "x"]),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            "+OK\r\n:0\r\n:1\r\n-wrong number of arguments for 'mset' command\r\n\
+             *6\r\n$1\r\n3\r\n$1\r\n2\r\n$1\r\n1\r\n$1\r\n2\r\n$-1\r\n$-1\r\n"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use crate::redis::core::errors::wrong_arguments;
+use crate::redis::core::request::Request;
+use crate::redis::core::WriteResp;
+use crate::redis::rdb::RedisStorage;
+
+const OK: &str = "OK";
+
+/// The commands a transaction runs itself rather than queue.
+const TRANSACTION_COMMANDS: [&str; 6] = ["exec", "discard", "multi", "watch", "quit", "reset"];
+
+struct Watch {
+    db: usize,
+    key: Vec<u8>,
+    version: u64,
+}
+
+/// The transaction state of a client: the commands queued since MULTI and the keys it
+/// watches.
+#[derive(Default)]
+pub struct Transaction {
+    /// The commands queued since MULTI, `None` outside a transaction.
+    queued: Option<Vec<Request>>,
+    /// Whether a command was rejected while queueing, which makes EXEC fail.
+    aborted: bool,
+    watched: Vec<Watch>,
+}
+
+impl Transaction {
+    /// Whether MULTI was called and neither EXEC nor DISCARD since.
+    pub fn is_open(&self) -> bool {
+        self.queued.is_some()
+    }
+
+    /// Whether `command` must be queued rather than run.
+    pub fn queues(&self, command: &str) -> bool {
+        self.is_open() && !TRANSACTION_COMMANDS.contains(&command)
+    }
+
+    pub fn queue(&mut self, request: Request) {
+        self.queued.get_or_insert_default().push(request);
+    }
+
+    pub fn abort(&mut self) {
+        self.aborted = true;
+    }
+
+    /// Ends the transaction and returns the queued commands, or `None` if one of them was
+    /// rejected.
+    pub fn take(&mut self) -> Option<Vec<Request>> {
+        let queued = self.queued.take().unwrap_or_default();
+        if std::mem::take(&mut self.aborted) {
+            None
+        } else {
+            Some(queued)
+        }
+    }
+
+    /// Whether a watched key was modified since it was watched.
+    pub fn is_dirty(&self, storage: &mut RedisStorage) -> bool {
+        self.watched
+            .iter()
+            .any(|watch| storage.database(watch.db).version(&watch.key) != watch.version)
+    }
+
+    pub fn unwatch(&mut self, storage: &mut RedisStorage) {
+        for watch in self.watched.drain(..) {
+            storage.database(watch.db).unwatch(&watch.key);
+        }
+    }
+}
+
+pub fn multi(writer: &mut impl WriteResp, transaction: &mut Transaction) -> std::io::Result<()> {
+    if transaction.is_open() {
+        return writer.write_error("MULTI calls can not be nested");
+    }
+    transaction.queued = Some(Vec::new());
+    writer.write_simple_string(OK)
+}
+
+pub fn discard(
+    writer: &mut impl WriteResp,
+    transaction: &mut Transaction,
+    storage: &mut RedisStorage,
+) -> std::io::Result<()> {
+    if !transaction.is_open() {
+        return writer.write_error("DISCARD without MULTI");
+    }
+    transaction.take();
+    transaction.unwatch(storage);
+    writer.write_simple_string(OK)
+}
+
+pub fn watch(
+    writer: &mut impl WriteResp,
+    transaction: &mut Transaction,
+    storage: &mut RedisStorage,
+    db: usize,
+    request: &Request,
+) -> std::io::Result<()> {
+    if request.len() < 2 {
+        return writer.write_error(wrong_arguments("watch"));
+    }
+    if transaction.is_open() {
+        return writer.write_error("WATCH inside MULTI is not allowed");
+    }
+
+    for index in 1..request.len() {
+        let key = request.get(index).unwrap();
+        if transaction
+            .watched
+            .iter()
+            .any(|watch| watch.db == db && watch.key == key)
+        {
+            continue;
+        }
+        let version = storage.database(db).watch(key);
+        transaction.watched.push(Watch {
+            db,
+            key: key.to_vec(),
+            version,
+        });
+    }
+    writer.write_simple_string(OK)
+}
+
+pub fn unwatch(
+    writer: &mut impl WriteResp,
+    transaction: &mut Transaction,
+    storage: &mut RedisStorage,
+) -> std::io::Result<()> {
+    transaction.unwatch(storage);
+    writer.write_simple_string(OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::list::{linsert, lrem, rpush};
+    use crate::redis::core::request::Request;
+    use crate::redis::core::set::srem;
+    use crate::redis::core::sorted_set::zadd;
+    use crate::redis::core::transaction::{discard, multi, watch, Transaction};
+    use crate::redis::rdb::RedisStorage;
+
+    #[test]
+    fn test_no_op_writes_do_not_abort_exec() {
+        let mut storage = RedisStorage::new(16);
+        let mut transaction = Transaction::default();
+        let mut reply = Vec::new();
+        rpush(
+            &mut reply,
+            storage.database(0),
+            &Request::from_strs(&["RPUSH", "list", "a"]),
+        )
+        .unwrap();
+        let watched = Request::from_strs(&["WATCH", "list", "set", "zset"]);
+        watch(&mut reply, &mut transaction, &mut storage, 0, &watched).unwrap();
+        let dirty = storage.dirty();
+
+        let db = storage.database(0);
+        lrem(
+            &mut reply,
+            db,
+            &Request::from_strs(&["LREM", "list", "0", "b"]),
+        )
+        .unwrap();
+        linsert(
+            &mut reply,
+            db,
+            &Request::from_strs(&["LINSERT", "list", "BEFORE", "b", "c"]),
+        )
+        .unwrap();
+        srem(&mut reply, db, &Request::from_strs(&["SREM", "set", "a"])).unwrap();
+        zadd(
+            &mut reply,
+            db,
+            &Request::from_strs(&["ZADD", "zset", "XX", "1", "a"]),
+        )
+        .unwrap();
+        assert!(!transaction.is_dirty(&mut storage));
+        assert_eq!(storage.dirty(), dirty);
+
+        let db = storage.database(0);
+        lrem(
+            &mut reply,
+            db,
+            &Request::from_strs(&["LREM", "list", "0", "a"]),
+        )
+        .unwrap();
+        assert!(transaction.is_dirty(&mut storage));
+        assert_ne!(storage.dirty(), dirty);
+    }
+
+    #[test]
+    fn test_errors_while_queueing_discard_the_transaction() {
+        let mut storage = RedisStorage::new(16);
+        let mut transaction = Transaction::default();
+        let mut reply = Vec::new();
+        discard(&mut reply, &mut transaction, &mut storage).unwrap();
+        multi(&mut reply, &mut transaction).unwrap();
+        multi(&mut reply, &mut transaction).unwrap();
+        let watched = Request::from_strs(&["WATCH", "a"]);
+        watch(&mut reply, &mut transaction, &mut storage, 0, &watched).unwrap();
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            "-DISCARD without MULTI\r\n+OK\r\n-MULTI calls can not be nested\r\n\
+             -WATCH inside MULTI is not allowed\r\n"
+        );
+
+        assert!(transaction.queues("set"));
+        assert!(!transaction.queues("exec"));
+        transaction.queue(Request::from_strs(&["SET", "a", "1"]));
+        transaction.abort();
+        assert!(transaction.take().is_none());
+        assert!(!transaction.is_open());
+
+        // The next transaction starts afresh.
+        multi(&mut Vec::new(), &mut transaction).unwrap();
+        transaction.queue(Request::from_strs(&["SET", "a", "1"]));
+        assert_eq!(transaction.take().map(|queued| queued.len()), Some(1));
+    }
+}
@@ -1,6 +1,13 @@
 pub trait WriteResp {
     fn write_simple_string(&mut self, message: impl AsRef<str>) -> std::io::Result<()>;
     fn write_error(&mut self, message: impl AsRef<str>) -> std::io::Result<()>;
-    fn write_bulk_sting(&mut self, message: &Option<impl AsRef<str>>) -> std::io::Result<()>;
-    fn write_array(&mut self, message: &[Option<impl AsRef<str>>]) -> std::io::Result<()>;
+    fn write_integer(&mut self, value: i64) -> std::io::Result<()>;
+    fn write_bulk_sting(&mut self, message: &Option<impl AsRef<[u8]>>) -> std::io::Result<()>;
+    fn write_array(&mut self, message: &[Option<impl AsRef<[u8]>>]) -> std::io::Result<()>;
+    /// Starts an array of `len` elements which the caller writes one by one.
+    fn write_array_header(&mut self, len: usize) -> std::io::Result<()>;
+    fn write_null_array(&mut self) -> std::io::Result<()>;
+    /// Writes an RDB file the way a master sends it after FULLRESYNC: a bulk string without
+    /// the trailing CRLF.
+    fn write_rdb(&mut self, rdb: &[u8]) -> std::io::Result<()>;
 }
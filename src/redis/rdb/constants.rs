@@ -4,3 +4,24 @@ pub const EXPIRE_TIME: u8 = 0xfd;
 pub const EXPIRE_TIME_MS: u8 = 0xfc;
 pub const RESIZE_DB: u8 = 0xfb;
 pub const AUX: u8 = 0xfa;
+
+pub const STRING_TYPE: u8 = 0;
+pub const LIST_TYPE: u8 = 1;
+pub const SET_TYPE: u8 = 2;
+pub const SORTED_SET_TYPE: u8 = 3;
+pub const HASH_TYPE: u8 = 4;
+pub const SORTED_SET_2_TYPE: u8 = 5;
+pub const SET_INTSET_TYPE: u8 = 11;
+pub const STREAM_LISTPACKS_TYPE: u8 = 15;
+pub const HASH_LISTPACK_TYPE: u8 = 16;
+pub const SORTED_SET_LISTPACK_TYPE: u8 = 17;
+pub const LIST_QUICKLIST_2_TYPE: u8 = 18;
+pub const STREAM_LISTPACKS_2_TYPE: u8 = 19;
+pub const SET_LISTPACK_TYPE: u8 = 20;
+pub const STREAM_LISTPACKS_3_TYPE: u8 = 21;
+
+pub const QUICKLIST_NODE_PLAIN: u32 = 1;
+pub const QUICKLIST_NODE_PACKED: u32 = 2;
+
+pub const STREAM_ITEM_FLAG_DELETED: i64 = 1;
+pub const STREAM_ITEM_FLAG_SAME_FIELDS: i64 = 2;
@@ -0,0 +1,139 @@
+use crate::redis::rdb::stream::StreamId;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// An entry delivered to a consumer and not acknowledged yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingEntry {
+    pub consumer: Vec<u8>,
+    pub delivery_time: u64,
+    pub delivery_count: u64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Consumer {
+    /// The last time the consumer interacted with the group, in Unix milliseconds.
+    pub seen_time: u64,
+    /// The last time the consumer read or claimed an entry, if it ever did.
+    pub active_time: Option<u64>,
+    pub pending: BTreeSet<StreamId>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConsumerGroup {
+    pub last_id: StreamId,
+    /// How many entries the group has read, `None` when it cannot be known because of
+    /// deletions or an arbitrary last delivered ID.
+    pub entries_read: Option<u64>,
+    pub pending: BTreeMap<StreamId, PendingEntry>,
+    pub consumers: BTreeMap<Vec<u8>, Consumer>,
+}
+
+impl ConsumerGroup {
+    pub fn new(last_id: StreamId, entries_read: Option<u64>) -> Self {
+        Self {
+            last_id,
+            entries_read,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the consumer, creating it if needed, and records that it was seen at `now`.
+    pub fn touch_consumer(&mut self, name: &[u8], now: u64) -> &mut Consumer {
+        let consumer = self.consumers.entry(name.to_vec()).or_default();
+        consumer.seen_time = now;
+        consumer
+    }
+
+    pub fn create_consumer(&mut self, name: &[u8], now: u64) -> bool {
+        if self.consumers.contains_key(name) {
+            return false;
+        }
+        self.touch_consumer(name, now);
+        true
+    }
+
+    /// Deletes a consumer along with its pending entries and returns how many it had.
+    pub fn delete_consumer(&mut self, name: &[u8]) -> Option<usize> {
+        let consumer = self.consumers.remove(name)?;
+        for id in &consumer.pending {
+            self.pending.remove(id);
+        }
+        Some(consumer.pending.len())
+    }
+
+    /// Records the delivery of a new entry to `consumer`.
+    pub fn deliver(&mut self, id: StreamId, consumer: &[u8], now: u64) {
+        self.remove_pending(&id);
+        self.pending.insert(
+            id,
+            PendingEntry {
+                consumer: consumer.to_vec(),
+                delivery_time: now,
+                delivery_count: 1,
+            },
+        );
+        self.consumers
+            .entry(consumer.to_vec())
+            .or_default()
+            .pending
+            .insert(id);
+    }
+
+    /// Transfers a pending entry to `consumer`, creating the entry if needed, and returns it.
+    pub fn claim(&mut self, id: StreamId, consumer: &[u8]) -> &mut PendingEntry {
+        let owner = self.pending.get(&id).map(|entry| entry.consumer.clone());
+        if owner.as_deref() != Some(consumer) {
+            if let Some(owner) = owner.and_then(|owner| self.consumers.get_mut(&owner)) {
+                owner.pending.remove(&id);
+            }
+            self.consumers
+                .entry(consumer.to_vec())
+                .or_default()
+                .pending
+                .insert(id);
+        }
+
+        let entry = self.pending.entry(id).or_insert(PendingEntry {
+            consumer: consumer.to_vec(),
+            delivery_time: 0,
+            delivery_count: 0,
+        });
+        entry.consumer = consumer.to_vec();
+        entry
+    }
+
+    /// Removes an entry from the pending entries list, which acknowledges it.
+    pub fn remove_pending(&mut self, id: &StreamId) -> bool {
+        let Some(entry) = self.pending.remove(id) else {
+            return false;
+        };
+        if let Some(consumer) = self.consumers.get_mut(&entry.consumer) {
+            consumer.pending.remove(id);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::rdb::consumer_group::ConsumerGroup;
+    use crate::redis::rdb::stream::StreamId;
+
+    #[test]
+    fn test_pending_entries_follow_their_owner() {
+        let mut group = ConsumerGroup::default();
+        let id = StreamId::new(1, 1);
+        group.deliver(id, b"alice", 10);
+        assert!(group.consumers[b"alice".as_slice()].pending.contains(&id));
+
+        let entry = group.claim(id, b"bob");
+        entry.delivery_count += 1;
+        assert_eq!(group.pending[&id].consumer, b"bob");
+        assert_eq!(group.pending[&id].delivery_count, 2);
+        assert!(group.consumers[b"alice".as_slice()].pending.is_empty());
+
+        assert_eq!(group.delete_consumer(b"bob"), Some(1));
+        assert!(group.pending.is_empty());
+        assert!(!group.remove_pending(&id));
+    }
+}
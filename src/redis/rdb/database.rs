@@ -0,0 +1,520 @@
+use crate::redis::rdb::dict::{Dict, Set};
+use crate::redis::rdb::expires::ExpireIndex;
+use crate::redis::rdb::sorted_set::SortedSet;
+use crate::redis::rdb::stream::Stream;
+use crate::redis::rdb::ttl::Ttl;
+use crate::redis::rdb::value::{Hash, List, Value};
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+/// How many keys with an expiration the active expiry cycle checks per round.
+const ACTIVE_EXPIRE_KEYS_PER_ROUND: usize = 20;
+/// The percentage of expired keys in a round above which the cycle goes for another round.
+const ACTIVE_EXPIRE_ACCEPTABLE_STALE: usize = 10;
+
+pub(super) type Entries = HashMap<Vec<u8>, (Value, Ttl)>;
+
+/// One of the keyspaces a client selects by index.
+#[derive(Default)]
+pub struct Database {
+    storage: Dict<(Value, Ttl)>,
+    expires: ExpireIndex,
+    /// Keys that were created or added to since the last call to `take_ready_keys`, which
+    /// clients blocked on them may now be able to consume.
+    ready_keys: Vec<Vec<u8>>,
+    /// The keys clients WATCH. They belong to the database index rather than its content, so
+    /// SWAPDB leaves them in place.
+    pub(super) watched: HashMap<Vec<u8>, WatchedKey>,
+    /// How many modifications were made, which tells whether a write changed anything.
+    pub(super) dirty: u64,
+}
+
+/// How many times a watched key was modified since the first client watched it, and by how
+/// many clients it is watched.
+#[derive(Default)]
+pub(super) struct WatchedKey {
+    version: u64,
+    watchers: usize,
+}
+
+impl Database {
+    /// Replaces every key with `entries`, as read from an RDB file.
+    pub(super) fn load(&mut self, entries: Entries) {
+        self.clear();
+        for (key, (value, ttl)) in entries {
+            self.insert_entry(key, value, ttl);
+        }
+    }
+
+    /// The entries that did not expire, for saving them.
+    pub(super) fn entries(&mut self) -> Vec<(&[u8], (&Value, &Ttl))> {
+        self.remove_expired_keys();
+        self.storage
+            .iter()
+            .map(|(key, (value, ttl))| (key.as_slice(), (value, ttl)))
+            .collect()
+    }
+
+    pub fn get(&mut self, key: &[u8]) -> Result<Option<&[u8]>, WrongTypeError> {
+        match self.get_value(key) {
+            None => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value.as_slice())),
+            Some(_) => Err(WrongTypeError),
+        }
+    }
+
+    /// Stores a string, replacing any previous value, that expires at `expire_at` Unix
+    /// milliseconds if given.
+    pub fn set(&mut self, key: Vec<u8>, value: Vec<u8>, expire_at: Option<u64>) {
+        let ttl = expire_at.map_or(Ttl::None, Ttl::Milliseconds);
+        self.insert_entry(key, Value::String(value), ttl);
+    }
+
+    /// Stores a string, replacing any previous value but keeping its expiration.
+    pub fn set_keep_ttl(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        match self.modify_value(&key) {
+            Some(previous) => {
+                *previous = Value::String(value);
+                self.touch(&key);
+            }
+            None => self.set(key, value, None),
+        }
+    }
+
+    /// The expiration of a key in Unix milliseconds, `Some(None)` if it never expires and `None`
+    /// if it does not exist.
+    pub fn get_expire_time(&mut self, key: &[u8]) -> Option<Option<u64>> {
+        self.get_value(key)?;
+        self.storage.get(key).map(|(_, ttl)| ttl.expire_time())
+    }
+
+    /// Sets or clears the expiration of a key, returning false if it does not exist.
+    pub fn set_expire_time(&mut self, key: &[u8], expire_at: Option<u64>) -> bool {
+        if self.get_value(key).is_none() {
+            return false;
+        }
+        self.touch(key);
+        match expire_at {
+            Some(_) => self.expires.insert(key),
+            None => self.expires.remove(key),
+        }
+        self.storage.get_mut(key).unwrap().1 = expire_at.map_or(Ttl::None, Ttl::Milliseconds);
+        true
+    }
+
+    pub fn contains_key(&mut self, key: &[u8]) -> bool {
+        self.get_value(key).is_some()
+    }
+
+    pub fn get_or_insert_string(&mut self, key: &[u8]) -> Result<&mut Vec<u8>, WrongTypeError> {
+        match self.get_or_insert_with(key, || Value::String(Vec::new())) {
+            Value::String(value) => Ok(value),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    pub fn get_list(&mut self, key: &[u8]) -> Result<Option<&List>, WrongTypeError> {
+        match self.get_value(key) {
+            None => Ok(None),
+            Some(Value::List(list)) => Ok(Some(list)),
+            Some(_) => Err(WrongTypeError),
+        }
+    }
+
+    pub fn get_list_mut(&mut self, key: &[u8]) -> Result<Option<&mut List>, WrongTypeError> {
+        match self.modify_value(key) {
+            None => Ok(None),
+            Some(Value::List(list)) => Ok(Some(list)),
+            Some(_) => Err(WrongTypeError),
+        }
+    }
+
+    pub fn get_or_insert_list(&mut self, key: &[u8]) -> Result<&mut List, WrongTypeError> {
+        match self.get_or_insert_with(key, || Value::List(VecDeque::new())) {
+            Value::List(list) => Ok(list),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    pub fn get_hash(&mut self, key: &[u8]) -> Result<Option<&Hash>, WrongTypeError> {
+        match self.get_value(key) {
+            None => Ok(None),
+            Some(Value::Hash(hash)) => Ok(Some(hash)),
+            Some(_) => Err(WrongTypeError),
+        }
+    }
+
+    pub fn get_hash_mut(&mut self, key: &[u8]) -> Result<Option<&mut Hash>, WrongTypeError> {
+        match self.modify_value(key) {
+            None => Ok(None),
+            Some(Value::Hash(hash)) => Ok(Some(hash)),
+            Some(_) => Err(WrongTypeError),
+        }
+    }
+
+    pub fn get_or_insert_hash(&mut self, key: &[u8]) -> Result<&mut Hash, WrongTypeError> {
+        match self.get_or_insert_with(key, || Value::Hash(Hash::new())) {
+            Value::Hash(hash) => Ok(hash),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    pub fn get_set(&mut self, key: &[u8]) -> Result<Option<&Set>, WrongTypeError> {
+        match self.get_value(key) {
+            None => Ok(None),
+            Some(Value::Set(set)) => Ok(Some(set)),
+            Some(_) => Err(WrongTypeError),
+        }
+    }
+
+    pub fn get_set_mut(&mut self, key: &[u8]) -> Result<Option<&mut Set>, WrongTypeError> {
+        match self.modify_value(key) {
+            None => Ok(None),
+            Some(Value::Set(set)) => Ok(Some(set)),
+            Some(_) => Err(WrongTypeError),
+        }
+    }
+
+    pub fn get_or_insert_set(&mut self, key: &[u8]) -> Result<&mut Set, WrongTypeError> {
+        match self.get_or_insert_with(key, || Value::Set(Set::new())) {
+            Value::Set(set) => Ok(set),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    /// Looks up several sets at once, failing if any of the keys holds another type.
+    pub fn get_sets(&mut self, keys: &[&[u8]]) -> Result<Vec<Option<&Set>>, WrongTypeError> {
+        self.get_values(keys)
+            .into_iter()
+            .map(|value| match value {
+                None => Ok(None),
+                Some(Value::Set(set)) => Ok(Some(set)),
+                Some(_) => Err(WrongTypeError),
+            })
+            .collect()
+    }
+
+    pub fn get_sorted_set(&mut self, key: &[u8]) -> Result<Option<&SortedSet>, WrongTypeError> {
+        match self.get_value(key) {
+            None => Ok(None),
+            Some(Value::SortedSet(sorted_set)) => Ok(Some(sorted_set)),
+            Some(_) => Err(WrongTypeError),
+        }
+    }
+
+    pub fn get_sorted_set_mut(
+        &mut self,
+        key: &[u8],
+    ) -> Result<Option<&mut SortedSet>, WrongTypeError> {
+        match self.modify_value(key) {
+            None => Ok(None),
+            Some(Value::SortedSet(sorted_set)) => Ok(Some(sorted_set)),
+            Some(_) => Err(WrongTypeError),
+        }
+    }
+
+    pub fn get_or_insert_sorted_set(
+        &mut self,
+        key: &[u8],
+    ) -> Result<&mut SortedSet, WrongTypeError> {
+        match self.get_or_insert_with(key, || Value::SortedSet(SortedSet::default())) {
+            Value::SortedSet(sorted_set) => Ok(sorted_set),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    pub fn get_stream(&mut self, key: &[u8]) -> Result<Option<&Stream>, WrongTypeError> {
+        match self.get_value(key) {
+            None => Ok(None),
+            Some(Value::Stream(stream)) => Ok(Some(stream)),
+            Some(_) => Err(WrongTypeError),
+        }
+    }
+
+    pub fn get_stream_mut(&mut self, key: &[u8]) -> Result<Option<&mut Stream>, WrongTypeError> {
+        match self.modify_value(key) {
+            None => Ok(None),
+            Some(Value::Stream(stream)) => Ok(Some(stream)),
+            Some(_) => Err(WrongTypeError),
+        }
+    }
+
+    pub fn get_or_insert_stream(&mut self, key: &[u8]) -> Result<&mut Stream, WrongTypeError> {
+        match self.get_or_insert_with(key, || Value::Stream(Stream::default())) {
+            Value::Stream(stream) => Ok(stream),
+            _ => Err(WrongTypeError),
+        }
+    }
+
+    /// Looks up the values of several keys at once, whatever their type.
+    pub fn get_values(&mut self, keys: &[&[u8]]) -> Vec<Option<&Value>> {
+        for key in keys {
+            self.get_value_mut(key);
+        }
+        keys.iter()
+            .map(|key| self.storage.get(key).map(|(value, _)| value))
+            .collect()
+    }
+
+    /// Stores `value` under `key`, replacing any previous value and its expiration.
+    pub fn insert(&mut self, key: Vec<u8>, value: Value) {
+        self.signal_ready(&key);
+        self.insert_entry(key, value, Ttl::None);
+    }
+
+    pub fn remove(&mut self, key: &[u8]) -> bool {
+        self.get_value_mut(key).is_some() && self.remove_entry(key).is_some()
+    }
+
+    /// Moves the value of `source` and its expiration to `destination`, replacing any value
+    /// there. Returns false if `source` does not exist.
+    pub fn rename(&mut self, source: &[u8], destination: &[u8]) -> bool {
+        let Some((value, ttl)) = self.take_entry(source) else {
+            return false;
+        };
+        self.put_entry(destination.to_vec(), value, ttl);
+        true
+    }
+
+    /// Removes a key that did not expire along with its expiration.
+    pub(super) fn take_entry(&mut self, key: &[u8]) -> Option<(Value, Ttl)> {
+        self.get_value(key)?;
+        self.remove_entry(key)
+    }
+
+    /// Clones the value of a key that did not expire along with its expiration.
+    pub(super) fn get_entry(&mut self, key: &[u8]) -> Option<(Value, Ttl)> {
+        self.get_value(key)?;
+        self.storage.get(key).cloned()
+    }
+
+    /// Stores a value with its expiration, replacing any previous value.
+    pub(super) fn put_entry(&mut self, key: Vec<u8>, value: Value, ttl: Ttl) {
+        self.signal_ready(&key);
+        self.insert_entry(key, value, ttl);
+    }
+
+    /// Deletes every key.
+    pub fn clear(&mut self) {
+        if !self.storage.is_empty() {
+            self.dirty += 1;
+        }
+        let keys: Vec<_> = self.watched.keys().cloned().collect();
+        for key in keys {
+            if self.storage.contains_key(&key) {
+                self.touch(&key);
+            }
+        }
+        self.storage.clear();
+        self.expires = ExpireIndex::default();
+    }
+
+    /// The number of keys, including expired ones that were not deleted yet.
+    pub fn len(&self) -> usize {
+        self.storage.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.storage.is_empty()
+    }
+
+    /// Returns a random key, expired or not, `random_index` drawing an index below its argument.
+    pub fn random_key(&self, random_index: impl FnMut(usize) -> usize) -> Option<&[u8]> {
+        self.storage
+            .random_entry(random_index)
+            .map(|(key, _)| key.as_slice())
+    }
+
+    /// Deletes `key` if it holds a collection that has no elements left.
+    pub fn remove_if_empty(&mut self, key: &[u8]) {
+        if self
+            .storage
+            .get(key)
+            .is_some_and(|(value, _)| value.is_empty())
+        {
+            self.remove_entry(key);
+        }
+    }
+
+    pub fn get_keys(&mut self) -> Vec<&[u8]> {
+        self.remove_expired_keys();
+        self.storage.keys().map(|x| x.as_slice()).collect()
+    }
+
+    /// Returns about `count` keys from `cursor` on along with the cursor to continue from, 0 once
+    /// every key was returned. Expired keys met on the way are deleted instead.
+    pub fn scan(&mut self, cursor: u64, count: usize) -> (u64, Vec<Vec<u8>>) {
+        let (cursor, keys) = self.storage.scan(cursor, count);
+        let mut keys: Vec<_> = keys.into_iter().map(|(key, _)| key.clone()).collect();
+        keys.retain(|key| self.get_value(key).is_some());
+        (cursor, keys)
+    }
+
+    pub fn take_ready_keys(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.ready_keys)
+    }
+
+    /// Deletes expired keys, checking a few keys with an expiration per round and going for
+    /// another round while many of them had expired, until `deadline`. Returns how many keys
+    /// were deleted.
+    pub fn active_expire_cycle(&mut self, deadline: Instant) -> usize {
+        let mut deleted = 0;
+        loop {
+            let keys = self.expires.next_keys(ACTIVE_EXPIRE_KEYS_PER_ROUND);
+            let mut expired = 0;
+            for key in &keys {
+                if self
+                    .storage
+                    .get(key)
+                    .is_some_and(|(_, ttl)| ttl.is_expired())
+                {
+                    self.remove_entry(key);
+                    expired += 1;
+                }
+            }
+            deleted += expired;
+            if expired * 100 <= keys.len() * ACTIVE_EXPIRE_ACCEPTABLE_STALE
+                || Instant::now() >= deadline
+            {
+                return deleted;
+            }
+        }
+    }
+
+    fn get_value(&mut self, key: &[u8]) -> Option<&Value> {
+        self.get_value_mut(key).map(|value| &*value)
+    }
+
+    fn get_value_mut(&mut self, key: &[u8]) -> Option<&mut Value> {
+        let should_remove = self.storage.get(key)?.1.is_expired();
+
+        if should_remove {
+            self.remove_entry(key);
+            None
+        } else {
+            self.storage.get_mut(key).map(|(v, _)| v)
+        }
+    }
+
+    /// Starts watching `key` for modifications and returns its current version.
+    pub fn watch(&mut self, key: &[u8]) -> u64 {
+        // A key that expired before being watched must not count as modified once deleted.
+        self.get_value(key);
+        let watched = self.watched.entry(key.to_vec()).or_default();
+        watched.watchers += 1;
+        watched.version
+    }
+
+    pub fn unwatch(&mut self, key: &[u8]) {
+        if let Some(watched) = self.watched.get_mut(key) {
+            watched.watchers -= 1;
+            if watched.watchers == 0 {
+                self.watched.remove(key);
+            }
+        }
+    }
+
+    /// The version of a watched key, which changes whenever it is modified, expiring included.
+    pub fn version(&mut self, key: &[u8]) -> u64 {
+        self.get_value(key);
+        self.watched.get(key).map_or(0, |watched| watched.version)
+    }
+
+    /// Records a modification of every watched key.
+    pub(super) fn touch_all(&mut self) {
+        self.dirty += 1;
+        for watched in self.watched.values_mut() {
+            watched.version += 1;
+        }
+    }
+
+    /// Records a modification of `key`, bumping its version if a client watches it. Commands
+    /// that change a value through a mutable borrow call this once they actually changed it,
+    /// so that no-op writes neither abort transactions nor reach replicas.
+    pub fn touch(&mut self, key: &[u8]) {
+        self.dirty += 1;
+        if let Some(watched) = self.watched.get_mut(key) {
+            watched.version += 1;
+        }
+    }
+
+    /// Gives write access to the value of a key; the caller touches it if it changes it.
+    fn modify_value(&mut self, key: &[u8]) -> Option<&mut Value> {
+        self.get_value(key)?;
+        self.storage.get_mut(key).map(|(v, _)| v)
+    }
+
+    fn get_or_insert_with(&mut self, key: &[u8], default: impl FnOnce() -> Value) -> &mut Value {
+        self.signal_ready(key);
+        if self.modify_value(key).is_none() {
+            self.insert_entry(key.to_vec(), default(), Ttl::None);
+        }
+        self.storage.get_mut(key).map(|(v, _)| v).unwrap()
+    }
+
+    /// Records that clients blocked on `key` may now be served.
+    pub fn signal_ready(&mut self, key: &[u8]) {
+        if !self.ready_keys.iter().any(|ready| ready == key) {
+            self.ready_keys.push(key.to_vec());
+        }
+    }
+
+    fn insert_entry(&mut self, key: Vec<u8>, value: Value, ttl: Ttl) {
+        self.touch(&key);
+        if ttl == Ttl::None {
+            self.expires.remove(&key);
+        } else {
+            self.expires.insert(&key);
+        }
+        self.storage.insert(key, (value, ttl));
+    }
+
+    fn remove_entry(&mut self, key: &[u8]) -> Option<(Value, Ttl)> {
+        self.touch(key);
+        self.expires.remove(key);
+        self.storage.remove(key)
+    }
+
+    fn remove_expired_keys(&mut self) {
+        let to_delete: Vec<Vec<u8>> = self
+            .expires
+            .keys()
+            .iter()
+            .filter(|key| {
+                self.storage
+                    .get(key)
+                    .is_some_and(|(_, ttl)| ttl.is_expired())
+            })
+            .cloned()
+            .collect();
+
+        for key in to_delete {
+            self.remove_entry(&key);
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+pub struct WrongTypeError;
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::rdb::database::Database;
+
+    #[test]
+    fn test_clear_forgets_expirations() {
+        let mut database = Database::default();
+        database.set(b"a".to_vec(), b"0".to_vec(), Some(u64::MAX / 2));
+        database.set(b"b".to_vec(), b"1".to_vec(), None);
+        database.set_expire_time(b"b", Some(u64::MAX / 2));
+        assert_eq!(database.expires.keys().len(), 2);
+
+        database.clear();
+        assert!(database.expires.keys().is_empty());
+        assert!(database.get_keys().is_empty());
+        database.set(b"a".to_vec(), b"0".to_vec(), None);
+        assert!(database.expires.keys().is_empty());
+        assert_eq!(database.get_expire_time(b"a"), Some(None));
+    }
+}
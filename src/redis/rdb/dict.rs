@@ -0,0 +1,463 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::mem;
+
+/// The fewest buckets a non-empty dict has.
+const MIN_BUCKETS: usize = 4;
+/// How many empty buckets a SCAN call visits per element it was asked for before returning.
+const EMPTY_VISITS_PER_ELEMENT: usize = 10;
+
+/// A hash table keyed by byte strings that, unlike `HashMap`, exposes its buckets so that SCAN
+/// can walk them with Redis' reverse binary cursor. The number of buckets is a power of two
+/// that doubles when the table fills up and halves when it becomes sparse.
+#[derive(Clone)]
+pub struct Dict<V> {
+    buckets: Vec<Vec<(Vec<u8>, V)>>,
+    len: usize,
+    hasher: RandomState,
+}
+
+impl<V> Default for Dict<V> {
+    fn default() -> Self {
+        Self {
+            buckets: Vec::new(),
+            len: 0,
+            hasher: RandomState::new(),
+        }
+    }
+}
+
+impl<V> Dict<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut dict = Self::default();
+        if capacity > 0 {
+            dict.resize(capacity.max(MIN_BUCKETS).next_power_of_two());
+        }
+        dict
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&V> {
+        self.bucket(key)?
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut V> {
+        let index = self.bucket_index(key)?;
+        self.buckets[index]
+            .iter_mut()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Sets the value of `key`, returning its previous value if it was already present.
+    pub fn insert(&mut self, key: Vec<u8>, value: V) -> Option<V> {
+        if let Some(previous) = self.get_mut(&key) {
+            return Some(mem::replace(previous, value));
+        }
+        if self.len >= self.buckets.len() {
+            self.resize((self.buckets.len() * 2).max(MIN_BUCKETS));
+        }
+        let index = self.bucket_index(&key).unwrap();
+        self.buckets[index].push((key, value));
+        self.len += 1;
+        None
+    }
+
+    pub fn remove(&mut self, key: &[u8]) -> Option<V> {
+        let index = self.bucket_index(key)?;
+        let bucket = &mut self.buckets[index];
+        let position = bucket.iter().position(|(k, _)| k == key)?;
+        let (_, value) = bucket.swap_remove(position);
+        self.len -= 1;
+        if self.len == 0 {
+            self.buckets = Vec::new();
+        } else if self.buckets.len() > MIN_BUCKETS && self.len * 8 < self.buckets.len() {
+            self.resize(self.buckets.len() / 2);
+        }
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets = Vec::new();
+        self.len = 0;
+    }
+
+    pub fn iter(&self) -> Iter<'_, V> {
+        Iter {
+            buckets: self.buckets.iter(),
+            bucket: [].iter(),
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// Returns the elements of the buckets from `cursor` on, until about `count` elements were
+    /// found, along with the cursor to continue from, 0 once every bucket was visited.
+    ///
+    /// The cursor increments its reversed bits, so it visits the buckets of a small table before
+    /// the buckets they split into in a larger one. An element present for the whole iteration
+    /// is thus returned even if the table is resized in between, possibly more than once if it
+    /// shrinks.
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(&Vec<u8>, &V)>) {
+        let mut page = Vec::new();
+        if self.buckets.is_empty() {
+            return (0, page);
+        }
+
+        let mask = (self.buckets.len() - 1) as u64;
+        let mut cursor = cursor;
+        let mut empty_visits = count.saturating_mul(EMPTY_VISITS_PER_ELEMENT);
+        loop {
+            let bucket = &self.buckets[(cursor & mask) as usize];
+            if bucket.is_empty() {
+                empty_visits = empty_visits.saturating_sub(1);
+            }
+            page.extend(bucket.iter().map(|(key, value)| (key, value)));
+
+            cursor |= !mask;
+            cursor = cursor.reverse_bits().wrapping_add(1).reverse_bits();
+            if cursor == 0 || page.len() >= count || empty_visits == 0 {
+                return (cursor, page);
+            }
+        }
+    }
+
+    /// Returns a random element, picking a random non-empty bucket and then a random element
+    /// in it, with `random_index` drawing an index below its argument. The table is at least an
+    /// eighth full, so a non-empty bucket is found after a few draws on average.
+    pub fn random_entry(
+        &self,
+        mut random_index: impl FnMut(usize) -> usize,
+    ) -> Option<(&Vec<u8>, &V)> {
+        if self.is_empty() {
+            return None;
+        }
+        loop {
+            let bucket = &self.buckets[random_index(self.buckets.len())];
+            if !bucket.is_empty() {
+                let (key, value) = &bucket[random_index(bucket.len())];
+                return Some((key, value));
+            }
+        }
+    }
+
+    /// Returns `count` distinct random elements, or every element if there are not that many.
+    /// Like Redis, a count close to the length shuffles a copy of the elements, while a small
+    /// one draws random elements until enough distinct ones were found.
+    pub fn random_entries(
+        &self,
+        count: usize,
+        mut random_index: impl FnMut(usize) -> usize,
+    ) -> Vec<(&Vec<u8>, &V)> {
+        if count >= self.len {
+            return self.iter().collect();
+        }
+        if count.saturating_mul(3) > self.len {
+            let mut entries: Vec<_> = self.iter().collect();
+            for i in 0..count {
+                let j = i + random_index(entries.len() - i);
+                entries.swap(i, j);
+            }
+            entries.truncate(count);
+            return entries;
+        }
+        let mut picked = HashSet::new();
+        let mut entries = Vec::with_capacity(count);
+        while entries.len() < count {
+            let (key, value) = self.random_entry(&mut random_index).unwrap();
+            if picked.insert(key) {
+                entries.push((key, value));
+            }
+        }
+        entries
+    }
+
+    fn bucket(&self, key: &[u8]) -> Option<&Vec<(Vec<u8>, V)>> {
+        self.bucket_index(key).map(|index| &self.buckets[index])
+    }
+
+    fn bucket_index(&self, key: &[u8]) -> Option<usize> {
+        if self.buckets.is_empty() {
+            return None;
+        }
+        Some(self.hasher.hash_one(key) as usize & (self.buckets.len() - 1))
+    }
+
+    /// Moves every element to a table of `size` buckets at once.
+    fn resize(&mut self, size: usize) {
+        let old = mem::replace(&mut self.buckets, (0..size).map(|_| Vec::new()).collect());
+        for (key, value) in old.into_iter().flatten() {
+            let index = self.bucket_index(&key).unwrap();
+            self.buckets[index].push((key, value));
+        }
+    }
+}
+
+impl<V: fmt::Debug> fmt::Debug for Dict<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<V: PartialEq> PartialEq for Dict<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl<V> FromIterator<(Vec<u8>, V)> for Dict<V> {
+    fn from_iter<T: IntoIterator<Item = (Vec<u8>, V)>>(iter: T) -> Self {
+        let mut dict = Dict::new();
+        for (key, value) in iter {
+            dict.insert(key, value);
+        }
+        dict
+    }
+}
+
+impl<'a, V> IntoIterator for &'a Dict<V> {
+    type Item = (&'a Vec<u8>, &'a V);
+    type IntoIter = Iter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<V> IntoIterator for Dict<V> {
+    type Item = (Vec<u8>, V);
+    type IntoIter = std::iter::Flatten<std::vec::IntoIter<Vec<(Vec<u8>, V)>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.buckets.into_iter().flatten()
+    }
+}
+
+/// Iterates the elements of a dict bucket by bucket.
+pub struct Iter<'a, V> {
+    buckets: std::slice::Iter<'a, Vec<(Vec<u8>, V)>>,
+    bucket: std::slice::Iter<'a, (Vec<u8>, V)>,
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (&'a Vec<u8>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key, value)) = self.bucket.next() {
+                return Some((key, value));
+            }
+            self.bucket = self.buckets.next()?.iter();
+        }
+    }
+}
+
+/// Iterates the members of a set.
+pub type Members<'a> = std::iter::Map<Iter<'a, ()>, fn((&'a Vec<u8>, &'a ())) -> &'a Vec<u8>>;
+
+/// A set of byte strings, stored as a dict without values.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Set {
+    members: Dict<()>,
+}
+
+impl Set {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            members: Dict::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    pub fn contains(&self, member: &[u8]) -> bool {
+        self.members.contains_key(member)
+    }
+
+    /// Adds `member`, returning whether it was not already present.
+    pub fn insert(&mut self, member: Vec<u8>) -> bool {
+        self.members.insert(member, ()).is_none()
+    }
+
+    /// Removes `member`, returning whether it was present.
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        self.members.remove(member).is_some()
+    }
+
+    pub fn iter(&self) -> Members<'_> {
+        self.members.iter().map(|(member, _)| member)
+    }
+
+    /// Like `Dict::random_entry`, for the members.
+    pub fn random_member(&self, random_index: impl FnMut(usize) -> usize) -> Option<&Vec<u8>> {
+        self.members
+            .random_entry(random_index)
+            .map(|(member, _)| member)
+    }
+
+    /// Like `Dict::random_entries`, for the members.
+    pub fn random_members(
+        &self,
+        count: usize,
+        random_index: impl FnMut(usize) -> usize,
+    ) -> Vec<&Vec<u8>> {
+        self.members
+            .random_entries(count, random_index)
+            .into_iter()
+            .map(|(member, _)| member)
+            .collect()
+    }
+
+    /// Like `Dict::scan`, for the members.
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<&Vec<u8>>) {
+        let (cursor, members) = self.members.scan(cursor, count);
+        (
+            cursor,
+            members.into_iter().map(|(member, _)| member).collect(),
+        )
+    }
+}
+
+impl FromIterator<Vec<u8>> for Set {
+    fn from_iter<T: IntoIterator<Item = Vec<u8>>>(iter: T) -> Self {
+        Self {
+            members: iter.into_iter().map(|member| (member, ())).collect(),
+        }
+    }
+}
+
+impl IntoIterator for Set {
+    type Item = Vec<u8>;
+    type IntoIter =
+        std::iter::Map<<Dict<()> as IntoIterator>::IntoIter, fn((Vec<u8>, ())) -> Vec<u8>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.members.into_iter().map(|(member, _)| member)
+    }
+}
+
+impl<'a> IntoIterator for &'a Set {
+    type Item = &'a Vec<u8>;
+    type IntoIter = Members<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::rdb::dict::Dict;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_insert_and_remove_across_resizes() {
+        let mut dict = Dict::new();
+        for i in 0..1000 {
+            assert_eq!(dict.insert(format!("key:{}", i).into_bytes(), i), None);
+        }
+        assert_eq!(dict.insert(b"key:7".to_vec(), 70), Some(7));
+        assert_eq!(dict.len(), 1000);
+        for i in 0..990 {
+            assert!(dict.remove(format!("key:{}", i).as_bytes()).is_some());
+        }
+        assert_eq!(dict.len(), 10);
+        assert_eq!(dict.get(b"key:995"), Some(&995));
+        assert_eq!(dict.get(b"key:7"), None);
+        assert_eq!(dict.iter().count(), 10);
+    }
+
+    #[test]
+    fn test_scan_returns_keys_present_for_the_whole_iteration() {
+        let mut dict = Dict::new();
+        for i in 0..50 {
+            dict.insert(format!("stable:{}", i).into_bytes(), ());
+        }
+
+        let mut seen = HashSet::new();
+        let mut cursor = 0;
+        let mut round = 0;
+        loop {
+            let (next, keys) = dict.scan(cursor, 5);
+            seen.extend(keys.into_iter().map(|(key, _)| key.clone()));
+            // Other keys come and go during the iteration, growing the table then shrinking
+            // it, which must not make the stable keys be missed.
+            for j in 0..20 {
+                if round < 10 {
+                    dict.insert(format!("churn:{}:{}", round, j).into_bytes(), ());
+                } else {
+                    dict.remove(format!("churn:{}:{}", round - 10, j).as_bytes());
+                }
+            }
+            round += 1;
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+
+        for i in 0..50 {
+            assert!(seen.contains(format!("stable:{}", i).as_bytes()));
+        }
+    }
+
+    #[test]
+    fn test_random_entries_are_distinct() {
+        let mut dict = Dict::new();
+        for i in 0..100 {
+            dict.insert(format!("key:{}", i).into_bytes(), i);
+        }
+        let mut seed = 1usize;
+        let mut random_index = |len: usize| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (seed >> 33) % len
+        };
+        assert!(dict.random_entry(&mut random_index).is_some());
+        for count in [0, 5, 50, 100, 1000] {
+            let entries = dict.random_entries(count, &mut random_index);
+            let keys: HashSet<_> = entries.iter().map(|(key, _)| *key).collect();
+            assert_eq!(entries.len(), count.min(100));
+            assert_eq!(keys.len(), entries.len());
+        }
+        assert!(Dict::<()>::new().random_entry(&mut random_index).is_none());
+    }
+}
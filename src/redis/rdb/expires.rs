@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+/// The keys that have an expiration, kept apart from the keyspace so that the active expiry
+/// cycle visits them without scanning every key.
+#[derive(Default)]
+pub struct ExpireIndex {
+    keys: Vec<Vec<u8>>,
+    positions: HashMap<Vec<u8>, usize>,
+    cursor: usize,
+}
+
+impl ExpireIndex {
+    pub fn insert(&mut self, key: &[u8]) {
+        if !self.positions.contains_key(key) {
+            self.positions.insert(key.to_vec(), self.keys.len());
+            self.keys.push(key.to_vec());
+        }
+    }
+
+    pub fn remove(&mut self, key: &[u8]) {
+        let Some(position) = self.positions.remove(key) else {
+            return;
+        };
+        self.keys.swap_remove(position);
+        if let Some(moved) = self.keys.get(position) {
+            self.positions.insert(moved.clone(), position);
+        }
+    }
+
+    pub fn keys(&self) -> &[Vec<u8>] {
+        &self.keys
+    }
+
+    /// Returns up to `count` keys from where the previous call stopped, wrapping around at the
+    /// end. Removals may move a key behind the cursor, which then waits for the next pass.
+    pub fn next_keys(&mut self, count: usize) -> Vec<Vec<u8>> {
+        let count = count.min(self.keys.len());
+        (0..count)
+            .map(|_| {
+                if self.cursor >= self.keys.len() {
+                    self.cursor = 0;
+                }
+                self.cursor += 1;
+                self.keys[self.cursor - 1].clone()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::rdb::expires::ExpireIndex;
+
+    #[test]
+    fn test_next_keys_wraps_around() {
+        let mut index = ExpireIndex::default();
+        for key in [b"a", b"b", b"c"] {
+            index.insert(key);
+        }
+        index.insert(b"a");
+        assert_eq!(index.keys().len(), 3);
+        assert_eq!(index.next_keys(2), vec![b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(index.next_keys(2), vec![b"c".to_vec(), b"a".to_vec()]);
+
+        index.remove(b"a");
+        assert_eq!(index.keys(), [b"c".to_vec(), b"b".to_vec()]);
+        assert_eq!(index.next_keys(5), vec![b"b".to_vec(), b"c".to_vec()]);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::redis::rdb::read_database::DatabaseReaderError;
+
+const HEADER_SIZE: usize = 6;
+const END: u8 = 0xff;
+
+/// Decodes a listpack blob into its entries, integers being rendered as decimal strings.
+pub fn read_listpack(data: &[u8]) -> Result<Vec<Vec<u8>>, DatabaseReaderError> {
+    let mut entries = Vec::new();
+    let mut position = HEADER_SIZE;
+
+    loop {
+        let encoding = *data
+            .get(position)
+            .ok_or(DatabaseReaderError::InvalidFileEncoding)?;
+        if encoding == END {
+            return Ok(entries);
+        }
+
+        let (entry, size) = match encoding {
+            0x00..=0x7f => (Entry::Integer(encoding as i64), 1),
+            0x80..=0xbf => {
+                let length = (encoding & 0x3f) as usize;
+                (Entry::String(read(data, position + 1, length)?), 1 + length)
+            }
+            0xc0..=0xdf => {
+                let value =
+                    ((encoding as i64 & 0x1f) << 8) | read(data, position + 1, 1)?[0] as i64;
+                (Entry::Integer(sign_extend(value, 13)), 2)
+            }
+            0xe0..=0xef => {
+                let length =
+                    ((encoding as usize & 0x0f) << 8) | read(data, position + 1, 1)?[0] as usize;
+                (Entry::String(read(data, position + 2, length)?), 2 + length)
+            }
+            0xf0 => {
+                let length = read_le(read(data, position + 1, 4)?) as usize;
+                (Entry::String(read(data, position + 5, length)?), 5 + length)
+            }
+            0xf1 => (Entry::Integer(read_signed(data, position, 2)?), 3),
+            0xf2 => (Entry::Integer(read_signed(data, position, 3)?), 4),
+            0xf3 => (Entry::Integer(read_signed(data, position, 4)?), 5),
+            0xf4 => (Entry::Integer(read_signed(data, position, 8)?), 9),
+            _ => return Err(DatabaseReaderError::InvalidFileEncoding),
+        };
+
+        entries.push(match entry {
+            Entry::Integer(value) => value.to_string().into_bytes(),
+            Entry::String(value) => value.to_vec(),
+        });
+        position += size + backlen_size(size);
+    }
+}
+
+/// Encodes entries into a listpack blob, storing the ones that are canonical decimal integers
+/// with an integer encoding like Redis does.
+pub fn write_listpack(entries: &[impl AsRef<[u8]>]) -> Vec<u8> {
+    let mut data = vec![0u8; HEADER_SIZE];
+    for entry in entries {
+        let entry = entry.as_ref();
+        let start = data.len();
+        match as_integer(entry) {
+            Some(value) => write_integer(&mut data, value),
+            None => write_string(&mut data, entry),
+        }
+        let size = data.len() - start;
+        write_backlen(&mut data, size);
+    }
+    data.push(END);
+
+    let total = data.len() as u32;
+    let count = entries.len().min(u16::MAX as usize) as u16;
+    data[0..4].copy_from_slice(&total.to_le_bytes());
+    data[4..6].copy_from_slice(&count.to_le_bytes());
+    data
+}
+
+enum Entry<'a> {
+    Integer(i64),
+    String(&'a [u8]),
+}
+
+fn read(data: &[u8], position: usize, length: usize) -> Result<&[u8], DatabaseReaderError> {
+    data.get(position..position + length)
+        .ok_or(DatabaseReaderError::InvalidFileEncoding)
+}
+
+fn read_le(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0u64, |value, byte| (value << 8) | *byte as u64)
+}
+
+fn read_signed(data: &[u8], position: usize, length: usize) -> Result<i64, DatabaseReaderError> {
+    let value = read_le(read(data, position + 1, length)?);
+    Ok(sign_extend(value as i64, length as u32 * 8))
+}
+
+fn sign_extend(value: i64, bits: u32) -> i64 {
+    if bits >= 64 {
+        return value;
+    }
+    let shift = 64 - bits;
+    (value << shift) >> shift
+}
+
+fn as_integer(entry: &[u8]) -> Option<i64> {
+    let value: i64 = std::str::from_utf8(entry).ok()?.parse().ok()?;
+    (value.to_string().as_bytes() == entry).then_some(value)
+}
+
+fn write_integer(data: &mut Vec<u8>, value: i64) {
+    match value {
+        0..=127 => data.push(value as u8),
+        -4096..=4095 => {
+            data.push(0xc0 | ((value >> 8) as u8 & 0x1f));
+            data.push(value as u8);
+        }
+        -32768..=32767 => {
+            data.push(0xf1);
+            data.extend_from_slice(&(value as i16).to_le_bytes());
+        }
+        -8388608..=8388607 => {
+            data.push(0xf2);
+            data.extend_from_slice(&(value as i32).to_le_bytes()[..3]);
+        }
+        -2147483648..=2147483647 => {
+            data.push(0xf3);
+            data.extend_from_slice(&(value as i32).to_le_bytes());
+        }
+        _ => {
+            data.push(0xf4);
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+fn write_string(data: &mut Vec<u8>, value: &[u8]) {
+    match value.len() {
+        0..64 => data.push(0x80 | value.len() as u8),
+        64..4096 => {
+            data.push(0xe0 | (value.len() >> 8) as u8);
+            data.push(value.len() as u8);
+        }
+        _ => {
+            data.push(0xf0);
+            data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        }
+    }
+    data.extend_from_slice(value);
+}
+
+/// Appends the size of the preceding entry, written so that it can be decoded backwards:
+/// the first byte holds the most significant 7 bits and every following byte has its high
+/// bit set.
+fn write_backlen(data: &mut Vec<u8>, size: usize) {
+    let bytes = backlen_size(size);
+    for index in (0..bytes).rev() {
+        let part = ((size >> (7 * index)) & 0x7f) as u8;
+        data.push(if index == bytes - 1 {
+            part
+        } else {
+            part | 0x80
+        });
+    }
+}
+
+fn backlen_size(size: usize) -> usize {
+    match size {
+        0..128 => 1,
+        128..16384 => 2,
+        16384..2097152 => 3,
+        2097152..268435456 => 4,
+        _ => 5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::rdb::listpack::{read_listpack, write_listpack};
+
+    #[test]
+    fn test_read_listpack() {
+        let data = [
+            0x12, 0, 0, 0, 0x4, 0, // header
+            0x83, b'f', b'o', b'o', 0x4, // "foo"
+            0x07, 0x1, // 7
+            0xdf, 0xff, 0x2, // -1
+            0xf1, 0x10, 0x27, 0x3, // 10000
+            0xff,
+        ];
+        assert_eq!(
+            read_listpack(&data).unwrap(),
+            vec![
+                b"foo".to_vec(),
+                b"7".to_vec(),
+                b"-1".to_vec(),
+                b"10000".to_vec()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_listpack() {
+        let entries = [
+            b"foo".to_vec(),
+            b"7".to_vec(),
+            b"-1".to_vec(),
+            b"10000".to_vec(),
+            b"007".to_vec(),
+            vec![b'x'; 200],
+            i64::MIN.to_string().into_bytes(),
+        ];
+        let data = write_listpack(&entries);
+        assert_eq!(
+            &data[..11],
+            [240, 0, 0, 0, 7, 0, 0x83, b'f', b'o', b'o', 0x4]
+        );
+        assert_eq!(read_listpack(&data).unwrap(), entries);
+    }
+}
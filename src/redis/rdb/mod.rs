@@ -1,7 +1,22 @@
 mod constants;
+mod consumer_group;
+mod database;
+mod dict;
+mod expires;
+mod listpack;
 mod read_database;
+mod skip_list;
+mod sorted_set;
 mod storage;
+mod stream;
 mod ttl;
+mod value;
 mod write_database;
 
+pub use consumer_group::ConsumerGroup;
+pub use database::{Database, WrongTypeError};
+pub use dict::Set;
+pub use sorted_set::{LexBound, ScoreBound, SortedSet};
 pub use storage::RedisStorage;
+pub use stream::{Stream, StreamFields, StreamId, Trim, TrimThreshold, STREAM_NODE_MAX_ENTRIES};
+pub use value::Value;
@@ -1,24 +1,42 @@
-use crate::redis::rdb::constants::{AUX, EOF, EXPIRE_TIME, EXPIRE_TIME_MS, RESIZE_DB, SELECT_DB};
+use crate::redis::rdb::constants::{
+    AUX, EOF, EXPIRE_TIME, EXPIRE_TIME_MS, HASH_LISTPACK_TYPE, HASH_TYPE, LIST_QUICKLIST_2_TYPE,
+    LIST_TYPE, QUICKLIST_NODE_PACKED, QUICKLIST_NODE_PLAIN, RESIZE_DB, SELECT_DB, SET_INTSET_TYPE,
+    SET_LISTPACK_TYPE, SET_TYPE, SORTED_SET_2_TYPE, SORTED_SET_LISTPACK_TYPE, SORTED_SET_TYPE,
+    STREAM_ITEM_FLAG_DELETED, STREAM_ITEM_FLAG_SAME_FIELDS, STREAM_LISTPACKS_2_TYPE,
+    STREAM_LISTPACKS_3_TYPE, STREAM_LISTPACKS_TYPE, STRING_TYPE,
+};
+use crate::redis::rdb::consumer_group::{Consumer, ConsumerGroup, PendingEntry};
+use crate::redis::rdb::dict::{Dict, Set};
+use crate::redis::rdb::listpack::read_listpack;
+use crate::redis::rdb::sorted_set::SortedSet;
+use crate::redis::rdb::stream::{Stream, StreamFields, StreamId};
 use crate::redis::rdb::ttl::Ttl;
+use crate::redis::rdb::value::Value;
 use crc_fast::{CrcAlgorithm, Digest};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
+use std::slice::Iter;
 use thiserror::Error;
 
 const MAGIC_STRING_SIZE: u8 = 5;
 const VERSION_STRING_SIZE: u8 = 4;
 
-type ReadResult = Result<Option<HashMap<String, (String, Ttl)>>, DatabaseReaderError>;
+type Database = HashMap<Vec<u8>, (Value, Ttl)>;
+type ReadResult = Result<Vec<(u32, Database)>, DatabaseReaderError>;
 
 pub fn read_databases(path: &Path) -> ReadResult {
-    let mut file = File::open(path)?;
+    read_rdb(&mut File::open(path)?)
+}
+
+/// Reads the databases of an RDB payload, like the one a master sends to its replicas.
+pub fn read_rdb(reader: &mut impl Read) -> ReadResult {
     let mut digest = Digest::new(CrcAlgorithm::Crc64Redis);
     let mut digest_option = Some(&mut digest);
 
     log::debug!("reading first database");
-    let (magic_string, version) = read_header_section(&mut file, &mut digest_option)?;
+    let (magic_string, version) = read_header_section(reader, &mut digest_option)?;
     log::debug!("version: {}", version);
     log::debug!("magic string: {}", magic_string);
     if magic_string != "REDIS" {
@@ -28,12 +46,12 @@ pub fn read_databases(path: &Path) -> ReadResult {
     let mut databases = vec![];
 
     loop {
-        let section = read_section(&mut file, &mut digest_option)?;
+        let section = read_section(reader, &mut digest_option)?;
         match section {
             Section::Metadata(key, value) => {
                 log::debug!("metadata: {}: {}", key, value);
             }
-            Section::Database(_, data) => databases.push(data),
+            Section::Database(number, data) => databases.push((number, data)),
             Section::Checksum(checksum) => {
                 log::debug!("checksum: {}", checksum);
                 let calculated_checksum = digest.finalize();
@@ -43,7 +61,7 @@ pub fn read_databases(path: &Path) -> ReadResult {
         }
     }
 
-    Ok(databases.pop())
+    Ok(databases)
 }
 fn read_length<T>(
     file: &mut T,
@@ -74,6 +92,13 @@ where
         ])));
     }
 
+    if length[0] == 0x81 {
+        let mut length = [0u8; 8];
+        file.read_exact(&mut length)?;
+        copy_to_digest(digest, &length);
+        return Ok(LengthEncoding::Bits64(u64::from_be_bytes(length)));
+    }
+
     if flag == 2 {
         let mut length = [0u8; 4];
         file.read_exact(&mut length)?;
@@ -88,6 +113,17 @@ fn read_string<T>(
     file: &mut T,
     digest: &mut Option<&mut Digest>,
 ) -> Result<String, DatabaseReaderError>
+where
+    T: Read,
+{
+    let string = read_bytes(file, digest)?;
+    Ok(String::from_utf8(string).map_err(|e| e.utf8_error())?)
+}
+
+fn read_bytes<T>(
+    file: &mut T,
+    digest: &mut Option<&mut Digest>,
+) -> Result<Vec<u8>, DatabaseReaderError>
 where
     T: Read,
 {
@@ -100,19 +136,27 @@ where
                 let mut value = [0u8; 1];
                 file.read_exact(&mut value)?;
                 copy_to_digest(digest, &value);
-                Ok(value[0].to_string())
+                Ok(i8::from_le_bytes(value).to_string().into_bytes())
             }
             1 => {
                 let mut value = [0u8; 2];
                 file.read_exact(&mut value)?;
                 copy_to_digest(digest, &value);
-                Ok(u16::from_be_bytes(value).to_string())
+                Ok(i16::from_le_bytes(value).to_string().into_bytes())
             }
             2 => {
                 let mut value = [0u8; 4];
                 file.read_exact(&mut value)?;
                 copy_to_digest(digest, &value);
-                Ok(u32::from_be_bytes(value).to_string())
+                Ok(i32::from_le_bytes(value).to_string().into_bytes())
+            }
+            3 => {
+                let compressed_length = read_length(file, digest)?.get_length()?;
+                let length = read_length(file, digest)?.get_length()?;
+                let mut compressed = vec![0u8; compressed_length as usize];
+                file.read_exact(&mut compressed)?;
+                copy_to_digest(digest, &compressed);
+                decompress_lzf(&compressed, length as usize)
             }
             _ => Err(DatabaseReaderError::InvalidFileEncoding),
         }
@@ -120,8 +164,54 @@ where
         let mut string = vec![0u8; length.get_length()? as usize];
         file.read_exact(&mut string)?;
         copy_to_digest(digest, &string);
-        Ok(std::str::from_utf8(&string)?.to_string())
+        Ok(string)
+    }
+}
+
+/// Expands an LZF blob: a control byte below 32 starts a run of that many plus one literal
+/// bytes, any other one copies an earlier stretch of the output, which may overlap the bytes
+/// being written.
+fn decompress_lzf(data: &[u8], length: usize) -> Result<Vec<u8>, DatabaseReaderError> {
+    let mut output = Vec::with_capacity(length);
+    let mut position = 0;
+    while position < data.len() {
+        let control = data[position] as usize;
+        position += 1;
+        if control < 32 {
+            let literal = data
+                .get(position..position + control + 1)
+                .ok_or(DatabaseReaderError::InvalidFileEncoding)?;
+            output.extend_from_slice(literal);
+            position += control + 1;
+            continue;
+        }
+
+        let mut run = control >> 5;
+        if run == 7 {
+            run += *data
+                .get(position)
+                .ok_or(DatabaseReaderError::InvalidFileEncoding)? as usize;
+            position += 1;
+        }
+        let offset = ((control & 0x1f) << 8)
+            + *data
+                .get(position)
+                .ok_or(DatabaseReaderError::InvalidFileEncoding)? as usize
+            + 1;
+        position += 1;
+        let start = output
+            .len()
+            .checked_sub(offset)
+            .ok_or(DatabaseReaderError::InvalidFileEncoding)?;
+        for index in start..start + run + 2 {
+            output.push(output[index]);
+        }
+    }
+
+    if output.len() != length {
+        return Err(DatabaseReaderError::InvalidFileEncoding);
     }
+    Ok(output)
 }
 
 fn read_header_section<T>(
@@ -178,7 +268,7 @@ where
 fn read_database_section<T>(
     file: &mut T,
     digest: &mut Option<&mut Digest>,
-) -> Result<HashMap<String, (String, Ttl)>, DatabaseReaderError>
+) -> Result<Database, DatabaseReaderError>
 where
     T: Read,
 {
@@ -208,7 +298,7 @@ where
                 file.read_exact(&mut expire_time)?;
                 copy_to_digest(digest, &expire_time);
                 let expire_time = u32::from_le_bytes(expire_time);
-                read_value_type(file, digest)?;
+                let value_type = read_value_type(file, digest)?;
                 current_db_size_expire += 1;
                 current_db_size += 1;
 
@@ -216,17 +306,16 @@ where
                     return Err(DatabaseReaderError::InvalidFileEncoding);
                 }
 
-                db.insert(
-                    read_string(file, digest)?,
-                    (read_string(file, digest)?, Ttl::Seconds(expire_time)),
-                );
+                let key = read_bytes(file, digest)?;
+                let value = read_value(file, digest, value_type)?;
+                db.insert(key, (value, Ttl::Seconds(expire_time)));
             }
             EXPIRE_TIME_MS => {
                 let mut expire_time = [0u8; 8];
                 file.read_exact(&mut expire_time)?;
                 copy_to_digest(digest, &expire_time);
                 let expire_time = u64::from_le_bytes(expire_time);
-                read_value_type(file, digest)?;
+                let value_type = read_value_type(file, digest)?;
                 current_db_size_expire += 1;
                 current_db_size += 1;
 
@@ -234,20 +323,15 @@ where
                     return Err(DatabaseReaderError::InvalidFileEncoding);
                 }
 
-                db.insert(
-                    read_string(file, digest)?,
-                    (read_string(file, digest)?, Ttl::Milliseconds(expire_time)),
-                );
+                let key = read_bytes(file, digest)?;
+                let value = read_value(file, digest, value_type)?;
+                db.insert(key, (value, Ttl::Milliseconds(expire_time)));
             }
-            _ => {
-                if !is_supported_value_type(byte[0]) {
-                    return Err(DatabaseReaderError::UnsupportedValueType);
-                }
+            value_type => {
                 current_db_size += 1;
-                db.insert(
-                    read_string(file, digest)?,
-                    (read_string(file, digest)?, Ttl::None),
-                );
+                let key = read_bytes(file, digest)?;
+                let value = read_value(file, digest, value_type)?;
+                db.insert(key, (value, Ttl::None));
             }
         }
     }
@@ -258,21 +342,390 @@ where
 fn read_value_type<T>(
     file: &mut T,
     digest: &mut Option<&mut Digest>,
-) -> Result<(), DatabaseReaderError>
+) -> Result<u8, DatabaseReaderError>
 where
     T: Read,
 {
     let mut byte = [0u8; 1];
     file.read_exact(&mut byte)?;
     copy_to_digest(digest, &byte);
-    if !is_supported_value_type(byte[0]) {
-        return Err(DatabaseReaderError::UnsupportedValueType);
+    Ok(byte[0])
+}
+
+fn read_value<T>(
+    file: &mut T,
+    digest: &mut Option<&mut Digest>,
+    value_type: u8,
+) -> Result<Value, DatabaseReaderError>
+where
+    T: Read,
+{
+    log::debug!("reading value of type {}", value_type);
+    match value_type {
+        STRING_TYPE => Ok(Value::String(read_bytes(file, digest)?)),
+        LIST_TYPE => {
+            let length = read_length(file, digest)?.get_length()?;
+            let mut list = VecDeque::with_capacity(length as usize);
+            for _ in 0..length {
+                list.push_back(read_bytes(file, digest)?);
+            }
+            Ok(Value::List(list))
+        }
+        LIST_QUICKLIST_2_TYPE => {
+            let nodes = read_length(file, digest)?.get_length()?;
+            let mut list = VecDeque::new();
+            for _ in 0..nodes {
+                let container = read_length(file, digest)?.get_length()?;
+                let node = read_bytes(file, digest)?;
+                match container {
+                    QUICKLIST_NODE_PLAIN => list.push_back(node),
+                    QUICKLIST_NODE_PACKED => list.extend(read_listpack(&node)?),
+                    _ => return Err(DatabaseReaderError::InvalidFileEncoding),
+                }
+            }
+            Ok(Value::List(list))
+        }
+        SET_TYPE => {
+            let length = read_length(file, digest)?.get_length()?;
+            let mut set = Set::with_capacity(length as usize);
+            for _ in 0..length {
+                set.insert(read_bytes(file, digest)?);
+            }
+            Ok(Value::Set(set))
+        }
+        SET_INTSET_TYPE => {
+            let data = read_bytes(file, digest)?;
+            Ok(Value::Set(read_intset(&data)?.into_iter().collect()))
+        }
+        SET_LISTPACK_TYPE => {
+            let entries = read_listpack(&read_bytes(file, digest)?)?;
+            Ok(Value::Set(entries.into_iter().collect()))
+        }
+        SORTED_SET_TYPE | SORTED_SET_2_TYPE => {
+            let length = read_length(file, digest)?.get_length()?;
+            let mut sorted_set = SortedSet::default();
+            for _ in 0..length {
+                let member = read_bytes(file, digest)?;
+                let score = if value_type == SORTED_SET_2_TYPE {
+                    let mut score = [0u8; 8];
+                    file.read_exact(&mut score)?;
+                    copy_to_digest(digest, &score);
+                    f64::from_le_bytes(score)
+                } else {
+                    read_string_score(file, digest)?
+                };
+                sorted_set.insert(member, score);
+            }
+            Ok(Value::SortedSet(sorted_set))
+        }
+        SORTED_SET_LISTPACK_TYPE => {
+            let entries = read_listpack(&read_bytes(file, digest)?)?;
+            if !entries.len().is_multiple_of(2) {
+                return Err(DatabaseReaderError::InvalidFileEncoding);
+            }
+            let mut entries = entries.into_iter();
+            let mut sorted_set = SortedSet::default();
+            while let (Some(member), Some(score)) = (entries.next(), entries.next()) {
+                sorted_set.insert(member, parse_score(&score)?);
+            }
+            Ok(Value::SortedSet(sorted_set))
+        }
+        HASH_TYPE => {
+            let length = read_length(file, digest)?.get_length()?;
+            let mut hash = Dict::with_capacity(length as usize);
+            for _ in 0..length {
+                let field = read_bytes(file, digest)?;
+                hash.insert(field, read_bytes(file, digest)?);
+            }
+            Ok(Value::Hash(hash))
+        }
+        HASH_LISTPACK_TYPE => {
+            let entries = read_listpack(&read_bytes(file, digest)?)?;
+            if !entries.len().is_multiple_of(2) {
+                return Err(DatabaseReaderError::InvalidFileEncoding);
+            }
+            let mut entries = entries.into_iter();
+            let mut hash = Dict::new();
+            while let (Some(field), Some(value)) = (entries.next(), entries.next()) {
+                hash.insert(field, value);
+            }
+            Ok(Value::Hash(hash))
+        }
+        STREAM_LISTPACKS_TYPE | STREAM_LISTPACKS_2_TYPE | STREAM_LISTPACKS_3_TYPE => {
+            Ok(Value::Stream(read_stream(file, digest, value_type)?))
+        }
+        _ => Err(DatabaseReaderError::UnsupportedValueType),
+    }
+}
+
+/// Reads the listpack nodes of a stream, keyed by the big-endian ID their entries are
+/// relative to, followed by the stream bookkeeping.
+fn read_stream<T>(
+    file: &mut T,
+    digest: &mut Option<&mut Digest>,
+    value_type: u8,
+) -> Result<Stream, DatabaseReaderError>
+where
+    T: Read,
+{
+    let nodes = read_length(file, digest)?.get_length()?;
+    let mut entries = BTreeMap::new();
+    for _ in 0..nodes {
+        let key = read_bytes(file, digest)?;
+        if key.len() != 16 {
+            return Err(DatabaseReaderError::InvalidFileEncoding);
+        }
+        let master_id = StreamId::new(
+            u64::from_be_bytes(key[0..8].try_into().unwrap()),
+            u64::from_be_bytes(key[8..16].try_into().unwrap()),
+        );
+        let items = read_listpack(&read_bytes(file, digest)?)?;
+        read_stream_node(master_id, &items, &mut entries)?;
+    }
+
+    read_length(file, digest)?.get_long_length()?;
+    let last_id = read_stream_id(file, digest)?;
+    let (max_deleted_id, entries_added) = if value_type == STREAM_LISTPACKS_TYPE {
+        (StreamId::MIN, entries.len() as u64)
+    } else {
+        read_stream_id(file, digest)?;
+        let max_deleted_id = read_stream_id(file, digest)?;
+        (
+            max_deleted_id,
+            read_length(file, digest)?.get_long_length()?,
+        )
+    };
+
+    let group_count = read_length(file, digest)?.get_length()?;
+    let mut groups = Vec::with_capacity(group_count as usize);
+    for _ in 0..group_count {
+        groups.push(read_consumer_group(file, digest, value_type)?);
+    }
+
+    let mut stream = Stream::from_parts(entries, last_id, max_deleted_id, entries_added);
+    for (name, mut group) in groups {
+        if value_type == STREAM_LISTPACKS_TYPE {
+            group.entries_read = stream.estimate_entries_read(&group.last_id);
+        }
+        stream.create_group(&name, group);
+    }
+    Ok(stream)
+}
+
+/// Reads a consumer group: its last delivered ID, the pending entries list and the consumers
+/// with the IDs of the pending entries they own.
+fn read_consumer_group<T>(
+    file: &mut T,
+    digest: &mut Option<&mut Digest>,
+    value_type: u8,
+) -> Result<(Vec<u8>, ConsumerGroup), DatabaseReaderError>
+where
+    T: Read,
+{
+    let name = read_bytes(file, digest)?;
+    let last_id = read_stream_id(file, digest)?;
+    let entries_read = if value_type == STREAM_LISTPACKS_TYPE {
+        None
+    } else {
+        let entries_read = read_length(file, digest)?.get_long_length()?;
+        (entries_read != u64::MAX).then_some(entries_read)
+    };
+    let mut group = ConsumerGroup::new(last_id, entries_read);
+
+    let pending = read_length(file, digest)?.get_long_length()?;
+    for _ in 0..pending {
+        let id = read_raw_stream_id(file, digest)?;
+        let delivery_time = read_millisecond_time(file, digest)?;
+        let delivery_count = read_length(file, digest)?.get_long_length()?;
+        group.pending.insert(
+            id,
+            PendingEntry {
+                consumer: Vec::new(),
+                delivery_time,
+                delivery_count,
+            },
+        );
+    }
+
+    let consumers = read_length(file, digest)?.get_long_length()?;
+    for _ in 0..consumers {
+        let consumer_name = read_bytes(file, digest)?;
+        let seen_time = read_millisecond_time(file, digest)?;
+        let active_time = if value_type == STREAM_LISTPACKS_3_TYPE {
+            let active_time = read_millisecond_time(file, digest)? as i64;
+            (active_time >= 0).then_some(active_time as u64)
+        } else {
+            Some(seen_time)
+        };
+
+        let mut consumer = Consumer {
+            seen_time,
+            active_time,
+            pending: BTreeSet::new(),
+        };
+        let pending = read_length(file, digest)?.get_long_length()?;
+        for _ in 0..pending {
+            let id = read_raw_stream_id(file, digest)?;
+            let entry = group
+                .pending
+                .get_mut(&id)
+                .ok_or(DatabaseReaderError::InvalidFileEncoding)?;
+            entry.consumer = consumer_name.clone();
+            consumer.pending.insert(id);
+        }
+        group.consumers.insert(consumer_name, consumer);
+    }
+    Ok((name, group))
+}
+
+fn read_raw_stream_id<T>(
+    file: &mut T,
+    digest: &mut Option<&mut Digest>,
+) -> Result<StreamId, DatabaseReaderError>
+where
+    T: Read,
+{
+    let mut id = [0u8; 16];
+    file.read_exact(&mut id)?;
+    copy_to_digest(digest, &id);
+    Ok(StreamId::new(
+        u64::from_be_bytes(id[0..8].try_into().unwrap()),
+        u64::from_be_bytes(id[8..16].try_into().unwrap()),
+    ))
+}
+
+fn read_millisecond_time<T>(
+    file: &mut T,
+    digest: &mut Option<&mut Digest>,
+) -> Result<u64, DatabaseReaderError>
+where
+    T: Read,
+{
+    let mut time = [0u8; 8];
+    file.read_exact(&mut time)?;
+    copy_to_digest(digest, &time);
+    Ok(u64::from_le_bytes(time))
+}
+
+/// Decodes a stream node: a master entry with the entry counts and the shared fields, then
+/// every entry with its flags, its ID relative to the master ID and its fields.
+fn read_stream_node(
+    master_id: StreamId,
+    items: &[Vec<u8>],
+    entries: &mut BTreeMap<StreamId, StreamFields>,
+) -> Result<(), DatabaseReaderError> {
+    let mut items = items.iter();
+    let count = next_integer(&mut items)?;
+    let deleted = next_integer(&mut items)?;
+    let master_fields = (0..next_integer(&mut items)?)
+        .map(|_| next_item(&mut items))
+        .collect::<Result<Vec<_>, _>>()?;
+    next_integer(&mut items)?;
+
+    for _ in 0..count + deleted {
+        let flags = next_integer(&mut items)?;
+        let id = StreamId::new(
+            master_id.ms.wrapping_add(next_integer(&mut items)? as u64),
+            master_id.seq.wrapping_add(next_integer(&mut items)? as u64),
+        );
+        let fields = if flags & STREAM_ITEM_FLAG_SAME_FIELDS != 0 {
+            master_fields
+                .iter()
+                .map(|field| Ok((field.clone(), next_item(&mut items)?)))
+                .collect::<Result<StreamFields, DatabaseReaderError>>()?
+        } else {
+            (0..next_integer(&mut items)?)
+                .map(|_| Ok((next_item(&mut items)?, next_item(&mut items)?)))
+                .collect::<Result<StreamFields, DatabaseReaderError>>()?
+        };
+        next_integer(&mut items)?;
+
+        if flags & STREAM_ITEM_FLAG_DELETED == 0 {
+            entries.insert(id, fields);
+        }
     }
     Ok(())
 }
 
-fn is_supported_value_type(byte: u8) -> bool {
-    byte == 0
+fn next_item(items: &mut Iter<Vec<u8>>) -> Result<Vec<u8>, DatabaseReaderError> {
+    items
+        .next()
+        .cloned()
+        .ok_or(DatabaseReaderError::InvalidFileEncoding)
+}
+
+fn next_integer(items: &mut Iter<Vec<u8>>) -> Result<i64, DatabaseReaderError> {
+    std::str::from_utf8(&next_item(items)?)?
+        .parse()
+        .map_err(|_| DatabaseReaderError::InvalidFileEncoding)
+}
+
+fn read_stream_id<T>(
+    file: &mut T,
+    digest: &mut Option<&mut Digest>,
+) -> Result<StreamId, DatabaseReaderError>
+where
+    T: Read,
+{
+    let ms = read_length(file, digest)?.get_long_length()?;
+    let seq = read_length(file, digest)?.get_long_length()?;
+    Ok(StreamId::new(ms, seq))
+}
+
+/// Reads a score stored as a length-prefixed decimal string, with the special lengths 253,
+/// 254 and 255 standing for NaN, +inf and -inf.
+fn read_string_score<T>(
+    file: &mut T,
+    digest: &mut Option<&mut Digest>,
+) -> Result<f64, DatabaseReaderError>
+where
+    T: Read,
+{
+    let mut length = [0u8; 1];
+    file.read_exact(&mut length)?;
+    copy_to_digest(digest, &length);
+    match length[0] {
+        253 => Ok(f64::NAN),
+        254 => Ok(f64::INFINITY),
+        255 => Ok(f64::NEG_INFINITY),
+        length => {
+            let mut score = vec![0u8; length as usize];
+            file.read_exact(&mut score)?;
+            copy_to_digest(digest, &score);
+            parse_score(&score)
+        }
+    }
+}
+
+fn parse_score(score: &[u8]) -> Result<f64, DatabaseReaderError> {
+    std::str::from_utf8(score)?
+        .parse()
+        .map_err(|_| DatabaseReaderError::InvalidFileEncoding)
+}
+
+/// Decodes an intset blob: a little-endian header with the integer width and element count,
+/// followed by the sorted little-endian integers.
+fn read_intset(data: &[u8]) -> Result<Vec<Vec<u8>>, DatabaseReaderError> {
+    if data.len() < 8 {
+        return Err(DatabaseReaderError::InvalidFileEncoding);
+    }
+    let width = u32::from_le_bytes(data[0..4].try_into().unwrap()) as usize;
+    let length = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+    if ![2, 4, 8].contains(&width) || data.len() < 8 + width * length {
+        return Err(DatabaseReaderError::InvalidFileEncoding);
+    }
+
+    Ok(data[8..8 + width * length]
+        .chunks(width)
+        .map(|bytes| {
+            let value = match width {
+                2 => i16::from_le_bytes(bytes.try_into().unwrap()) as i64,
+                4 => i32::from_le_bytes(bytes.try_into().unwrap()) as i64,
+                _ => i64::from_le_bytes(bytes.try_into().unwrap()),
+            };
+            value.to_string().into_bytes()
+        })
+        .collect())
 }
 
 fn copy_to_digest(digest: &mut Option<&mut Digest>, data: &[u8]) {
@@ -284,7 +737,7 @@ fn copy_to_digest(digest: &mut Option<&mut Digest>, data: &[u8]) {
 #[derive(Debug, PartialEq)]
 enum Section {
     Metadata(String, String),
-    Database(u32, HashMap<String, (String, Ttl)>),
+    Database(u32, Database),
     Checksum(u64),
 }
 
@@ -293,6 +746,7 @@ enum LengthEncoding {
     Bits6(u8),
     Bits14(u16),
     Bits32(u32),
+    Bits64(u64),
     Special(u8),
 }
 
@@ -302,9 +756,19 @@ impl LengthEncoding {
             LengthEncoding::Bits6(length) => Ok(*length as u32),
             LengthEncoding::Bits14(length) => Ok(*length as u32),
             LengthEncoding::Bits32(length) => Ok(*length),
+            LengthEncoding::Bits64(length) => {
+                u32::try_from(*length).map_err(|_| DatabaseReaderError::InvalidFileEncoding)
+            }
             LengthEncoding::Special(_) => Err(DatabaseReaderError::InvalidFileEncoding),
         }
     }
+
+    pub fn get_long_length(&self) -> Result<u64, DatabaseReaderError> {
+        match self {
+            LengthEncoding::Bits64(length) => Ok(*length),
+            length => length.get_length().map(u64::from),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -323,12 +787,23 @@ pub enum DatabaseReaderError {
 
 #[cfg(test)]
 mod tests {
+    use crate::redis::rdb::constants::{
+        HASH_LISTPACK_TYPE, LIST_QUICKLIST_2_TYPE, SET_INTSET_TYPE, SET_LISTPACK_TYPE,
+        SORTED_SET_LISTPACK_TYPE, STREAM_LISTPACKS_3_TYPE,
+    };
+    use crate::redis::rdb::consumer_group::PendingEntry;
     use crate::redis::rdb::read_database::{
-        read_header_section, read_length, read_section, read_string, LengthEncoding, Section, AUX,
-        EOF,
+        read_bytes, read_header_section, read_length, read_section, read_string, read_value,
+        LengthEncoding, Section, AUX, EOF,
     };
+    use crate::redis::rdb::stream::StreamId;
+    use crate::redis::rdb::value::Value;
     use std::io;
 
+    fn decode(value_type: u8, bytes: &[u8]) -> Value {
+        read_value(&mut io::Cursor::new(bytes), &mut None, value_type).unwrap()
+    }
+
     #[test]
     fn test_read_header_section() {
         assert_eq!(
@@ -402,4 +877,168 @@ mod tests {
             Section::Checksum(10)
         )
     }
+
+    #[test]
+    fn test_read_integer_strings() {
+        let read = |bytes: &[u8]| read_bytes(&mut io::Cursor::new(bytes), &mut None).unwrap();
+        assert_eq!(read(&[0xc0, 0xff]), b"-1");
+        assert_eq!(read(&[0xc1, 0xe8, 0x03]), b"1000");
+        assert_eq!(read(&[0xc2, 0x60, 0x79, 0xfe, 0xff]), b"-100000");
+    }
+
+    #[test]
+    fn test_read_lzf_string() {
+        // SET key aaaaaaaaaaaaaaaaaaaaaaaaaaaaaa
+        assert_eq!(
+            read_bytes(
+                &mut io::Cursor::new([
+                    0xc3, 0x09, 0x1e, 0x01, b'a', b'a', 0xe0, 0x11, 0x00, 0x01, b'a', b'a'
+                ]),
+                &mut None
+            )
+            .unwrap(),
+            vec![b'a'; 30]
+        );
+    }
+
+    #[test]
+    fn test_read_quicklist() {
+        // RPUSH list a b 1
+        let list = decode(
+            LIST_QUICKLIST_2_TYPE,
+            &[
+                0x01, 0x02, 0x0f, 0x0f, 0x00, 0x00, 0x00, 0x03, 0x00, 0x81, b'a', 0x02, 0x81, b'b',
+                0x02, 0x01, 0x01, 0xff,
+            ],
+        );
+        assert_eq!(
+            list,
+            Value::List([b"a".to_vec(), b"b".to_vec(), b"1".to_vec()].into())
+        );
+    }
+
+    #[test]
+    fn test_read_hash_listpack() {
+        // HSET hash f1 v1 f2 2
+        let hash = decode(
+            HASH_LISTPACK_TYPE,
+            &[
+                0x15, 0x15, 0x00, 0x00, 0x00, 0x04, 0x00, 0x82, b'f', b'1', 0x03, 0x82, b'v', b'1',
+                0x03, 0x82, b'f', b'2', 0x03, 0x02, 0x01, 0xff,
+            ],
+        );
+        assert_eq!(
+            hash,
+            Value::Hash(
+                [
+                    (b"f1".to_vec(), b"v1".to_vec()),
+                    (b"f2".to_vec(), b"2".to_vec())
+                ]
+                .into_iter()
+                .collect()
+            )
+        );
+    }
+
+    #[test]
+    fn test_read_sets() {
+        // SADD set 1 2 3
+        let intset = decode(
+            SET_INTSET_TYPE,
+            &[
+                0x0e, 0x02, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x03,
+                0x00,
+            ],
+        );
+        assert_eq!(
+            intset,
+            Value::Set(
+                [b"1".to_vec(), b"2".to_vec(), b"3".to_vec()]
+                    .into_iter()
+                    .collect()
+            )
+        );
+
+        // SADD set a b
+        let listpack = decode(
+            SET_LISTPACK_TYPE,
+            &[
+                0x0d, 0x0d, 0x00, 0x00, 0x00, 0x02, 0x00, 0x81, b'a', 0x02, 0x81, b'b', 0x02, 0xff,
+            ],
+        );
+        assert_eq!(
+            listpack,
+            Value::Set([b"a".to_vec(), b"b".to_vec()].into_iter().collect())
+        );
+    }
+
+    #[test]
+    fn test_read_sorted_set_listpack() {
+        // ZADD zset 1 a 2.5 b
+        let sorted_set = decode(
+            SORTED_SET_LISTPACK_TYPE,
+            &[
+                0x14, 0x14, 0x00, 0x00, 0x00, 0x04, 0x00, 0x81, b'a', 0x02, 0x01, 0x01, 0x81, b'b',
+                0x02, 0x83, b'2', b'.', b'5', 0x04, 0xff,
+            ],
+        );
+        assert_eq!(
+            sorted_set,
+            Value::SortedSet(
+                [(b"a".to_vec(), 1.0), (b"b".to_vec(), 2.5)]
+                    .into_iter()
+                    .collect()
+            )
+        );
+    }
+
+    #[test]
+    fn test_read_stream() {
+        // XADD stream 1-1 f v, XGROUP CREATE stream g 0, XREADGROUP GROUP g c STREAMS stream >
+        let id = [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1];
+        let time = [0x00, 0x68, 0xe5, 0xcf, 0x8b, 0x01, 0x00, 0x00];
+        let mut bytes = vec![0x01, 0x10];
+        bytes.extend(id);
+        bytes.extend([
+            0x1d, 0x1d, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x01, 0x01, 0x00, 0x01, 0x01, 0x01, 0x81,
+            b'f', 0x02, 0x00, 0x01, 0x02, 0x01, 0x00, 0x01, 0x00, 0x01, 0x81, b'v', 0x02, 0x04,
+            0x01, 0xff,
+        ]);
+        // length, last ID, first ID, max deleted ID, entries added and one group
+        bytes.extend([0x01, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x01, 0x01]);
+        bytes.extend([0x01, b'g', 0x01, 0x01, 0x01, 0x01]);
+        bytes.extend(id);
+        bytes.extend(time);
+        bytes.extend([0x01, 0x01, 0x01, b'c']);
+        bytes.extend(time);
+        bytes.extend(time);
+        bytes.push(0x01);
+        bytes.extend(id);
+
+        let Value::Stream(stream) = decode(STREAM_LISTPACKS_3_TYPE, &bytes) else {
+            panic!("expected a stream");
+        };
+        let first = StreamId::new(1, 1);
+        assert_eq!(
+            stream.iter().collect::<Vec<_>>(),
+            vec![(&first, &vec![(b"f".to_vec(), b"v".to_vec())])]
+        );
+        assert_eq!(stream.last_id(), first);
+        assert_eq!(stream.entries_added(), 1);
+
+        let group = stream.group(b"g").unwrap();
+        assert_eq!(group.last_id, first);
+        assert_eq!(group.entries_read, Some(1));
+        assert_eq!(
+            group.pending.get(&first),
+            Some(&PendingEntry {
+                consumer: b"c".to_vec(),
+                delivery_time: 1_700_000_000_000,
+                delivery_count: 1,
+            })
+        );
+        let consumer = &group.consumers[b"c".as_slice()];
+        assert_eq!(consumer.active_time, Some(1_700_000_000_000));
+        assert!(consumer.pending.contains(&first));
+    }
 }
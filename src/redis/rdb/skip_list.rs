@@ -0,0 +1,366 @@
+use std::mem;
+
+const MAX_LEVEL: usize = 32;
+const HEAD: usize = 0;
+
+/// An ordered set of keys with rank lookups, modelled on Redis' zskiplist: every link
+/// remembers how many nodes it spans, so ranks are found in O(log N) on the way down.
+/// Nodes live in an arena and refer to each other by index; index 0 is the header.
+#[derive(Debug, Clone)]
+pub struct SkipList<K> {
+    nodes: Vec<Node<K>>,
+    free: Vec<usize>,
+    tail: Option<usize>,
+    level: usize,
+    len: usize,
+    seed: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Node<K> {
+    key: Option<K>,
+    backward: Option<usize>,
+    levels: Vec<Level>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Level {
+    forward: Option<usize>,
+    span: usize,
+}
+
+impl<K: Ord> Default for SkipList<K> {
+    fn default() -> Self {
+        let head = Node {
+            key: None,
+            backward: None,
+            levels: vec![Level::default(); MAX_LEVEL],
+        };
+        Self {
+            nodes: vec![head],
+            free: Vec::new(),
+            tail: None,
+            level: 1,
+            len: 0,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+}
+
+impl<K: Ord> SkipList<K> {
+    /// Inserts `key`, which must not already be present.
+    pub fn insert(&mut self, key: K) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            rank[i] = if i + 1 == self.level { 0 } else { rank[i + 1] };
+            while let Some(next) = self.forward(x, i) {
+                if *self.key(next) >= key {
+                    break;
+                }
+                rank[i] += self.nodes[x].levels[i].span;
+                x = next;
+            }
+            update[i] = x;
+        }
+
+        let level = self.random_level();
+        if level > self.level {
+            for i in self.level..level {
+                self.nodes[HEAD].levels[i].span = self.len;
+            }
+            self.level = level;
+        }
+
+        let node = self.allocate(key, level);
+        for i in 0..level {
+            let previous = update[i];
+            let skipped = rank[0] - rank[i];
+            self.nodes[node].levels[i] = Level {
+                forward: self.nodes[previous].levels[i].forward,
+                span: self.nodes[previous].levels[i].span - skipped,
+            };
+            self.nodes[previous].levels[i] = Level {
+                forward: Some(node),
+                span: skipped + 1,
+            };
+        }
+        for (i, &previous) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[previous].levels[i].span += 1;
+        }
+
+        self.nodes[node].backward = (update[0] != HEAD).then_some(update[0]);
+        match self.forward(node, 0) {
+            Some(next) => self.nodes[next].backward = Some(node),
+            None => self.tail = Some(node),
+        }
+        self.len += 1;
+    }
+
+    /// Removes `key`, returning whether it was present.
+    pub fn remove(&mut self, key: &K) -> bool {
+        let update = self.predecessors(key);
+        let Some(target) = self
+            .forward(update[0], 0)
+            .filter(|&node| self.key(node) == key)
+        else {
+            return false;
+        };
+        self.unlink(target, &update);
+        true
+    }
+
+    /// Returns the number of keys strictly less than `key`, which is its rank when present.
+    pub fn rank(&self, key: &K) -> usize {
+        let mut rank = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.forward(x, i) {
+                if self.key(next) >= key {
+                    break;
+                }
+                rank += self.nodes[x].levels[i].span;
+                x = next;
+            }
+        }
+        rank
+    }
+
+    /// Iterates the keys at positions `start..end`, clamped to the length of the list.
+    pub fn range(&self, start: usize, end: usize) -> Iter<'_, K> {
+        let end = end.min(self.len);
+        if start >= end {
+            return Iter {
+                list: self,
+                front: None,
+                back: None,
+                remaining: 0,
+            };
+        }
+        Iter {
+            list: self,
+            front: self.node_at(start),
+            back: self.node_at(end - 1),
+            remaining: end - start,
+        }
+    }
+
+    pub fn pop_first(&mut self) -> Option<K> {
+        let first = self.forward(HEAD, 0)?;
+        Some(self.remove_node(first))
+    }
+
+    pub fn pop_last(&mut self) -> Option<K> {
+        let last = self.tail?;
+        Some(self.remove_node(last))
+    }
+
+    fn remove_node(&mut self, target: usize) -> K {
+        let update = self.predecessors(self.key(target));
+        self.unlink(target, &update)
+    }
+
+    /// Returns, for every level, the last node whose key is less than `key`.
+    fn predecessors(&self, key: &K) -> [usize; MAX_LEVEL] {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.forward(x, i) {
+                if self.key(next) >= key {
+                    break;
+                }
+                x = next;
+            }
+            update[i] = x;
+        }
+        update
+    }
+
+    fn unlink(&mut self, target: usize, update: &[usize; MAX_LEVEL]) -> K {
+        for (i, &previous) in update.iter().enumerate().take(self.level) {
+            if self.forward(previous, i) == Some(target) {
+                let removed = self.nodes[target].levels[i];
+                self.nodes[previous].levels[i] = Level {
+                    forward: removed.forward,
+                    span: self.nodes[previous].levels[i].span + removed.span - 1,
+                };
+            } else {
+                self.nodes[previous].levels[i].span -= 1;
+            }
+        }
+        let backward = self.nodes[target].backward;
+        match self.forward(target, 0) {
+            Some(next) => self.nodes[next].backward = backward,
+            None => self.tail = backward,
+        }
+        while self.level > 1 && self.forward(HEAD, self.level - 1).is_none() {
+            self.level -= 1;
+        }
+        self.len -= 1;
+        self.free.push(target);
+        mem::take(&mut self.nodes[target].key).unwrap()
+    }
+
+    /// Returns the node at the 0-based position `rank`.
+    fn node_at(&self, rank: usize) -> Option<usize> {
+        let target = rank + 1;
+        let mut traversed = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.forward(x, i) {
+                let span = self.nodes[x].levels[i].span;
+                if traversed + span > target {
+                    break;
+                }
+                traversed += span;
+                x = next;
+            }
+            if traversed == target {
+                return Some(x);
+            }
+        }
+        None
+    }
+
+    fn allocate(&mut self, key: K, level: usize) -> usize {
+        let node = Node {
+            key: Some(key),
+            backward: None,
+            levels: vec![Level::default(); level],
+        };
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Picks a level with Redis' 1/4 promotion probability, from an xorshift sequence.
+    fn random_level(&mut self) -> usize {
+        let mut level = 1;
+        loop {
+            self.seed ^= self.seed << 13;
+            self.seed ^= self.seed >> 7;
+            self.seed ^= self.seed << 17;
+            if level == MAX_LEVEL || self.seed & 3 != 0 {
+                return level;
+            }
+            level += 1;
+        }
+    }
+
+    fn forward(&self, node: usize, level: usize) -> Option<usize> {
+        self.nodes[node].levels[level].forward
+    }
+
+    fn key(&self, node: usize) -> &K {
+        self.nodes[node].key.as_ref().unwrap()
+    }
+}
+
+/// Iterates a run of consecutive keys from both ends.
+pub struct Iter<'a, K> {
+    list: &'a SkipList<K>,
+    front: Option<usize>,
+    back: Option<usize>,
+    remaining: usize,
+}
+
+impl<'a, K: Ord> Iterator for Iter<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.front?;
+        self.remaining -= 1;
+        self.front = self.list.forward(node, 0);
+        Some(self.list.key(node))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: Ord> DoubleEndedIterator for Iter<'_, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.back?;
+        self.remaining -= 1;
+        self.back = self.list.nodes[node].backward;
+        Some(self.list.key(node))
+    }
+}
+
+impl<K: Ord> ExactSizeIterator for Iter<'_, K> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::rdb::skip_list::SkipList;
+
+    #[test]
+    fn test_ranks_follow_inserts_and_removals() {
+        let mut list = SkipList::default();
+        for key in (0..1000).rev() {
+            list.insert(key * 2);
+        }
+        assert_eq!(list.range(0, usize::MAX).len(), 1000);
+        assert_eq!(list.rank(&0), 0);
+        assert_eq!(list.rank(&500), 250);
+        assert_eq!(list.rank(&501), 251);
+
+        for key in (0..1000).step_by(2) {
+            assert!(list.remove(&(key * 2)));
+        }
+        assert!(!list.remove(&0));
+        assert_eq!(list.range(0, usize::MAX).len(), 500);
+        assert_eq!(list.rank(&502), 125);
+        assert_eq!(list.pop_first(), Some(2));
+        assert_eq!(list.pop_last(), Some(1998));
+
+        let keys: Vec<_> = list.range(0, 3).copied().collect();
+        assert_eq!(keys, vec![6, 10, 14]);
+        let keys: Vec<_> = list.range(495, 600).rev().copied().collect();
+        assert_eq!(keys, vec![1994, 1990, 1986]);
+        assert_eq!(list.range(3, 3).count(), 0);
+    }
+
+    #[test]
+    fn test_matches_a_sorted_vec() {
+        let mut list = SkipList::default();
+        let mut expected: Vec<u32> = Vec::new();
+        let mut seed = 7u32;
+        for _ in 0..20_000 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let key = (seed >> 8) % 500;
+            match expected.binary_search(&key) {
+                Ok(position) => {
+                    assert!(list.remove(&key));
+                    expected.remove(position);
+                }
+                Err(position) => {
+                    list.insert(key);
+                    expected.insert(position, key);
+                }
+            }
+            assert_eq!(list.rank(&key), expected.partition_point(|x| *x < key));
+        }
+        let start = expected.len() / 3;
+        let keys: Vec<_> = list.range(start, start + 50).copied().collect();
+        assert_eq!(keys, expected[start..start + 50]);
+        let keys: Vec<_> = list.range(0, usize::MAX).rev().copied().collect();
+        let mut reversed = expected.clone();
+        reversed.reverse();
+        assert_eq!(keys, reversed);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::redis::rdb::dict::Dict;
+use crate::redis::rdb::skip_list::SkipList;
+use std::cmp::Ordering;
+
+/// A sorted set: a member→score map plus a skip list ordered by `(score, member)` that also
+/// answers rank queries.
+#[derive(Debug, Default, Clone)]
+pub struct SortedSet {
+    scores: Dict<f64>,
+    index: SkipList<(Score, Vec<u8>)>,
+}
+
+impl PartialEq for SortedSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.scores == other.scores
+    }
+}
+
+impl SortedSet {
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Sets the score of `member`, returning its previous score if it was already present.
+    pub fn insert(&mut self, member: Vec<u8>, score: f64) -> Option<f64> {
+        // -0.0 and 0.0 must be the same score for ordering purposes.
+        let score = if score == 0.0 { 0.0 } else { score };
+        let previous = self.scores.insert(member.clone(), score);
+        if let Some(previous) = previous {
+            self.index.remove(&(Score(previous), member.clone()));
+        }
+        self.index.insert((Score(score), member));
+        previous
+    }
+
+    pub fn remove(&mut self, member: &[u8]) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+        self.index.remove(&(Score(score), member.to_vec()));
+        Some(score)
+    }
+
+    /// Returns the 0-based position of `member` in ascending score order.
+    pub fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+        Some(self.index.rank(&(Score(score), member.to_vec())))
+    }
+
+    /// Iterates members in ascending `(score, member)` order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&[u8], f64)> {
+        self.range_by_rank(0, self.len())
+    }
+
+    /// Iterates the members at the 0-based positions `start..end` in ascending order.
+    pub fn range_by_rank(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> impl DoubleEndedIterator<Item = (&[u8], f64)> {
+        self.index
+            .range(start, end)
+            .map(|(score, member)| (member.as_slice(), score.0))
+    }
+
+    /// Returns about `count` members from `cursor` on, in the order of the member→score dict,
+    /// along with the cursor to continue from.
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(&[u8], f64)>) {
+        let (cursor, members) = self.scores.scan(cursor, count);
+        let members = members
+            .into_iter()
+            .map(|(member, score)| (member.as_slice(), *score))
+            .collect();
+        (cursor, members)
+    }
+
+    /// Returns the members whose score lies between `min` and `max`, in ascending order.
+    pub fn range_by_score(&self, min: &ScoreBound, max: &ScoreBound) -> Vec<(&[u8], f64)> {
+        let start = self.index.rank(&(Score(min.value), Vec::new()));
+        self.range_by_rank(start, self.len())
+            .skip_while(|(_, score)| min.exclusive && *score == min.value)
+            .take_while(|(_, score)| *score < max.value || (!max.exclusive && *score == max.value))
+            .collect()
+    }
+
+    /// Returns the members lexicographically between `min` and `max`, in ascending order.
+    /// Like Redis, this assumes all members share the same score.
+    pub fn range_by_lex(&self, min: &LexBound, max: &LexBound) -> Vec<(&[u8], f64)> {
+        self.iter()
+            .skip_while(|(member, _)| !min.is_below(member))
+            .take_while(|(member, _)| max.is_above(member))
+            .collect()
+    }
+
+    pub fn pop_min(&mut self) -> Option<(Vec<u8>, f64)> {
+        let (score, member) = self.index.pop_first()?;
+        self.scores.remove(&member);
+        Some((member, score.0))
+    }
+
+    pub fn pop_max(&mut self) -> Option<(Vec<u8>, f64)> {
+        let (score, member) = self.index.pop_last()?;
+        self.scores.remove(&member);
+        Some((member, score.0))
+    }
+}
+
+impl FromIterator<(Vec<u8>, f64)> for SortedSet {
+    fn from_iter<T: IntoIterator<Item = (Vec<u8>, f64)>>(iter: T) -> Self {
+        let mut sorted_set = SortedSet::default();
+        for (member, score) in iter {
+            sorted_set.insert(member, score);
+        }
+        sorted_set
+    }
+}
+
+/// A score that can be used as an ordered key; NaN is never stored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Score(f64);
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct ScoreBound {
+    pub value: f64,
+    pub exclusive: bool,
+}
+
+#[derive(Debug)]
+pub enum LexBound {
+    Min,
+    Max,
+    Inclusive(Vec<u8>),
+    Exclusive(Vec<u8>),
+}
+
+impl LexBound {
+    /// Whether `member` satisfies this bound used as the lower end of a range.
+    pub fn is_below(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Inclusive(value) => value.as_slice() <= member,
+            LexBound::Exclusive(value) => value.as_slice() < member,
+        }
+    }
+
+    /// Whether `member` satisfies this bound used as the upper end of a range.
+    pub fn is_above(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(value) => member <= value.as_slice(),
+            LexBound::Exclusive(value) => member < value.as_slice(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::rdb::sorted_set::{LexBound, ScoreBound, SortedSet};
+
+    fn sorted_set() -> SortedSet {
+        [
+            (b"c".to_vec(), 2.0),
+            (b"a".to_vec(), 1.0),
+            (b"b".to_vec(), 2.0),
+            (b"d".to_vec(), 3.0),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn test_order_and_rank() {
+        let mut sorted_set = sorted_set();
+        let members: Vec<_> = sorted_set.iter().map(|(member, _)| member).collect();
+        assert_eq!(members, vec![b"a", b"b", b"c", b"d"]);
+        assert_eq!(sorted_set.rank(b"c"), Some(2));
+
+        assert_eq!(sorted_set.insert(b"a".to_vec(), 4.0), Some(1.0));
+        assert_eq!(sorted_set.rank(b"a"), Some(3));
+        assert_eq!(sorted_set.pop_min(), Some((b"b".to_vec(), 2.0)));
+    }
+
+    #[test]
+    fn test_rank_and_range_by_rank_on_a_large_set() {
+        let count = 100_000;
+        let mut sorted_set: SortedSet = (0..count)
+            .map(|i| (format!("m{}", i).into_bytes(), (count - i) as f64))
+            .collect();
+        assert_eq!(sorted_set.rank(b"m0"), Some(count - 1));
+        assert_eq!(sorted_set.rank(b"m99999"), Some(0));
+        assert_eq!(sorted_set.rank(b"m40000"), Some(59_999));
+
+        let members: Vec<_> = sorted_set
+            .range_by_rank(50_000, 50_003)
+            .map(|(member, score)| (member.to_vec(), score))
+            .collect();
+        assert_eq!(
+            members,
+            vec![
+                (b"m49999".to_vec(), 50_001.0),
+                (b"m49998".to_vec(), 50_002.0),
+                (b"m49997".to_vec(), 50_003.0),
+            ]
+        );
+        let last: Vec<_> = sorted_set.range_by_rank(0, count).rev().take(1).collect();
+        assert_eq!(last, vec![(b"m0".as_slice(), count as f64)]);
+
+        for i in (0..count).step_by(2) {
+            sorted_set.remove(format!("m{}", i).as_bytes());
+        }
+        assert_eq!(sorted_set.len(), count / 2);
+        assert_eq!(sorted_set.rank(b"m1"), Some(count / 2 - 1));
+        assert_eq!(
+            sorted_set.insert(b"m1".to_vec(), 0.0),
+            Some((count - 1) as f64)
+        );
+        assert_eq!(sorted_set.rank(b"m1"), Some(0));
+    }
+
+    #[test]
+    fn test_range_by_score() {
+        let sorted_set = sorted_set();
+        let min = ScoreBound {
+            value: 1.0,
+            exclusive: true,
+        };
+        let max = ScoreBound {
+            value: f64::INFINITY,
+            exclusive: false,
+        };
+        let members: Vec<_> = sorted_set
+            .range_by_score(&min, &max)
+            .into_iter()
+            .map(|(member, _)| member)
+            .collect();
+        assert_eq!(members, vec![b"b", b"c", b"d"]);
+    }
+
+    #[test]
+    fn test_range_by_lex() {
+        let sorted_set = sorted_set();
+        let members: Vec<_> = sorted_set
+            .range_by_lex(
+                &LexBound::Exclusive(b"a".to_vec()),
+                &LexBound::Inclusive(b"c".to_vec()),
+            )
+            .into_iter()
+            .map(|(member, _)| member)
+            .collect();
+        assert_eq!(members, vec![b"b", b"c"]);
+    }
+}
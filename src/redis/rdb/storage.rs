@@ -1,77 +1,178 @@
-use crate::redis::rdb::read_database::read_databases;
-use crate::redis::rdb::ttl::Ttl;
-use crate::redis::rdb::write_database::write_database;
-use chrono::Utc;
-use std::collections::HashMap;
+use crate::redis::rdb::database::{Database, Entries};
+use crate::redis::rdb::read_database::{read_databases, read_rdb};
+use crate::redis::rdb::write_database::{encode_database, write_database, Databases};
 use std::fmt::Display;
 use std::path::Path;
+use std::time::Instant;
 
-#[derive(Default)]
+/// The logical databases, which clients select by index.
 pub struct RedisStorage {
-    storage: HashMap<String, (String, Ttl)>,
+    databases: Vec<Database>,
+    /// The database the next active expiry cycle starts with, so that every database gets its
+    /// turn even when the cycles run out of time.
+    next_expire_db: usize,
 }
 
 impl RedisStorage {
+    pub fn new(databases: usize) -> Self {
+        Self {
+            databases: (0..databases).map(|_| Database::default()).collect(),
+            next_expire_db: 0,
+        }
+    }
+
     pub fn restore_database(&mut self, path: &Path) -> Result<(), RedisStorageError> {
-        let db = read_databases(path).map_err(|e| RedisStorageError {
+        let databases = read_databases(path).map_err(|e| RedisStorageError {
             msg: format!("error restore database: {}", e),
         })?;
-        if let Some(db) = db {
-            self.storage = db;
+        self.load(databases).map_err(|e| RedisStorageError {
+            msg: format!("error restore database: {}", e),
+        })
+    }
+
+    pub fn backup_database(&mut self, path: &Path) -> Result<(), RedisStorageError> {
+        let data = self.databases_to_save();
+        write_database("0001", None, &data, path, false).map_err(|e| RedisStorageError {
+            msg: format!("error backup database: {}", e),
+        })
+    }
+
+    /// Encodes every database in the RDB format, for a replica to load.
+    pub fn snapshot(&mut self) -> Result<Vec<u8>, RedisStorageError> {
+        let data = self.databases_to_save();
+        encode_database("0001", None, &data, false).map_err(|e| RedisStorageError {
+            msg: format!("error encoding snapshot: {}", e),
+        })
+    }
+
+    /// Replaces every database with those of an RDB payload received from the master.
+    pub fn load_snapshot(&mut self, rdb: &[u8]) -> Result<(), RedisStorageError> {
+        let databases = read_rdb(&mut &rdb[..]).map_err(|e| RedisStorageError {
+            msg: format!("error loading snapshot: {}", e),
+        })?;
+        self.load(databases).map_err(|e| RedisStorageError {
+            msg: format!("error loading snapshot: {}", e),
+        })
+    }
+
+    fn load(&mut self, databases: Vec<(u32, Entries)>) -> Result<(), String> {
+        if let Some((number, _)) = databases
+            .iter()
+            .find(|(number, _)| *number as usize >= self.databases.len())
+        {
+            return Err(format!(
+                "database {} is out of range, only {} configured",
+                number,
+                self.databases.len()
+            ));
+        }
+        self.clear();
+        for (number, entries) in databases {
+            self.databases[number as usize].load(entries);
         }
         Ok(())
     }
 
-    pub fn get(&mut self, key: &str) -> Option<&str> {
-        let should_remove = match self.storage.get(key) {
-            None => return None,
-            Some((_, ttl)) => ttl.is_expired(),
-        };
+    fn databases_to_save(&mut self) -> Databases<'_> {
+        self.databases
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, database)| !database.is_empty())
+            .map(|(number, database)| (number as u32, database.entries()))
+            .collect()
+    }
+
+    /// How many databases there are, valid indexes going from 0 up to it.
+    pub fn len(&self) -> usize {
+        self.databases.len()
+    }
 
-        if should_remove {
-            self.storage.remove(key);
-            None
-        } else {
-            self.storage.get(key).map(|(v, _)| v.as_ref())
+    /// Returns the database at `index`, which must be in range.
+    pub fn database(&mut self, index: usize) -> &mut Database {
+        &mut self.databases[index]
+    }
+
+    /// Moves `key` with its expiration from one database to another. Returns false if it does
+    /// not exist in `source` or already exists in `destination`.
+    pub fn move_key(&mut self, key: &[u8], source: usize, destination: usize) -> bool {
+        if self.databases[destination].contains_key(key) {
+            return false;
         }
+        let Some((value, ttl)) = self.databases[source].take_entry(key) else {
+            return false;
+        };
+        self.databases[destination].put_entry(key.to_vec(), value, ttl);
+        true
     }
 
-    pub fn set(&mut self, key: String, value: String, px: Option<u64>) {
-        let ttl = px
-            .map(|v| Utc::now().timestamp_millis() as u64 + v)
-            .map_or(Ttl::None, Ttl::Milliseconds);
-        self.storage.insert(key, (value, ttl));
+    /// Copies the value of `source` with its expiration to `destination` in another database,
+    /// replacing any value there. Returns false if `source` does not exist.
+    pub fn copy_key(
+        &mut self,
+        source_db: usize,
+        source: &[u8],
+        destination_db: usize,
+        destination: &[u8],
+    ) -> bool {
+        let Some((value, ttl)) = self.databases[source_db].get_entry(source) else {
+            return false;
+        };
+        self.databases[destination_db].put_entry(destination.to_vec(), value, ttl);
+        true
     }
 
-    pub fn get_keys(&mut self) -> Vec<&str> {
-        self.remove_expired_keys();
-        self.storage.keys().map(|x| x.as_str()).collect()
+    /// Swaps the content of two databases, the keys clients watch staying with the index. The
+    /// `blocked` keys clients wait for in either database become ready if they exist there now.
+    pub fn swap(&mut self, first: usize, second: usize, blocked: &[(usize, Vec<u8>)]) {
+        if first == second {
+            return;
+        }
+        self.databases.swap(first, second);
+        let watched = std::mem::take(&mut self.databases[first].watched);
+        self.databases[first].watched =
+            std::mem::replace(&mut self.databases[second].watched, watched);
+        self.databases[first].touch_all();
+        self.databases[second].touch_all();
+        for (db, key) in blocked {
+            if (*db == first || *db == second) && self.databases[*db].contains_key(key) {
+                self.databases[*db].signal_ready(key);
+            }
+        }
     }
 
-    pub fn backup_database(&mut self, path: &Path) -> Result<(), RedisStorageError> {
-        self.remove_expired_keys();
-        let db = self
-            .storage
-            .iter()
-            .map(|(k, (v, ttl))| (k.as_str(), (v.as_str(), ttl)))
-            .collect();
-        let data = vec![(1, db)];
-        write_database("0001", None, &data, path, false).map_err(|e| RedisStorageError {
-            msg: format!("error backup database: {}", e),
-        })
+    pub fn clear(&mut self) {
+        self.databases.iter_mut().for_each(Database::clear);
     }
 
-    fn remove_expired_keys(&mut self) {
-        let to_delete: Vec<String> = self
-            .storage
-            .iter()
-            .filter(|(_, (_, ttl))| ttl.is_expired())
-            .map(|(k, _)| k.clone())
-            .collect();
+    /// A counter of the modifications made to any database, which only ever grows.
+    pub fn dirty(&self) -> u64 {
+        self.databases.iter().map(|database| database.dirty).sum()
+    }
+
+    /// Takes the keys that became ready in every database, along with the database index.
+    pub fn take_ready_keys(&mut self) -> Vec<(usize, Vec<u8>)> {
+        self.databases
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(index, database)| {
+                let keys = database.take_ready_keys();
+                keys.into_iter().map(move |key| (index, key))
+            })
+            .collect()
+    }
 
-        for key in to_delete {
-            self.storage.remove(&key);
+    /// Runs the active expiry cycle of each database in turn until `deadline`. Returns how many
+    /// keys were deleted.
+    pub fn active_expire_cycle(&mut self, deadline: Instant) -> usize {
+        let mut deleted = 0;
+        for _ in 0..self.databases.len() {
+            if Instant::now() >= deadline {
+                break;
+            }
+            deleted += self.databases[self.next_expire_db].active_expire_cycle(deadline);
+            self.next_expire_db = (self.next_expire_db + 1) % self.databases.len();
         }
+        deleted
     }
 }
 
@@ -85,3 +186,112 @@ impl Display for RedisStorageError {
         write!(f, "{}", self.msg)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::rdb::storage::RedisStorage;
+
+    #[test]
+    fn test_every_database_round_trips() {
+        let mut storage = RedisStorage::new(16);
+        let expire_at = u64::MAX / 2;
+        storage.database(0).set(b"a".to_vec(), b"0".to_vec(), None);
+        storage
+            .database(5)
+            .set(b"a".to_vec(), b"5".to_vec(), Some(expire_at));
+        storage
+            .database(15)
+            .set(b"b".to_vec(), b"15".to_vec(), None);
+
+        let path = std::env::temp_dir().join(format!("databases-{}.rdb", std::process::id()));
+        storage.backup_database(&path).unwrap();
+        let mut restored = RedisStorage::new(16);
+        restored.restore_database(&path).unwrap();
+        let mut too_few = RedisStorage::new(8);
+        assert!(too_few.restore_database(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            restored.database(0).get(b"a").unwrap(),
+            Some(b"0".as_slice())
+        );
+        assert_eq!(
+            restored.database(5).get(b"a").unwrap(),
+            Some(b"5".as_slice())
+        );
+        assert_eq!(
+            restored.database(5).get_expire_time(b"a"),
+            Some(Some(expire_at))
+        );
+        assert_eq!(
+            restored.database(15).get(b"b").unwrap(),
+            Some(b"15".as_slice())
+        );
+        assert!(restored.database(1).is_empty());
+    }
+
+    #[test]
+    fn test_move_keeps_an_existing_destination() {
+        let mut storage = RedisStorage::new(16);
+        storage.database(0).set(b"a".to_vec(), b"0".to_vec(), None);
+        storage.database(1).set(b"a".to_vec(), b"1".to_vec(), None);
+        assert!(!storage.move_key(b"a", 0, 1));
+        assert_eq!(
+            storage.database(0).get(b"a").unwrap(),
+            Some(b"0".as_slice())
+        );
+        assert_eq!(
+            storage.database(1).get(b"a").unwrap(),
+            Some(b"1".as_slice())
+        );
+
+        storage.database(1).remove(b"a");
+        assert!(storage.move_key(b"a", 0, 1));
+        assert!(!storage.database(0).contains_key(b"a"));
+        assert!(!storage.move_key(b"a", 0, 1));
+    }
+
+    #[test]
+    fn test_swap_keeps_watches_and_wakes_blocked_keys() {
+        let mut storage = RedisStorage::new(16);
+        storage.database(0).set(b"a".to_vec(), b"0".to_vec(), None);
+        storage.database(0).set(b"b".to_vec(), b"0".to_vec(), None);
+        let version = storage.database(1).watch(b"a");
+        storage.take_ready_keys();
+
+        let blocked = vec![(1, b"a".to_vec()), (1, b"c".to_vec()), (2, b"b".to_vec())];
+        storage.swap(0, 1, &blocked);
+        assert_eq!(storage.take_ready_keys(), vec![(1, b"a".to_vec())]);
+        assert_ne!(storage.database(1).version(b"a"), version);
+        assert_eq!(
+            storage.database(1).get(b"a").unwrap(),
+            Some(b"0".as_slice())
+        );
+        assert!(storage.database(0).is_empty());
+
+        let version = storage.database(1).version(b"a");
+        storage.database(0).set(b"a".to_vec(), b"1".to_vec(), None);
+        assert_eq!(storage.database(1).version(b"a"), version);
+        storage.database(1).set(b"a".to_vec(), b"1".to_vec(), None);
+        assert_ne!(storage.database(1).version(b"a"), version);
+    }
+
+    #[test]
+    fn test_clear_empties_every_database() {
+        let mut storage = RedisStorage::new(16);
+        let expire_at = u64::MAX / 2;
+        storage
+            .database(0)
+            .set(b"a".to_vec(), b"0".to_vec(), Some(expire_at));
+        storage.database(3).set(b"b".to_vec(), b"3".to_vec(), None);
+        let dirty = storage.dirty();
+
+        storage.clear();
+        assert_ne!(storage.dirty(), dirty);
+        for index in 0..storage.len() {
+            assert!(storage.database(index).is_empty());
+            assert_eq!(storage.database(index).scan(0, 100), (0, Vec::new()));
+        }
+        assert_eq!(storage.database(0).get_expire_time(b"a"), None);
+    }
+}
@@ -0,0 +1,360 @@
+use crate::redis::rdb::consumer_group::ConsumerGroup;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// The number of entries Redis packs in a single stream node. Approximate trimming only ever
+/// removes whole nodes.
+pub const STREAM_NODE_MAX_ENTRIES: usize = 100;
+
+pub type StreamFields = Vec<(Vec<u8>, Vec<u8>)>;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        Self { ms, seq }
+    }
+
+    /// Parses `<ms>-<seq>` or a bare `<ms>`, in which case the sequence is `default_seq`.
+    pub fn parse(value: &[u8], default_seq: u64) -> Option<Self> {
+        let value = std::str::from_utf8(value).ok()?;
+        match value.split_once('-') {
+            Some((ms, seq)) => Some(Self::new(parse_part(ms)?, parse_part(seq)?)),
+            None => Some(Self::new(parse_part(value)?, default_seq)),
+        }
+    }
+
+    pub fn next(&self) -> Option<Self> {
+        if self.seq < u64::MAX {
+            Some(Self::new(self.ms, self.seq + 1))
+        } else if self.ms < u64::MAX {
+            Some(Self::new(self.ms + 1, 0))
+        } else {
+            None
+        }
+    }
+
+    pub fn previous(&self) -> Option<Self> {
+        if self.seq > 0 {
+            Some(Self::new(self.ms, self.seq - 1))
+        } else if self.ms > 0 {
+            Some(Self::new(self.ms - 1, u64::MAX))
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for StreamId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+fn parse_part(value: &str) -> Option<u64> {
+    if value.is_empty() || !value.bytes().all(|x| x.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+pub enum TrimThreshold {
+    MaxLen(usize),
+    MinId(StreamId),
+}
+
+/// A MAXLEN or MINID trimming request, `limit` 0 meaning no limit.
+pub struct Trim {
+    pub threshold: TrimThreshold,
+    pub approximate: bool,
+    pub limit: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, StreamFields>,
+    last_id: StreamId,
+    max_deleted_id: StreamId,
+    entries_added: u64,
+    groups: BTreeMap<Vec<u8>, ConsumerGroup>,
+}
+
+impl Stream {
+    /// Rebuilds a stream with the bookkeeping saved next to its entries.
+    pub fn from_parts(
+        entries: BTreeMap<StreamId, StreamFields>,
+        last_id: StreamId,
+        max_deleted_id: StreamId,
+        entries_added: u64,
+    ) -> Self {
+        Self {
+            entries,
+            last_id,
+            max_deleted_id,
+            entries_added,
+            groups: BTreeMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The greatest ID ever added, which new IDs must exceed even if it has been deleted.
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    pub fn first_id(&self) -> Option<StreamId> {
+        self.entries.keys().next().copied()
+    }
+
+    pub fn max_deleted_id(&self) -> StreamId {
+        self.max_deleted_id
+    }
+
+    pub fn entries_added(&self) -> u64 {
+        self.entries_added
+    }
+
+    /// Appends an entry. The caller must have checked that `id` is greater than the last ID.
+    pub fn add(&mut self, id: StreamId, fields: StreamFields) {
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        self.entries_added += 1;
+    }
+
+    pub fn get(&self, id: &StreamId) -> Option<&StreamFields> {
+        self.entries.get(id)
+    }
+
+    pub fn last_entry(&self) -> Option<(&StreamId, &StreamFields)> {
+        self.entries.last_key_value()
+    }
+
+    /// Iterates the entries between `start` and `end`, both inclusive.
+    pub fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+    ) -> impl DoubleEndedIterator<Item = (&StreamId, &StreamFields)> {
+        if start <= end {
+            self.entries.range(start..=end)
+        } else {
+            self.entries.range(StreamId::MIN..StreamId::MIN)
+        }
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&StreamId, &StreamFields)> {
+        self.entries.iter()
+    }
+
+    pub fn remove(&mut self, id: &StreamId) -> bool {
+        if self.entries.remove(id).is_none() {
+            return false;
+        }
+        self.max_deleted_id = self.max_deleted_id.max(*id);
+        true
+    }
+
+    pub fn groups(&self) -> &BTreeMap<Vec<u8>, ConsumerGroup> {
+        &self.groups
+    }
+
+    pub fn group(&self, name: &[u8]) -> Option<&ConsumerGroup> {
+        self.groups.get(name)
+    }
+
+    pub fn group_mut(&mut self, name: &[u8]) -> Option<&mut ConsumerGroup> {
+        self.groups.get_mut(name)
+    }
+
+    pub fn create_group(&mut self, name: &[u8], group: ConsumerGroup) -> bool {
+        if self.groups.contains_key(name) {
+            return false;
+        }
+        self.groups.insert(name.to_vec(), group);
+        true
+    }
+
+    pub fn destroy_group(&mut self, name: &[u8]) -> bool {
+        self.groups.remove(name).is_some()
+    }
+
+    /// Delivers up to `count` entries (0 for all) newer than the group's last delivered ID to
+    /// `consumer`, adding them to the pending entries list unless `no_ack` is set.
+    pub fn deliver(
+        &mut self,
+        group: &[u8],
+        consumer: &[u8],
+        count: usize,
+        no_ack: bool,
+        now: u64,
+    ) -> Vec<(StreamId, StreamFields)> {
+        let Some(last_id) = self.groups.get(group).map(|group| group.last_id) else {
+            return Vec::new();
+        };
+        let count = if count == 0 { usize::MAX } else { count };
+        let entries: Vec<_> = match last_id.next() {
+            Some(start) => self
+                .range(start, StreamId::MAX)
+                .take(count)
+                .map(|(id, fields)| (*id, fields.clone()))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        for (id, _) in &entries {
+            let entries_read = self.groups[group].entries_read;
+            let entries_read = match entries_read {
+                Some(entries_read) if !self.has_tombstones_from(id) => Some(entries_read + 1),
+                _ => self.estimate_entries_read(id),
+            };
+            let group = self.groups.get_mut(group).unwrap();
+            group.entries_read = entries_read;
+            group.last_id = *id;
+            if !no_ack {
+                group.deliver(*id, consumer, now);
+            }
+        }
+
+        let group = self.groups.get_mut(group).unwrap();
+        let consumer = group.touch_consumer(consumer, now);
+        if !entries.is_empty() {
+            consumer.active_time = Some(now);
+        }
+        entries
+    }
+
+    /// How many entries the group is behind the stream, if it can be known.
+    pub fn lag(&self, group: &ConsumerGroup) -> Option<u64> {
+        if self.entries_added == 0 {
+            return Some(0);
+        }
+        let entries_read = match group.entries_read {
+            Some(entries_read) if !self.has_tombstones_from(&group.last_id) => Some(entries_read),
+            _ => self.estimate_entries_read(&group.last_id),
+        };
+        entries_read.map(|entries_read| self.entries_added.saturating_sub(entries_read))
+    }
+
+    /// Estimates how many entries were added up to `id`, which can only be known at the ends of
+    /// the stream when no entry was deleted in between.
+    pub fn estimate_entries_read(&self, id: &StreamId) -> Option<u64> {
+        if self.entries_added == 0 {
+            return Some(0);
+        }
+        if self.entries.is_empty() && *id <= self.last_id {
+            return Some(self.entries_added);
+        }
+        if *id == self.last_id {
+            return Some(self.entries_added);
+        }
+        if *id > self.last_id {
+            return None;
+        }
+
+        let first_id = self.first_id().unwrap_or(StreamId::MIN);
+        if self.max_deleted_id == StreamId::MIN || self.max_deleted_id < first_id {
+            let before_first = self.entries_added - self.len() as u64;
+            if *id < first_id {
+                return Some(before_first);
+            }
+            if *id == first_id {
+                return Some(before_first + 1);
+            }
+        }
+        None
+    }
+
+    fn has_tombstones_from(&self, start: &StreamId) -> bool {
+        !self.entries.is_empty()
+            && self.max_deleted_id != StreamId::MIN
+            && *start <= self.max_deleted_id
+    }
+
+    /// Evicts the oldest entries according to `trim` and returns how many were removed.
+    pub fn trim(&mut self, trim: &Trim) -> usize {
+        let mut count = match trim.threshold {
+            TrimThreshold::MaxLen(max_len) => self.len().saturating_sub(max_len),
+            TrimThreshold::MinId(min_id) => self.entries.range(..min_id).count(),
+        };
+        if trim.limit > 0 {
+            count = count.min(trim.limit);
+        }
+        if trim.approximate {
+            count -= count % STREAM_NODE_MAX_ENTRIES;
+        }
+
+        for _ in 0..count {
+            self.entries.pop_first();
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::rdb::stream::{Stream, StreamId, Trim, TrimThreshold};
+
+    fn stream(len: u64) -> Stream {
+        let mut stream = Stream::default();
+        for seq in 1..=len {
+            stream.add(StreamId::new(1, seq), vec![(b"f".to_vec(), b"v".to_vec())]);
+        }
+        stream
+    }
+
+    #[test]
+    fn test_parse_id() {
+        assert_eq!(StreamId::parse(b"5-3", 0), Some(StreamId::new(5, 3)));
+        assert_eq!(
+            StreamId::parse(b"5", u64::MAX),
+            Some(StreamId::new(5, u64::MAX))
+        );
+        assert_eq!(StreamId::parse(b"5-", 0), None);
+        assert_eq!(StreamId::parse(b"-5", 0), None);
+        assert_eq!(StreamId::parse(b"+5", 0), None);
+    }
+
+    #[test]
+    fn test_trim() {
+        let mut stream = stream(250);
+        let approximate = Trim {
+            threshold: TrimThreshold::MaxLen(20),
+            approximate: true,
+            limit: 0,
+        };
+        assert_eq!(stream.trim(&approximate), 200);
+        assert_eq!(stream.first_id(), Some(StreamId::new(1, 201)));
+
+        let exact = Trim {
+            threshold: TrimThreshold::MinId(StreamId::new(1, 245)),
+            approximate: false,
+            limit: 0,
+        };
+        assert_eq!(stream.trim(&exact), 44);
+        assert_eq!(stream.len(), 6);
+        assert_eq!(stream.last_id(), StreamId::new(1, 250));
+    }
+
+    #[test]
+    fn test_remove_keeps_last_id() {
+        let mut stream = stream(3);
+        assert!(stream.remove(&StreamId::new(1, 3)));
+        assert!(!stream.remove(&StreamId::new(1, 3)));
+        assert_eq!(stream.last_id(), StreamId::new(1, 3));
+        assert_eq!(stream.max_deleted_id(), StreamId::new(1, 3));
+        assert_eq!(stream.range(StreamId::MIN, StreamId::MAX).count(), 2);
+    }
+}
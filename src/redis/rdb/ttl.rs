@@ -1,6 +1,6 @@
-use chrono::{DateTime, Utc};
+use chrono::Utc;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Ttl {
     None,
     Seconds(u32),
@@ -8,15 +8,21 @@ pub enum Ttl {
 }
 
 impl Ttl {
+    /// The Unix time in milliseconds at which the key expires, if it does.
+    pub fn expire_time(&self) -> Option<u64> {
+        match self {
+            Ttl::None => None,
+            Ttl::Seconds(seconds) => Some(*seconds as u64 * 1000),
+            Ttl::Milliseconds(milliseconds) => Some(*milliseconds),
+        }
+    }
+
     pub fn is_expired(&self) -> bool {
+        let now = Utc::now().timestamp_millis() as u64;
         match self {
             Ttl::None => false,
-            Ttl::Seconds(seconds) => {
-                DateTime::from_timestamp_millis(*seconds as i64 * 1000).unwrap() <= Utc::now()
-            }
-            Ttl::Milliseconds(milliseconds) => {
-                DateTime::from_timestamp_millis(*milliseconds as i64).unwrap() <= Utc::now()
-            }
+            Ttl::Seconds(seconds) => *seconds as u64 * 1000 <= now,
+            Ttl::Milliseconds(milliseconds) => *milliseconds <= now,
         }
     }
 }
@@ -0,0 +1,44 @@
+use crate::redis::rdb::dict::{Dict, Set};
+use crate::redis::rdb::sorted_set::SortedSet;
+use crate::redis::rdb::stream::Stream;
+use std::collections::VecDeque;
+
+pub type List = VecDeque<Vec<u8>>;
+pub type Hash = Dict<Vec<u8>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(Vec<u8>),
+    List(List),
+    Hash(Hash),
+    Set(Set),
+    SortedSet(SortedSet),
+    Stream(Stream),
+}
+
+impl Value {
+    /// The name TYPE reports for the value.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Hash(_) => "hash",
+            Value::Set(_) => "set",
+            Value::SortedSet(_) => "zset",
+            Value::Stream(_) => "stream",
+        }
+    }
+
+    /// Collections are never stored empty: a key whose collection becomes empty is removed.
+    /// Streams are the exception and outlive their last entry, like in Redis.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Value::String(_) => false,
+            Value::List(list) => list.is_empty(),
+            Value::Hash(hash) => hash.is_empty(),
+            Value::Set(set) => set.is_empty(),
+            Value::SortedSet(sorted_set) => sorted_set.is_empty(),
+            Value::Stream(_) => false,
+        }
+    }
+}
@@ -1,78 +1,233 @@
-use crate::redis::rdb::constants::{AUX, EOF, EXPIRE_TIME, EXPIRE_TIME_MS, RESIZE_DB, SELECT_DB};
+use crate::redis::rdb::constants::{
+    AUX, EOF, EXPIRE_TIME, EXPIRE_TIME_MS, HASH_TYPE, LIST_TYPE, RESIZE_DB, SELECT_DB, SET_TYPE,
+    SORTED_SET_2_TYPE, STREAM_LISTPACKS_3_TYPE, STRING_TYPE,
+};
+use crate::redis::rdb::listpack::write_listpack;
+use crate::redis::rdb::stream::{Stream, StreamId, STREAM_NODE_MAX_ENTRIES};
 use crate::redis::rdb::ttl::Ttl;
-use crc_fast::checksum_file;
+use crate::redis::rdb::value::Value;
+use crc_fast::checksum;
 use crc_fast::CrcAlgorithm::Crc64Redis;
-use std::fs::File;
 use std::io::{Error, Write};
 use std::path::Path;
 
-type Database<'a> = Vec<(u32, Vec<(&'a str, (&'a str, &'a Ttl))>)>;
+pub(super) type Databases<'a> = Vec<(u32, Vec<(&'a [u8], (&'a Value, &'a Ttl))>)>;
 
 pub fn write_database(
     version: &str,
     metadata: Option<&Vec<(&str, &str)>>,
-    databases: &Database,
+    databases: &Databases,
     path: &Path,
     calculate_checksum: bool,
 ) -> Result<(), Error> {
-    let mut file = File::create(path)?;
+    let rdb = encode_database(version, metadata, databases, calculate_checksum)?;
+    std::fs::write(path, rdb)
+}
+
+/// Encodes the databases in the RDB format in memory, the way they are sent to a replica.
+pub fn encode_database(
+    version: &str,
+    metadata: Option<&Vec<(&str, &str)>>,
+    databases: &Databases,
+    calculate_checksum: bool,
+) -> Result<Vec<u8>, Error> {
+    let mut rdb = Vec::new();
 
-    file.write_all(b"REDIS")?;
-    file.write_all(version.as_bytes().split_at(4).0)?;
+    rdb.write_all(b"REDIS")?;
+    rdb.write_all(version.as_bytes().split_at(4).0)?;
     if let Some(metadata) = metadata {
         for (key, value) in metadata {
-            file.write_all(&[AUX])?;
-            write_string(&mut file, key)?;
-            write_string(&mut file, value)?;
+            rdb.write_all(&[AUX])?;
+            write_string(&mut rdb, key.as_bytes())?;
+            write_string(&mut rdb, value.as_bytes())?;
         }
     }
     for (number, data) in databases {
-        file.write_all(&[SELECT_DB])?;
-        write_length(&mut file, number)?;
+        rdb.write_all(&[SELECT_DB])?;
+        write_length(&mut rdb, number)?;
         let db_size = data.len() as u32;
-        let db_size_expire = data.iter().filter(|(_, (_, ttl))| ttl.is_expired()).count() as u32;
-        file.write_all(&[RESIZE_DB])?;
-        write_length(&mut file, &db_size)?;
-        write_length(&mut file, &db_size_expire)?;
+        let db_size_expire = data
+            .iter()
+            .filter(|(_, (_, ttl))| **ttl != Ttl::None)
+            .count() as u32;
+        rdb.write_all(&[RESIZE_DB])?;
+        write_length(&mut rdb, &db_size)?;
+        write_length(&mut rdb, &db_size_expire)?;
 
         for (key, (value, ttl)) in data {
             match ttl {
                 Ttl::Seconds(seconds) => {
-                    file.write_all(&[EXPIRE_TIME])?;
-                    file.write_all(&seconds.to_le_bytes())?;
-                    file.write_all(&[0])?;
-                    write_string(&mut file, key)?;
-                    write_string(&mut file, value)?;
+                    rdb.write_all(&[EXPIRE_TIME])?;
+                    rdb.write_all(&seconds.to_le_bytes())?;
                 }
                 Ttl::Milliseconds(milliseconds) => {
-                    file.write_all(&[EXPIRE_TIME_MS])?;
-                    file.write_all(&milliseconds.to_le_bytes())?;
-                    file.write_all(&[0])?;
-                    write_string(&mut file, key)?;
-                    write_string(&mut file, value)?;
-                }
-                Ttl::None => {
-                    file.write_all(&[0])?;
-                    write_string(&mut file, key)?;
-                    write_string(&mut file, value)?;
+                    rdb.write_all(&[EXPIRE_TIME_MS])?;
+                    rdb.write_all(&milliseconds.to_le_bytes())?;
                 }
+                Ttl::None => {}
             }
+            write_value(&mut rdb, key, value)?;
         }
     }
 
-    file.write_all(&[EOF])?;
+    rdb.write_all(&[EOF])?;
 
-    if calculate_checksum {
-        let checksum = checksum_file(Crc64Redis, path.to_str().unwrap(), None)?;
-        file.write_all(checksum.to_be_bytes().as_slice())?;
+    let checksum = if calculate_checksum {
+        checksum(Crc64Redis, &rdb)
     } else {
-        file.write_all(0u64.to_be_bytes().as_slice())?;
+        0
+    };
+    rdb.write_all(checksum.to_be_bytes().as_slice())?;
+
+    Ok(rdb)
+}
+
+fn write_value(writer: &mut impl Write, key: &[u8], value: &Value) -> Result<(), Error> {
+    match value {
+        Value::String(value) => {
+            writer.write_all(&[STRING_TYPE])?;
+            write_string(writer, key)?;
+            write_string(writer, value)
+        }
+        Value::List(list) => {
+            writer.write_all(&[LIST_TYPE])?;
+            write_string(writer, key)?;
+            write_length(writer, &(list.len() as u32))?;
+            for element in list {
+                write_string(writer, element)?;
+            }
+            Ok(())
+        }
+        Value::Set(set) => {
+            writer.write_all(&[SET_TYPE])?;
+            write_string(writer, key)?;
+            write_length(writer, &(set.len() as u32))?;
+            for member in set {
+                write_string(writer, member)?;
+            }
+            Ok(())
+        }
+        Value::SortedSet(sorted_set) => {
+            writer.write_all(&[SORTED_SET_2_TYPE])?;
+            write_string(writer, key)?;
+            write_length(writer, &(sorted_set.len() as u32))?;
+            for (member, score) in sorted_set.iter() {
+                write_string(writer, member)?;
+                writer.write_all(&score.to_le_bytes())?;
+            }
+            Ok(())
+        }
+        Value::Hash(hash) => {
+            writer.write_all(&[HASH_TYPE])?;
+            write_string(writer, key)?;
+            write_length(writer, &(hash.len() as u32))?;
+            for (field, value) in hash {
+                write_string(writer, field)?;
+                write_string(writer, value)?;
+            }
+            Ok(())
+        }
+        Value::Stream(stream) => {
+            writer.write_all(&[STREAM_LISTPACKS_3_TYPE])?;
+            write_string(writer, key)?;
+            write_stream(writer, stream)
+        }
+    }
+}
+
+/// Writes the entries in listpack nodes keyed by their first ID, each entry carrying its own
+/// fields and its ID as a difference from the node's, followed by the stream bookkeeping.
+fn write_stream(writer: &mut impl Write, stream: &Stream) -> Result<(), Error> {
+    let entries: Vec<_> = stream.iter().collect();
+    let nodes = entries.chunks(STREAM_NODE_MAX_ENTRIES);
+    write_length(writer, &(nodes.len() as u32))?;
+    for node in nodes {
+        let master_id = *node[0].0;
+        write_string(writer, &raw_stream_id(&master_id))?;
+
+        // count, deleted, master fields count and the master entry terminator
+        let mut items = vec![
+            integer(node.len() as i64),
+            integer(0),
+            integer(0),
+            integer(0),
+        ];
+        for (id, fields) in node {
+            items.push(integer(0));
+            items.push(integer(id.ms.wrapping_sub(master_id.ms) as i64));
+            items.push(integer(id.seq.wrapping_sub(master_id.seq) as i64));
+            items.push(integer(fields.len() as i64));
+            for (field, value) in fields.iter() {
+                items.push(field.clone());
+                items.push(value.clone());
+            }
+            items.push(integer(fields.len() as i64 * 2 + 4));
+        }
+        write_string(writer, &write_listpack(&items))?;
     }
 
+    write_long_length(writer, stream.len() as u64)?;
+    write_stream_id(writer, &stream.last_id())?;
+    write_stream_id(writer, &stream.first_id().unwrap_or(StreamId::MIN))?;
+    write_stream_id(writer, &stream.max_deleted_id())?;
+    write_long_length(writer, stream.entries_added())?;
+
+    write_length(writer, &(stream.groups().len() as u32))?;
+    for (name, group) in stream.groups() {
+        write_string(writer, name)?;
+        write_stream_id(writer, &group.last_id)?;
+        write_long_length(writer, group.entries_read.unwrap_or(u64::MAX))?;
+
+        write_long_length(writer, group.pending.len() as u64)?;
+        for (id, entry) in &group.pending {
+            writer.write_all(&raw_stream_id(id))?;
+            writer.write_all(&entry.delivery_time.to_le_bytes())?;
+            write_long_length(writer, entry.delivery_count)?;
+        }
+
+        write_long_length(writer, group.consumers.len() as u64)?;
+        for (name, consumer) in &group.consumers {
+            write_string(writer, name)?;
+            writer.write_all(&consumer.seen_time.to_le_bytes())?;
+            let active_time = consumer.active_time.map_or(-1, |time| time as i64);
+            writer.write_all(&active_time.to_le_bytes())?;
+            write_long_length(writer, consumer.pending.len() as u64)?;
+            for id in &consumer.pending {
+                writer.write_all(&raw_stream_id(id))?;
+            }
+        }
+    }
     Ok(())
 }
 
-fn write_length(writer: &mut File, length: &u32) -> Result<(), Error> {
+/// Encodes an ID as 16 big-endian bytes, the way stream nodes and pending entries are keyed.
+fn raw_stream_id(id: &StreamId) -> [u8; 16] {
+    let mut raw = [0u8; 16];
+    raw[..8].copy_from_slice(&id.ms.to_be_bytes());
+    raw[8..].copy_from_slice(&id.seq.to_be_bytes());
+    raw
+}
+
+fn integer(value: i64) -> Vec<u8> {
+    value.to_string().into_bytes()
+}
+
+fn write_stream_id(writer: &mut impl Write, id: &StreamId) -> Result<(), Error> {
+    write_long_length(writer, id.ms)?;
+    write_long_length(writer, id.seq)
+}
+
+fn write_long_length(writer: &mut impl Write, length: u64) -> Result<(), Error> {
+    match u32::try_from(length) {
+        Ok(length) => write_length(writer, &length),
+        Err(_) => {
+            writer.write_all(&[0x81])?;
+            writer.write_all(&length.to_be_bytes())
+        }
+    }
+}
+
+fn write_length(writer: &mut impl Write, length: &u32) -> Result<(), Error> {
     match length {
         0..64 => writer.write_all(&[*length as u8]),
         64..16384 => {
@@ -95,7 +250,7 @@ fn write_length(writer: &mut File, length: &u32) -> Result<(), Error> {
     }
 }
 
-fn write_string(writer: &mut File, string: &str) -> Result<(), Error> {
+fn write_string(writer: &mut impl Write, string: &[u8]) -> Result<(), Error> {
     write_length(writer, &(string.len() as u32))?;
-    writer.write_all(string.as_bytes())
+    writer.write_all(string)
 }
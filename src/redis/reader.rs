@@ -1,173 +1,411 @@
-use crate::redis::core::ReadResp;
-use mio::net::TcpStream;
-use std::io::{BufRead, BufReader};
-use std::str::FromStr;
+use crate::redis::connection::Connection;
+use crate::redis::core::{Frame, ReadResp, WriteResp};
+use std::mem;
 use thiserror::Error;
 
-impl ReadResp for TcpStream {
+/// The largest bulk string a client may send, as Redis' default proto-max-bulk-len.
+const MAX_BULK_LENGTH: i64 = 512 * 1024 * 1024;
+/// The largest number of elements of an array a client may send.
+const MAX_MULTIBULK_LENGTH: usize = 1024 * 1024;
+
+impl ReadResp for Connection {
     type Error = MessageReaderError;
-    fn read_resp(&self) -> Result<Vec<String>, MessageReaderError> {
-        let reader = BufReader::with_capacity(10, self);
-        read_message(reader)
+    fn read_resp(&mut self) -> Result<Vec<Frame>, MessageReaderError> {
+        self.receive()?;
+        let mut frames = Vec::new();
+        loop {
+            match self.reader_mut().next_frame() {
+                Ok(Some(frame)) => frames.push(frame),
+                Ok(None) => return Ok(frames),
+                Err(e @ MessageReaderError::Io(_)) => return Err(e),
+                Err(e) => {
+                    // Like Redis, tell the client why it is about to be disconnected.
+                    self.write_error(format!("ERR {}", e))?;
+                    return Err(e);
+                }
+            }
+        }
     }
 }
 
-fn read_message(reader: impl BufRead) -> Result<Vec<String>, MessageReaderError> {
-    let mut lines = Vec::new();
-    let mut result_size = 1;
-    let mut previous_marker = None;
-    for (i, line) in reader.lines().enumerate() {
-        if i == 0 {
-            match RespType::from_str(line?.as_str())? {
-                RespType::Array(size) => {
-                    result_size = size;
-                    previous_marker = Some(RespType::Array(size));
+/// Incremental RESP parser.
+///
+/// Bytes are appended with [`MessageReader::extend`] as they arrive from the socket and
+/// complete frames are taken out with [`MessageReader::next_message`]. A frame that is
+/// split across several reads is resumed from where the parser stopped instead of being
+/// parsed again from the beginning.
+#[derive(Default)]
+pub struct MessageReader {
+    buffer: Vec<u8>,
+    position: usize,
+    array_size: Option<usize>,
+    bulk_size: Option<usize>,
+    items: Vec<Vec<u8>>,
+    /// How many bytes of the frame being parsed were consumed.
+    frame_size: usize,
+}
+
+impl MessageReader {
+    pub fn extend(&mut self, data: &[u8]) {
+        if self.position > 0 {
+            self.buffer.drain(..self.position);
+            self.position = 0;
+        }
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Returns the next complete frame, or `None` if more bytes are needed.
+    pub fn next_message(&mut self) -> Result<Option<Vec<Vec<u8>>>, MessageReaderError> {
+        Ok(self.next_frame()?.map(|(message, _)| message))
+    }
+
+    /// Returns the next complete frame along with its size in bytes, or `None` if more bytes
+    /// are needed.
+    pub fn next_frame(&mut self) -> Result<Option<Frame>, MessageReaderError> {
+        loop {
+            if let Some(size) = self.bulk_size {
+                let end = self.position + size;
+                if self.buffer.len() < end + 2 {
+                    return Ok(None);
+                }
+                if &self.buffer[end..end + 2] != b"\r\n" {
+                    return Err(MessageReaderError::InvalidBulkStringFormat);
+                }
+                let value = self.buffer[self.position..end].to_vec();
+                self.position = end + 2;
+                self.frame_size += size + 2;
+                self.bulk_size = None;
+                if let Some(frame) = self.push(value) {
+                    return Ok(Some(frame));
+                }
+                continue;
+            }
+
+            let line = match self.read_line() {
+                None => return Ok(None),
+                Some(line) => line,
+            };
+            let in_array = self.array_size.is_some();
+            match RespType::try_from(line.as_slice()).map_err(|e| unexpected(e, in_array))? {
+                RespType::Array(size) if self.array_size.is_none() => {
+                    if size > MAX_MULTIBULK_LENGTH {
+                        return Err(MessageReaderError::InvalidMultibulkLength);
+                    }
+                    if size == 0 {
+                        // An empty frame still takes bytes, which the replication offset counts.
+                        return Ok(Some((Vec::new(), mem::take(&mut self.frame_size))));
+                    }
+                    self.array_size = Some(size);
                 }
                 RespType::BulkString(size) => {
-                    if size == -1 {
-                        return Err(MessageReaderError::UnknownDataType);
-                    } else {
-                        previous_marker = Some(RespType::BulkString(size));
+                    if !(0..=MAX_BULK_LENGTH).contains(&size) {
+                        return Err(MessageReaderError::InvalidBulkLength);
+                    }
+                    self.bulk_size = Some(size as usize);
+                }
+                RespType::Integer(s) | RespType::SimpleString(s) => {
+                    if let Some(frame) = self.push(s) {
+                        return Ok(Some(frame));
                     }
                 }
-                RespType::Integer(s) => lines.push(s),
-                RespType::SimpleString(s) => lines.push(s),
-                _ => return Err(MessageReaderError::UnknownDataType),
+                _ => {
+                    return Err(unexpected(
+                        MessageReaderError::UnknownDataType(line[0]),
+                        in_array,
+                    ))
+                }
             }
-            continue;
         }
+    }
 
-        if lines.len() >= result_size {
-            break;
+    /// Returns the RDB file a master sends after FULLRESYNC, a bulk string without the
+    /// trailing CRLF, or `None` if more bytes are needed.
+    pub fn next_rdb(&mut self) -> Result<Option<Vec<u8>>, MessageReaderError> {
+        let rest = &self.buffer[self.position..];
+        let Some(end) = rest.windows(2).position(|x| x == b"\r\n") else {
+            return Ok(None);
+        };
+        let size = match RespType::try_from(&rest[..end])? {
+            RespType::BulkString(size) if size >= 0 => size as usize,
+            _ => return Err(MessageReaderError::InvalidBulkStringFormat),
+        };
+        let start = end + 2;
+        if rest.len() < start + size {
+            return Ok(None);
         }
+        let rdb = rest[start..start + size].to_vec();
+        self.position += start + size;
+        Ok(Some(rdb))
+    }
 
-        if let Some(RespType::BulkString(size)) = previous_marker {
-            match line {
-                Err(e) => return Err(e.into()),
-                Ok(line) => {
-                    if line.len() != size as usize {
-                        return Err(MessageReaderError::InvalidBulkStringFormat);
-                    }
-                    lines.push(line);
-                    previous_marker = None;
-                }
-            }
-            continue;
-        }
+    fn read_line(&mut self) -> Option<Vec<u8>> {
+        let rest = &self.buffer[self.position..];
+        let end = rest.windows(2).position(|x| x == b"\r\n")?;
+        let line = rest[..end].to_vec();
+        self.position += end + 2;
+        self.frame_size += end + 2;
+        Some(line)
+    }
 
-        match RespType::from_str(line?.as_str())? {
-            RespType::BulkString(size) => {
-                if size == -1 {
-                    return Err(MessageReaderError::UnknownDataType);
-                } else {
-                    previous_marker = Some(RespType::BulkString(size));
-                }
+    fn push(&mut self, value: Vec<u8>) -> Option<Frame> {
+        self.items.push(value);
+        match self.array_size {
+            Some(size) if self.items.len() < size => None,
+            _ => {
+                self.array_size = None;
+                Some((mem::take(&mut self.items), mem::take(&mut self.frame_size)))
             }
-            RespType::Integer(s) => lines.push(s),
-            RespType::SimpleString(s) => lines.push(s),
-            _ => return Err(MessageReaderError::UnknownDataType),
         }
     }
-    Ok(lines)
 }
 
 enum RespType {
-    SimpleString(String),
+    SimpleString(Vec<u8>),
     BulkString(i64),
     Array(usize),
     Error,
-    Integer(String),
+    Integer(Vec<u8>),
 }
 
-impl FromStr for RespType {
-    type Err = MessageReaderError;
+impl TryFrom<&[u8]> for RespType {
+    type Error = MessageReaderError;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut chars = s.chars();
+    fn try_from(s: &[u8]) -> Result<Self, MessageReaderError> {
+        let (marker, rest) = s
+            .split_first()
+            .ok_or(MessageReaderError::UnknownDataType(b' '))?;
 
-        match chars.next() {
-            Some('+') => Ok(RespType::SimpleString(chars.as_str().to_string())),
-            Some('$') => {
-                let size = chars
-                    .as_str()
-                    .parse::<i64>()
-                    .map_err(|_| MessageReaderError::InvalidBulkStringFormat)?;
+        match marker {
+            b'+' => Ok(RespType::SimpleString(rest.to_vec())),
+            b'$' => {
+                let size = std::str::from_utf8(rest)
+                    .ok()
+                    .and_then(|x| x.parse::<i64>().ok())
+                    .ok_or(MessageReaderError::InvalidBulkLength)?;
                 Ok(RespType::BulkString(size))
             }
-            Some('-') => Ok(RespType::Error),
-            Some(':') => Ok(RespType::Integer(chars.as_str().to_string())),
-            Some('*') => {
-                let size = chars
-                    .as_str()
-                    .parse::<usize>()
-                    .map_err(|_| MessageReaderError::InvalidArrayFormat)?;
+            b'-' => Ok(RespType::Error),
+            b':' => Ok(RespType::Integer(rest.to_vec())),
+            b'*' => {
+                let size = std::str::from_utf8(rest)
+                    .ok()
+                    .and_then(|x| x.parse::<usize>().ok())
+                    .ok_or(MessageReaderError::InvalidMultibulkLength)?;
                 Ok(RespType::Array(size))
             }
-            _ => Err(MessageReaderError::UnknownDataType),
+            _ => Err(MessageReaderError::UnknownDataType(*marker)),
         }
     }
 }
 
+/// Names the type a request element should have had, like Redis does, when it has another one.
+fn unexpected(error: MessageReaderError, in_array: bool) -> MessageReaderError {
+    match error {
+        MessageReaderError::UnknownDataType(got) if in_array => {
+            MessageReaderError::UnexpectedType(b'$', got)
+        }
+        error => error,
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum MessageReaderError {
     #[error("connection error")]
     Io(#[from] std::io::Error),
-    #[error("invalid RESP bulk string format")]
+    #[error("Protocol error: invalid bulk string format")]
     InvalidBulkStringFormat,
-    #[error("invalid RESP array format")]
-    InvalidArrayFormat,
-    #[error("unknown RESP data type")]
-    UnknownDataType,
+    #[error("Protocol error: unknown type byte '{}'", char::from(*.0))]
+    UnknownDataType(u8),
+    #[error("Protocol error: expected '{}', got '{}'", char::from(*.0), char::from(*.1))]
+    UnexpectedType(u8, u8),
+    #[error("Protocol error: invalid bulk length")]
+    InvalidBulkLength,
+    #[error("Protocol error: invalid multibulk length")]
+    InvalidMultibulkLength,
 }
 #[cfg(test)]
 mod tests {
-    use crate::redis::reader::read_message;
-    use std::io;
-    use std::string::String;
+    use crate::redis::connection::Connection;
+    use crate::redis::core::ReadResp;
+    use crate::redis::reader::{MessageReader, MessageReaderError};
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    fn read_message(data: &[u8]) -> Option<Vec<Vec<u8>>> {
+        let mut reader = MessageReader::default();
+        reader.extend(data);
+        reader.next_message().unwrap()
+    }
 
     #[test]
     fn test_read_integer() {
-        assert_eq!(
-            read_message(io::Cursor::new(b":1000\r\n")).unwrap(),
-            vec!["1000".to_string()]
-        );
+        assert_eq!(read_message(b":1000\r\n").unwrap(), vec![b"1000".to_vec()]);
     }
 
     #[test]
     fn test_read_simple_string() {
-        assert_eq!(
-            read_message(io::Cursor::new(b"+OK\r\n")).unwrap(),
-            vec!["OK".to_string()]
-        );
+        assert_eq!(read_message(b"+OK\r\n").unwrap(), vec![b"OK".to_vec()]);
     }
 
     #[test]
     fn test_read_bulk_string() {
         assert_eq!(
-            read_message(io::Cursor::new(b"$5\r\nhello\r\n")).unwrap(),
-            vec!["hello".to_string()]
+            read_message(b"$5\r\nhello\r\n").unwrap(),
+            vec![b"hello".to_vec()]
         );
     }
 
     #[test]
     fn test_read_empty_bulk_string() {
+        assert_eq!(read_message(b"$0\r\n\r\n").unwrap(), vec![b"".to_vec()]);
+    }
+
+    #[test]
+    fn test_read_binary_bulk_string() {
         assert_eq!(
-            read_message(io::Cursor::new(b"$0\r\n\r\n")).unwrap(),
-            vec!["".to_string()]
+            read_message(b"$6\r\na\r\n\xff\x00b\r\n").unwrap(),
+            vec![b"a\r\n\xff\x00b".to_vec()]
         );
     }
 
     #[test]
     fn test_read_array() {
         assert_eq!(
-            read_message(io::Cursor::new(b"*2\r\n$4\r\nECHO\r\n$5\r\nmango\r\n")).unwrap(),
-            vec!["ECHO".to_string(), "mango".to_string()]
+            read_message(b"*2\r\n$4\r\nECHO\r\n$5\r\nmango\r\n").unwrap(),
+            vec![b"ECHO".to_vec(), b"mango".to_vec()]
         );
     }
 
     #[test]
     fn test_read_empty_stream() {
-        let expected: Vec<String> = Vec::new();
-        assert_eq!(read_message(io::Cursor::new(b"")).unwrap(), expected);
+        assert_eq!(read_message(b""), None);
+    }
+
+    #[test]
+    fn test_read_pipelined_messages() {
+        let mut reader = MessageReader::default();
+        reader.extend(b"*1\r\n$4\r\nPING\r\n*2\r\n$4\r\nECHO\r\n$2\r\nhi\r\n");
+        assert_eq!(reader.next_message().unwrap(), Some(vec![b"PING".to_vec()]));
+        assert_eq!(
+            reader.next_message().unwrap(),
+            Some(vec![b"ECHO".to_vec(), b"hi".to_vec()])
+        );
+        assert_eq!(reader.next_message().unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_rdb_followed_by_message() {
+        let mut reader = MessageReader::default();
+        reader.extend(b"+FULLRESYNC id 0\r\n$4\r\nRE");
+        assert_eq!(
+            reader.next_message().unwrap(),
+            Some(vec![b"FULLRESYNC id 0".to_vec()])
+        );
+        assert_eq!(reader.next_rdb().unwrap(), None);
+        reader.extend(b"DI*1\r\n$4\r\nPING\r\n");
+        assert_eq!(reader.next_rdb().unwrap(), Some(b"REDI".to_vec()));
+        assert_eq!(reader.next_message().unwrap(), Some(vec![b"PING".to_vec()]));
+    }
+
+    #[test]
+    fn test_read_frame_sizes() {
+        let mut reader = MessageReader::default();
+        reader.extend(b"*1\r\n$4\r\nPING\r\n*2\r\n$4\r\nEC");
+        assert_eq!(
+            reader.next_frame().unwrap(),
+            Some((vec![b"PING".to_vec()], 14))
+        );
+        assert_eq!(reader.next_frame().unwrap(), None);
+        reader.extend(b"HO\r\n$2\r\nhi\r\n");
+        assert_eq!(
+            reader.next_frame().unwrap(),
+            Some((vec![b"ECHO".to_vec(), b"hi".to_vec()], 22))
+        );
+    }
+
+    #[test]
+    fn test_read_empty_array_as_empty_frame() {
+        let mut reader = MessageReader::default();
+        reader.extend(b"*0\r\n*1\r\n$4\r\nPING\r\n");
+        assert_eq!(reader.next_frame().unwrap(), Some((vec![], 4)));
+        assert_eq!(
+            reader.next_frame().unwrap(),
+            Some((vec![b"PING".to_vec()], 14))
+        );
+    }
+
+    #[test]
+    fn test_reject_oversized_lengths() {
+        let mut reader = MessageReader::default();
+        reader.extend(b"*1\r\n$536870913\r\n");
+        assert!(matches!(
+            reader.next_frame(),
+            Err(MessageReaderError::InvalidBulkLength)
+        ));
+
+        let mut reader = MessageReader::default();
+        reader.extend(b"*1048577\r\n");
+        assert!(matches!(
+            reader.next_frame(),
+            Err(MessageReaderError::InvalidMultibulkLength)
+        ));
+
+        let mut reader = MessageReader::default();
+        reader.extend(b"*1048576\r\n$536870912\r\n");
+        assert_eq!(reader.next_frame().unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_partial_message() {
+        let mut reader = MessageReader::default();
+        reader.extend(b"*2\r\n$4\r\nECHO\r\n$5\r\nman");
+        assert_eq!(reader.next_message().unwrap(), None);
+        reader.extend(b"go\r");
+        assert_eq!(reader.next_message().unwrap(), None);
+        reader.extend(b"\n");
+        assert_eq!(
+            reader.next_message().unwrap(),
+            Some(vec![b"ECHO".to_vec(), b"mango".to_vec()])
+        );
+    }
+
+    #[test]
+    fn test_protocol_errors_read_like_redis() {
+        for (data, expected) in [
+            (
+                &b"*1\r\n$abc\r\n"[..],
+                "Protocol error: invalid bulk length",
+            ),
+            (b"*1\r\n$-1\r\n", "Protocol error: invalid bulk length"),
+            (b"*x\r\n", "Protocol error: invalid multibulk length"),
+            (b"*1\r\n*1\r\n", "Protocol error: expected '$', got '*'"),
+            (b"!1\r\n", "Protocol error: unknown type byte '!'"),
+        ] {
+            let mut reader = MessageReader::default();
+            reader.extend(data);
+            let error = reader.next_frame().unwrap_err();
+            assert_eq!(error.to_string(), expected, "{data:?}");
+        }
+    }
+
+    #[test]
+    fn test_protocol_errors_are_replied_before_closing() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        stream.set_nonblocking(true).unwrap();
+        let mut connection = Connection::new(mio::net::TcpStream::from_std(stream), 0);
+
+        client.write_all(b"*1\r\n$x\r\n").unwrap();
+        // Reads return nothing until the bytes went through the loopback interface.
+        while let Ok(frames) = connection.read_resp() {
+            assert!(frames.is_empty());
+        }
+        connection.send_pending().unwrap();
+        drop(connection);
+
+        let mut reply = String::new();
+        client.read_to_string(&mut reply).unwrap();
+        assert_eq!(reply, "-ERR Protocol error: invalid bulk length\r\n");
     }
 }
@@ -1,14 +1,31 @@
 use crate::redis::client::TcpClient;
+use crate::redis::connection::Connection;
 use crate::redis::core::{Configuration, RequestHandler};
 use crate::redis::rdb::RedisStorage;
 use mio::net::TcpListener;
-use mio::{Events, Interest, Poll, Token};
+use mio::{Events, Interest, Poll, Registry, Token};
 use std::collections::HashMap;
+use std::io::{ErrorKind, Write};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::rc::Rc;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 
 const LISTENER_TOKEN: Token = Token(0);
+/// The connection to the master this server replicates, if it is a replica.
+const MASTER_TOKEN: Token = Token(1);
+/// How often expired keys are actively deleted, and how long each cycle may take at most.
+const ACTIVE_EXPIRE_PERIOD: Duration = Duration::from_millis(100);
+const ACTIVE_EXPIRE_TIME_LIMIT: Duration = Duration::from_millis(25);
+
+/// The connection to the master once the handshake is done, along with the dataset to start
+/// from.
+struct MasterLink {
+    connection: Connection,
+    rdb: Vec<u8>,
+    /// The replication offset the dataset is at.
+    offset: u64,
+}
 
 pub struct Server {
     configuration: Rc<Configuration>,
@@ -23,14 +40,20 @@ impl Server {
 
     pub fn run(&mut self) {
         log::info!("Starting server");
-        let storage = self.create_storage();
-        let mut request_handler = RequestHandler::new(storage, self.configuration.clone());
+        let mut storage = self.create_storage();
 
+        let mut master = None;
         if let Some(addr) = self.configuration.replicaof() {
-            if !self.replicaof_handshake(addr) {
+            let Some(link) = self.replicaof_handshake(addr) else {
+                return;
+            };
+            if let Err(e) = storage.load_snapshot(&link.rdb) {
+                log::error!("error loading snapshot from master: {}", e);
                 return;
             }
+            master = Some(link);
         }
+        let mut request_handler = RequestHandler::new(storage, self.configuration.clone());
 
         let mut poll = Poll::new().unwrap();
         let addr = SocketAddr::new(
@@ -45,39 +68,106 @@ impl Server {
 
         let mut events = Events::with_capacity(1024);
         let mut connections = HashMap::new();
-        let mut next_token = Token(1);
+        let mut next_token = Token(MASTER_TOKEN.0 + 1);
+        let mut next_expire_cycle = Instant::now() + ACTIVE_EXPIRE_PERIOD;
+
+        if let Some(MasterLink {
+            mut connection,
+            offset,
+            ..
+        }) = master
+        {
+            request_handler.set_master_offset(offset);
+            poll.registry()
+                .register(&mut connection, MASTER_TOKEN, Interest::READABLE)
+                .unwrap();
+            connections.insert(MASTER_TOKEN, connection);
+            // The master may have streamed writes along with the RDB file, which were read
+            // already and will not make the socket readable again.
+            serve(
+                poll.registry(),
+                &mut connections,
+                &mut request_handler,
+                MASTER_TOKEN,
+                true,
+                false,
+            );
+        }
 
         loop {
-            poll.poll(&mut events, None).unwrap();
+            // Wake up in time for the next expire cycle or to answer the first blocked client
+            // whose timeout elapses.
+            let mut timeout = next_expire_cycle.saturating_duration_since(Instant::now());
+            if let Some(blocked_timeout) = request_handler.next_timeout() {
+                timeout = timeout.min(blocked_timeout);
+            }
+            poll.poll(&mut events, Some(timeout)).unwrap();
 
             for event in events.iter() {
                 match event.token() {
-                    LISTENER_TOKEN => {
-                        let (mut stream, _) = listener.accept().unwrap();
+                    LISTENER_TOKEN => loop {
+                        let stream = match listener.accept() {
+                            Ok((stream, _)) => stream,
+                            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                            Err(e) => {
+                                log::error!("error accepting connection: {}", e);
+                                break;
+                            }
+                        };
+                        let mut connection = Connection::new(
+                            stream,
+                            self.configuration.client_output_buffer_limit(),
+                        );
                         let token = next_token;
                         next_token.0 += 1;
                         poll.registry()
-                            .register(&mut stream, token, Interest::READABLE | Interest::WRITABLE)
+                            .register(&mut connection, token, Interest::READABLE)
                             .unwrap();
-                        connections.insert(token, stream);
-                    }
-                    token => {
-                        let stream = connections.get_mut(&token).unwrap();
-                        if !event.is_readable() || !event.is_writable() {
-                            continue;
-                        }
-
-                        if request_handler.handle_request(stream).is_err() {
-                            poll.registry().deregister(stream).unwrap();
-                            connections.remove(&token);
-                        }
-                    }
+                        connections.insert(token, connection);
+                    },
+                    token => serve(
+                        poll.registry(),
+                        &mut connections,
+                        &mut request_handler,
+                        token,
+                        event.is_readable(),
+                        event.is_writable(),
+                    ),
+                }
+            }
+
+            if Instant::now() >= next_expire_cycle {
+                request_handler.active_expire_cycle(ACTIVE_EXPIRE_TIME_LIMIT);
+                next_expire_cycle = Instant::now() + ACTIVE_EXPIRE_PERIOD;
+            }
+            if let Err(e) = request_handler.handle_timeouts() {
+                log::error!("error answering timed out clients: {}", e);
+            }
+            for (token, reply) in request_handler.take_replies() {
+                let Some(connection) = connections.get_mut(&token) else {
+                    continue;
+                };
+                let result = connection
+                    .write_all(&reply)
+                    .and_then(|_| connection.flush_output(poll.registry(), token));
+                if let Err(e) = &result {
+                    log::debug!("closing client: {}", e);
+                }
+                if result.is_err() || connection.is_closed() || request_handler.has_quit(token) {
+                    close(
+                        poll.registry(),
+                        &mut connections,
+                        &mut request_handler,
+                        token,
+                    );
                 }
             }
         }
     }
 
-    fn replicaof_handshake(&self, addr: &str) -> bool {
+    /// Connects to the master and returns the connection, which then streams the writes to
+    /// replicate, along with the RDB file it sent.
+    fn replicaof_handshake(&self, addr: &str) -> Option<MasterLink> {
         let addr = addr.split_once(' ');
         match addr {
             Some((address, port)) => {
@@ -89,28 +179,28 @@ impl Server {
                 let port = port.parse::<u16>();
                 if port.is_err() {
                     log::error!("Invalid replicaof port format");
-                    return false;
+                    return None;
                 }
                 match handshake(address, port.unwrap(), self.configuration.port()) {
-                    Ok(_) => {
+                    Ok(link) => {
                         log::info!("replicaof handshake successful");
-                        true
+                        Some(link)
                     }
                     Err(e) => {
                         log::error!("replicaof handshake failed: {}", e);
-                        false
+                        None
                     }
                 }
             }
             None => {
                 log::error!("Invalid replicaof configuration format");
-                false
+                None
             }
         }
     }
 
     fn create_storage(&self) -> RedisStorage {
-        let mut storage = RedisStorage::default();
+        let mut storage = RedisStorage::new(self.configuration.databases());
         if let Some(path) = self.configuration.get_db_file_path() {
             let result = storage.restore_database(&path);
             if let Err(e) = result {
@@ -121,7 +211,66 @@ impl Server {
     }
 }
 
-fn handshake(address: &str, master_port: u16, slave_port: u16) -> std::io::Result<()> {
+/// Handles a readiness event of a client, or of the master when the token is `MASTER_TOKEN`:
+/// sends pending output, runs what was received, then sends the replies.
+fn serve(
+    registry: &Registry,
+    connections: &mut HashMap<Token, Connection>,
+    request_handler: &mut RequestHandler,
+    token: Token,
+    readable: bool,
+    writable: bool,
+) {
+    let Some(connection) = connections.get_mut(&token) else {
+        return;
+    };
+
+    let mut result = Ok(());
+    if writable {
+        result = connection.send_pending().map_err(|e| e.to_string());
+    }
+    if result.is_ok() && readable {
+        result = if token == MASTER_TOKEN {
+            request_handler.handle_master(token, connection)
+        } else {
+            request_handler.handle_requests(token, connection)
+        }
+        .map_err(|e| e.to_string());
+    }
+    if result.is_ok() {
+        result = connection
+            .flush_output(registry, token)
+            .map_err(|e| e.to_string());
+    }
+
+    if let Err(e) = &result {
+        log::debug!("closing client: {}", e);
+        // Best effort to deliver the error that explains why, such as a protocol error.
+        if let Err(e) = connection.send_pending() {
+            log::debug!("cannot send the last replies: {}", e);
+        }
+    }
+    if result.is_err() || connection.is_closed() || request_handler.has_quit(token) {
+        if token == MASTER_TOKEN {
+            log::error!("lost the connection to the master");
+        }
+        close(registry, connections, request_handler, token);
+    }
+}
+
+fn close(
+    registry: &Registry,
+    connections: &mut HashMap<Token, Connection>,
+    request_handler: &mut RequestHandler,
+    token: Token,
+) {
+    if let Some(mut connection) = connections.remove(&token) {
+        registry.deregister(&mut connection).unwrap();
+    }
+    request_handler.disconnect(token);
+}
+
+fn handshake(address: &str, master_port: u16, slave_port: u16) -> std::io::Result<MasterLink> {
     log::debug!(
         "handshake: connecting to master at {}:{}",
         address,
@@ -134,7 +283,7 @@ fn handshake(address: &str, master_port: u16, slave_port: u16) -> std::io::Resul
     client.send(&[Some("PING")])?;
     match client.receive() {
         Ok(response) => {
-            if response.len() != 1 || response.first().unwrap().as_str() != "PONG" {
+            if response.len() != 1 || response.first().unwrap().as_slice() != b"PONG" {
                 log::error!("handshake failed: invalid response");
                 return Err(std::io::Error::other("handshake failed"));
             }
@@ -161,15 +310,52 @@ fn handshake(address: &str, master_port: u16, slave_port: u16) -> std::io::Resul
 
     log::info!("handshake send: PSYNC ? -1",);
     client.send(&[Some("PSYNC"), Some("?"), Some("-1")])?;
-    let _ = client.receive();
+    let offset = match client.receive() {
+        Ok(response) if response.len() == 1 && response[0].starts_with(b"FULLRESYNC ") => {
+            let response = String::from_utf8_lossy(&response[0]).into_owned();
+            log::info!("handshake received: {}", response);
+            match response
+                .rsplit(' ')
+                .next()
+                .and_then(|x| x.parse::<u64>().ok())
+            {
+                Some(offset) => offset,
+                None => {
+                    log::error!("handshake failed: invalid offset");
+                    return Err(std::io::Error::other("handshake failed"));
+                }
+            }
+        }
+        Ok(_) => {
+            log::error!("handshake failed: invalid response");
+            return Err(std::io::Error::other("handshake failed"));
+        }
+        Err(e) => {
+            log::error!("handshake failed: {}", e);
+            return Err(std::io::Error::other("handshake failed"));
+        }
+    };
 
-    Ok(())
+    match client.receive_rdb() {
+        Ok(rdb) => {
+            log::info!("handshake received: RDB file of {} bytes", rdb.len());
+            Ok(MasterLink {
+                connection: client.into_connection()?,
+                rdb,
+                offset,
+            })
+        }
+        Err(e) => {
+            log::error!("handshake failed: {}", e);
+            Err(std::io::Error::other("handshake failed"))
+        }
+    }
 }
 
 fn receive_replconf_ack(client: &mut TcpClient) -> std::io::Result<()> {
     match client.receive() {
         Ok(response) => {
-            if response.len() != 1 || response.first().unwrap().as_str() != "OK" {
+            if response.len() != 1 || response.first().unwrap().as_slice() != b"OK" {
                 log::error!("handshake failed: invalid response");
                 return Err(std::io::Error::other("handshake failed"));
             }
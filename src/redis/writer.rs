@@ -1,27 +1,60 @@
 use crate::redis::core::WriteResp;
-use mio::net::TcpStream;
 use std::io::{Error, Write};
 
-impl WriteResp for TcpStream {
+impl<T: Write> WriteResp for T {
     fn write_simple_string(&mut self, message: impl AsRef<str>) -> Result<(), Error> {
         self.write_all(format!("+{}\r\n", message.as_ref()).as_bytes())
     }
     fn write_error(&mut self, message: impl AsRef<str>) -> Result<(), Error> {
-        self.write_all(format!("-{}\r\n", message.as_ref()).as_bytes())
+        // Errors may quote client arguments, whose line breaks would end the reply early.
+        let message = message.as_ref().replace(['\r', '\n'], " ");
+        self.write_all(format!("-{}\r\n", message).as_bytes())
     }
-    fn write_bulk_sting(&mut self, message: &Option<impl AsRef<str>>) -> Result<(), Error> {
+    fn write_integer(&mut self, value: i64) -> Result<(), Error> {
+        self.write_all(format!(":{}\r\n", value).as_bytes())
+    }
+    fn write_bulk_sting(&mut self, message: &Option<impl AsRef<[u8]>>) -> Result<(), Error> {
         match message {
-            Some(message) => self.write_all(
-                format!("${}\r\n{}\r\n", message.as_ref().len(), message.as_ref()).as_bytes(),
-            ),
+            Some(message) => {
+                let message = message.as_ref();
+                self.write_all(format!("${}\r\n", message.len()).as_bytes())?;
+                self.write_all(message)?;
+                self.write_all(b"\r\n")
+            }
             None => self.write_all(b"$-1\r\n"),
         }
     }
-    fn write_array(&mut self, message: &[Option<impl AsRef<str>>]) -> Result<(), Error> {
-        self.write_all(format!("*{}\r\n", message.len()).as_bytes())?;
+    fn write_array(&mut self, message: &[Option<impl AsRef<[u8]>>]) -> Result<(), Error> {
+        self.write_array_header(message.len())?;
         for message in message {
             self.write_bulk_sting(message)?;
         }
         Ok(())
     }
+    fn write_array_header(&mut self, len: usize) -> Result<(), Error> {
+        self.write_all(format!("*{}\r\n", len).as_bytes())
+    }
+    fn write_null_array(&mut self) -> Result<(), Error> {
+        self.write_all(b"*-1\r\n")
+    }
+    fn write_rdb(&mut self, rdb: &[u8]) -> Result<(), Error> {
+        self.write_all(format!("${}\r\n", rdb.len()).as_bytes())?;
+        self.write_all(rdb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redis::core::WriteResp;
+
+    #[test]
+    fn test_errors_stay_on_one_line() {
+        let mut reply = Vec::new();
+        reply.write_error("Unknown command 'foo\r\n:1'").unwrap();
+        reply.write_error("Unsupported option \n").unwrap();
+        assert_eq!(
+            reply,
+            b"-Unknown command 'foo  :1'\r\n-Unsupported option  \r\n"
+        );
+    }
 }